@@ -0,0 +1,95 @@
+//! Compares `from_str_via_value` (which builds a `Value` tree first) with the
+//! streaming `from_str` path. Run with `cargo bench -p merde --features full`.
+//!
+//! There's no benchmarking framework here, just a warm-up and the best of a few
+//! timed rounds, which is enough to compare the two paths to each other.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use merde::json::{from_str, from_str_via_value};
+use merde::{CowStr, JsonDeserialize, ValueDeserialize};
+
+#[derive(ValueDeserialize, JsonDeserialize)]
+#[allow(dead_code)]
+struct Address<'s> {
+    street: CowStr<'s>,
+    city: CowStr<'s>,
+    zip: u32,
+}
+
+#[derive(ValueDeserialize, JsonDeserialize)]
+#[allow(dead_code)]
+struct User<'s> {
+    id: u64,
+    name: CowStr<'s>,
+    email: CowStr<'s>,
+    active: bool,
+    tags: Vec<CowStr<'s>>,
+    address: Address<'s>,
+    manager_id: Option<u64>,
+}
+
+#[derive(ValueDeserialize, JsonDeserialize)]
+#[allow(dead_code)]
+struct Payload<'s> {
+    users: Vec<User<'s>>,
+}
+
+fn payload(users: usize) -> String {
+    let users: Vec<String> = (0..users)
+        .map(|i| {
+            format!(
+                r#"{{"id":{i},"name":"User {i}","email":"user{i}@example.org","active":{},"tags":["a","b","c"],"address":{{"street":"{i} Main St","city":"Springfield","zip":{}}},"manager_id":{},"ignored":{{"nested":[1,2,3]}}}}"#,
+                i % 2 == 0,
+                10000 + i,
+                if i % 3 == 0 { "null".to_string() } else { (i / 3).to_string() },
+            )
+        })
+        .collect();
+    format!(r#"{{"users":[{}]}}"#, users.join(","))
+}
+
+fn bench(name: &str, input: &str, f: impl Fn(&str)) {
+    const ROUNDS: usize = 5;
+    const MIN_ROUND: Duration = Duration::from_millis(200);
+
+    // warm up, and figure out how many iterations make up a round
+    let mut iters = 1;
+    loop {
+        let start = Instant::now();
+        for _ in 0..iters {
+            f(black_box(input));
+        }
+        if start.elapsed() >= MIN_ROUND {
+            break;
+        }
+        iters *= 2;
+    }
+
+    let best = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iters {
+                f(black_box(input));
+            }
+            start.elapsed() / iters
+        })
+        .min()
+        .unwrap();
+    let mb_per_sec = input.len() as f64 / best.as_secs_f64() / 1e6;
+    println!("{name:<40} {best:>12.2?}/iter {mb_per_sec:>10.1} MB/s");
+}
+
+fn main() {
+    for users in [10, 1000] {
+        let input = payload(users);
+        println!("{users} users, {} bytes", input.len());
+        bench("  from_str_via_value", &input, |s| {
+            black_box(from_str_via_value::<Payload>(s).unwrap());
+        });
+        bench("  from_str (streaming)", &input, |s| {
+            black_box(from_str::<Payload>(s).unwrap());
+        });
+    }
+}
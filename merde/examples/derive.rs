@@ -0,0 +1,59 @@
+use merde::{
+    CowStr, IntoStatic, JsonSerialize, OwnedValueDeserialize, ValueDeserialize, WithLifetime,
+};
+
+#[derive(Debug, PartialEq, ValueDeserialize, JsonSerialize, IntoStatic, WithLifetime)]
+struct Address<'s> {
+    street: CowStr<'s>,
+    city: CowStr<'s>,
+    state: CowStr<'s>,
+    zip: u16,
+}
+
+#[derive(Debug, PartialEq, ValueDeserialize, JsonSerialize, IntoStatic, WithLifetime)]
+struct Person<'s> {
+    name: CowStr<'s>,
+    age: u8,
+    address: Address<'s>,
+}
+
+// No lifetime, and no `_phantom` field needed.
+#[derive(Debug, PartialEq, ValueDeserialize, JsonSerialize, IntoStatic, WithLifetime)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<u32>,
+}
+
+fn deser_and_return<T>(s: String) -> T
+where
+    T: OwnedValueDeserialize,
+{
+    merde_json::owned_from_str_via_value(&s)
+        .map_err(|e| e.to_static())
+        .unwrap()
+}
+
+fn main() {
+    let input = r#"
+        {
+            "name": "John Doe",
+            "age": 42,
+            "address": {
+                "street": "123 Main St",
+                "city": "Anytown",
+                "state": "CA",
+                "zip": 12345
+            }
+        }
+    "#;
+
+    let person: Person = merde_json::from_str_via_value(input).unwrap();
+    println!("{:?}", person);
+
+    let serialized = person.to_json_string();
+    let person2 = deser_and_return::<Person>(serialized);
+    assert_eq!(person, person2);
+
+    let page: Page<u64> = merde_json::from_str_via_value(r#"{"items":[1,2,3]}"#).unwrap();
+    println!("{:?}", page);
+}
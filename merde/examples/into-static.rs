@@ -1,4 +1,4 @@
-use merde_core::IntoStatic;
+use merde::IntoStatic;
 use std::borrow::Cow;
 
 #[allow(dead_code)]
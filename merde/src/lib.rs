@@ -1,13 +1,22 @@
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
-#![doc = include_str!("../README.md")]
+// the README's examples use most features: they're only doc-tested with `full`
+#![cfg_attr(any(not(doctest), feature = "full"), doc = include_str!("../README.md"))]
 
 #[cfg(feature = "json")]
 pub use merde_json as json;
 
+#[cfg(feature = "yaml")]
+pub use merde_yaml as yaml;
+
+#[cfg(feature = "toml")]
+pub use merde_toml as toml;
+
+#[cfg(feature = "msgpack")]
+pub use merde_msgpack as msgpack;
+
 #[cfg(feature = "json")]
-#[allow(unused_imports)]
-use json::JsonSerialize;
+pub use json::{JsonDeserialize, JsonSerialize};
 
 #[cfg(feature = "time")]
 pub use merde_time as time;
@@ -15,6 +24,15 @@ pub use merde_time as time;
 #[cfg(feature = "core")]
 pub use merde_core::*;
 
+#[cfg(feature = "derive")]
+pub use merde_derive::{
+    IntoStatic, JsonDeserialize, JsonSerialize, ValueDeserialize, ValueSerialize, WithLifetime,
+};
+
+// lets code generated by `merde_derive` (which refers to `::merde`) be tested here
+#[cfg(test)]
+extern crate self as merde;
+
 #[doc(hidden)]
 #[cfg(feature = "deserialize")]
 #[macro_export]
@@ -161,10 +179,13 @@ macro_rules! impl_json_serialize {
         #[automatically_derived]
         impl<$lifetime> $crate::json::JsonSerialize for $struct_name<$lifetime> {
             fn json_serialize(&self, serializer: &mut $crate::json::JsonSerializer) {
-                #[allow(unused_imports)]
-                use $crate::MerdeError;
+                $crate::json::JsonSerializeObject::json_serialize_pairs(self, &mut serializer.write_obj());
+            }
+        }
 
-                let mut guard = serializer.write_obj();
+        #[automatically_derived]
+        impl<$lifetime> $crate::json::JsonSerializeObject for $struct_name<$lifetime> {
+            fn json_serialize_pairs(&self, guard: &mut $crate::json::ObjectGuard<'_, '_>) {
                 $(
                     guard.pair(stringify!($field), &self.$field);
                 )+
@@ -176,10 +197,13 @@ macro_rules! impl_json_serialize {
         #[automatically_derived]
         impl $crate::json::JsonSerialize for $struct_name {
             fn json_serialize(&self, serializer: &mut $crate::json::JsonSerializer) {
-                #[allow(unused_imports)]
-                use $crate::MerdeError;
+                $crate::json::JsonSerializeObject::json_serialize_pairs(self, &mut serializer.write_obj());
+            }
+        }
 
-                let mut guard = serializer.write_obj();
+        #[automatically_derived]
+        impl $crate::json::JsonSerializeObject for $struct_name {
+            fn json_serialize_pairs(&self, guard: &mut $crate::json::ObjectGuard<'_, '_>) {
                 $(
                     guard.pair(stringify!($field), &self.$field);
                 )+
@@ -195,6 +219,56 @@ macro_rules! impl_json_serialize {
     ($($tt:tt)*) => {};
 }
 
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "core")]
+macro_rules! impl_value_serialize {
+    ($struct_name:ident < $lifetime:lifetime > { $($field:ident),+ }) => {
+        #[automatically_derived]
+        impl<$lifetime> $crate::ValueSerialize for $struct_name<$lifetime> {
+            fn to_value(&self) -> $crate::Value<'_> {
+                $crate::Value::Map($crate::ValueSerializeMap::to_map(self))
+            }
+        }
+
+        #[automatically_derived]
+        impl<$lifetime> $crate::ValueSerializeMap for $struct_name<$lifetime> {
+            fn to_map(&self) -> $crate::Map<'_> {
+                $crate::Map::new()
+                    $(
+                        .with(stringify!($field), $crate::ValueSerialize::to_value(&self.$field))
+                    )+
+            }
+        }
+    };
+
+    ($struct_name:ident { $($field:ident),+ }) => {
+        #[automatically_derived]
+        impl $crate::ValueSerialize for $struct_name {
+            fn to_value(&self) -> $crate::Value<'_> {
+                $crate::Value::Map($crate::ValueSerializeMap::to_map(self))
+            }
+        }
+
+        #[automatically_derived]
+        impl $crate::ValueSerializeMap for $struct_name {
+            fn to_map(&self) -> $crate::Map<'_> {
+                $crate::Map::new()
+                    $(
+                        .with(stringify!($field), $crate::ValueSerialize::to_value(&self.$field))
+                    )+
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "core"))]
+macro_rules! impl_value_serialize {
+    ($($tt:tt)*) => {};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! impl_trait {
@@ -207,6 +281,15 @@ macro_rules! impl_trait {
         $crate::impl_json_serialize!($struct_name { $($field),+ });
     };
 
+    // borrowed
+    (@impl ValueSerialize, $struct_name:ident <$lifetime:lifetime> { $($field:ident),+ }) => {
+        $crate::impl_value_serialize!($struct_name <$lifetime> { $($field),+ });
+    };
+    // owned
+    (@impl ValueSerialize, $struct_name:ident { $($field:ident),+ }) => {
+        $crate::impl_value_serialize!($struct_name { $($field),+ });
+    };
+
     // with lifetime param
     (@impl ValueDeserialize, $struct_name:ident <$lifetime:lifetime> { $($field:ident),+ }) => {
         $crate::impl_value_deserialize!($struct_name <$lifetime> { $($field),+ });
@@ -224,32 +307,37 @@ macro_rules! impl_trait {
 /// Derives the specified traits for a struct.
 ///
 /// This macro can be used to generate implementations of [`JsonSerialize`], [`ValueDeserialize`],
-/// and [`IntoStatic`] traits for a given struct.
+/// [`ValueSerialize`] and [`IntoStatic`] traits for a given struct.
 ///
 /// # Usage
 ///
-/// ```rust
-/// use merde::ValueDeserialize;
-/// use merde::CowStr;
-/// use merde::json::JsonSerialize;
-///
-/// #[derive(Debug, PartialEq)]
-/// struct MyStruct<'s> {
-///     field1: CowStr<'s>,
-///     field2: i32,
-///     field3: bool,
-/// }
-///
-/// merde::derive! {
-///     impl (JsonSerialize, ValueDeserialize) for MyStruct<'s> {
-///         field1,
-///         field2,
-///         field3
-///     }
-/// }
-/// ```
+#[cfg_attr(
+    any(not(doctest), all(feature = "core", feature = "json")),
+    doc = r#"
+```rust
+use merde::{ValueDeserialize, ValueSerialize};
+use merde::CowStr;
+use merde::json::JsonSerialize;
+
+#[derive(Debug, PartialEq)]
+struct MyStruct<'s> {
+    field1: CowStr<'s>,
+    field2: i32,
+    field3: bool,
+}
+
+merde::derive! {
+    impl (JsonSerialize, ValueDeserialize, ValueSerialize) for MyStruct<'s> {
+        field1,
+        field2,
+        field3
+    }
+}
+```
+"#
+)]
 ///
-/// In this example, all three traits are derived, of course you can omit the ones you don't need.
+/// In this example, all four traits are derived, of course you can omit the ones you don't need.
 ///
 /// The struct must have exactly one lifetime parameter. Additionally, even if there are no
 /// borrowed fields, the struct must include a `_phantom` field of type `PhantomData<&'a ()>`,
@@ -258,6 +346,10 @@ macro_rules! impl_trait {
 /// Implementing other variants (no lifetimes, multiple lifetimes, etc.) with declarative macros
 /// would be too complicated. At this point we'd want a whole parser / compiler / code generator
 /// for this — or a proc macro, see [serde](https://serde.rs/)'s serde_derive.
+///
+/// If you can afford a proc macro, enable the `derive` feature and use
+/// `#[derive(ValueDeserialize, ValueSerialize, JsonSerialize, IntoStatic, WithLifetime)]` instead:
+/// it reads the struct definition directly, and supports generic type parameters.
 #[macro_export]
 macro_rules! derive {
     // cow variants
@@ -287,12 +379,157 @@ macro_rules! derive {
     (@step1 { } $struct_name:ident $fields:tt) => {};
 }
 
-#[cfg(test)]
-#[cfg(feature = "json")]
+#[cfg(all(test, feature = "json", feature = "deserialize"))]
 mod json_tests {
     use super::*;
     use crate::json::{from_str_via_value, JsonSerialize};
 
+    #[test]
+    fn test_trailing_data() {
+        use crate::json::from_str_via_value_prefix;
+
+        for input in [r#"{"a":1} garbage"#, "[1][2]"] {
+            let err = from_str_via_value::<Value>(input).unwrap_err();
+            assert!(
+                err.to_string().contains("trailing characters"),
+                "unexpected error: {err}"
+            );
+        }
+
+        let input = "[1][2]";
+        let (first, consumed) = from_str_via_value_prefix::<Vec<u8>>(input).unwrap();
+        assert_eq!((first, consumed), (vec![1], 3));
+        let (second, _) = from_str_via_value_prefix::<Vec<u8>>(&input[consumed..]).unwrap();
+        assert_eq!(second, vec![2]);
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        use crate::json::{
+            from_str_via_value_lenient, try_to_string, JsonSerializer, MerdeJsonError,
+            NonFiniteFloats,
+        };
+
+        let serialize = |value: &Value, policy| {
+            let mut s = JsonSerializer::new().non_finite_floats(policy);
+            value.json_serialize(&mut s);
+            String::from_utf8(s.into_inner()).unwrap()
+        };
+        let values = Array::new()
+            .with(Value::Float(f64::NAN))
+            .with(Value::Float(f64::INFINITY))
+            .with(Value::Float(f64::NEG_INFINITY))
+            .with(Value::Float(1.5));
+        let values = Value::Array(values);
+
+        let json = serialize(&values, NonFiniteFloats::Null);
+        assert_eq!(json, "[null,null,null,1.5]");
+        assert_eq!(values.to_json_string(), json);
+        let back: Value = from_str_via_value(&json).unwrap();
+        assert_eq!(
+            back,
+            Value::Array(
+                Array::new()
+                    .with(Value::Null)
+                    .with(Value::Null)
+                    .with(Value::Null)
+                    .with(Value::Float(1.5))
+            )
+        );
+
+        let json = serialize(&values, NonFiniteFloats::Tokens);
+        assert_eq!(json, "[NaN,Infinity,-Infinity,1.5]");
+        // strict by default: these tokens aren't JSON
+        assert!(from_str_via_value::<Value>("NaN").is_err());
+        assert!(from_str_via_value::<Value>(&json).is_err());
+        let back: Vec<Value> = from_str_via_value_lenient(&json).unwrap();
+        assert!(matches!(back[0], Value::Float(f) if f.is_nan()));
+        assert_eq!(back[1..], values.as_array().unwrap()[1..]);
+
+        let mut s = JsonSerializer::new().non_finite_floats(NonFiniteFloats::Error);
+        values.json_serialize(&mut s);
+        let err = s.try_into_inner().unwrap_err();
+        assert!(matches!(err, MerdeJsonError::NonFiniteFloat(f) if f.is_nan()));
+        let err = try_to_string(&values).unwrap_err();
+        assert!(matches!(err, MerdeJsonError::NonFiniteFloat(f) if f.is_nan()));
+        assert_eq!(err.to_string(), "Cannot serialize NaN as JSON");
+        assert_eq!(try_to_string(&Value::Float(1.5)).unwrap(), "1.5");
+    }
+
+    #[test]
+    fn test_pretty() {
+        use crate::json::{to_string_pretty, to_writer_pretty, JsonSerializer, PrettyConfig};
+
+        let value = Array::new()
+            .with(Map::new().with("list", Array::new().with(1).with("two")))
+            .with(Map::new())
+            .with(Array::new())
+            .with(Map::new().with("nested", Map::new().with("deep", Value::Null)));
+        let expected = r#"[
+  {
+    "list": [
+      1,
+      "two"
+    ]
+  },
+  {},
+  [],
+  {
+    "nested": {
+      "deep": null
+    }
+  }
+]"#;
+        assert_eq!(to_string_pretty(&value), expected);
+        assert_eq!(from_str_via_value::<Array>(expected).unwrap(), value);
+
+        let mut out = Vec::new();
+        to_writer_pretty(&mut out, &value).unwrap();
+        assert_eq!(out, expected.as_bytes());
+
+        let config = PrettyConfig::default()
+            .indent("\t")
+            .inline_arrays(2)
+            .trailing_newline(true);
+        let mut s = JsonSerializer::pretty(config);
+        Map::new()
+            .with("short", Array::new().with(1).with("a, b"))
+            .with("long", Array::new().with(1).with(2).with(3))
+            .with("nested", Array::new().with(Array::new().with(1)))
+            .json_serialize(&mut s);
+        let out = String::from_utf8(s.into_inner()).unwrap();
+        let out: Vec<&str> = out.split('\n').collect();
+        // keys come out in whatever order the map has them in
+        for line in [
+            "\t\"short\": [1, \"a, b\"]",
+            "\t\"long\": [",
+            "\t\t3",
+            "\t\"nested\": [",
+            "\t\t[1]",
+        ] {
+            assert!(
+                out.iter().any(|l| l.trim_end_matches(',') == line),
+                "{line:?} not in {out:#?}"
+            );
+        }
+        assert_eq!(out.last(), Some(&""));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_preserve_order() {
+        let input = r#"{"zebra":1,"apple":{"y":[],"x":null},"mango":"m"}"#;
+        let value: Value = from_str_via_value(input).unwrap();
+        assert_eq!(value.to_json_string(), input);
+
+        let mut map = value.into_map().unwrap();
+        let _: u8 = map.must_remove("zebra").unwrap();
+        assert_eq!(
+            map.to_json_string(),
+            r#"{"apple":{"y":[],"x":null},"mango":"m"}"#
+        );
+    }
+
     #[test]
     fn test_complex_structs() {
         use std::borrow::Cow;
@@ -305,7 +542,7 @@ mod json_tests {
         }
 
         derive! {
-            impl (JsonSerialize, ValueDeserialize) for SecondStruct<'s> {
+            impl (JsonSerialize, ValueDeserialize, ValueSerialize) for SecondStruct<'s> {
                 string_field,
                 int_field
             }
@@ -331,7 +568,7 @@ mod json_tests {
         }
 
         derive! {
-            impl (JsonSerialize, ValueDeserialize) for ComplexStruct<'s> {
+            impl (JsonSerialize, ValueDeserialize, ValueSerialize) for ComplexStruct<'s> {
                 string_field,
                 u8_field,
                 u16_field,
@@ -378,6 +615,674 @@ mod json_tests {
         let deserialized: ComplexStruct = from_str_via_value(&serialized).unwrap();
 
         assert_eq!(original, deserialized);
+
+        let value: Value = from_str_via_value(&serialized).unwrap();
+        assert_eq!(crate::to_value(&original), value);
+    }
+}
+
+#[cfg(all(test, feature = "derive", feature = "json", feature = "deserialize"))]
+mod derive_tests {
+    use std::marker::PhantomData;
+
+    use crate::json::{from_str_via_value, owned_from_str_via_value};
+    use crate::{
+        CowStr, IntoStatic, JsonDeserialize, JsonSerialize, MerdeError, OwnedValueDeserialize,
+        ValueDeserialize, ValueSerialize, WithLifetime,
+    };
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    struct Borrowed<'s> {
+        name: CowStr<'s>,
+        age: u8,
+        tags: Vec<CowStr<'s>>,
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    struct Owned {
+        x: i32,
+        y: Option<i32>,
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    struct Generic<'s, T> {
+        label: CowStr<'s>,
+        items: Vec<T>,
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    struct Nested<'s> {
+        owned: Owned,
+        inner: Generic<'s, Borrowed<'s>>,
+    }
+
+    #[test]
+    fn test_borrowed_roundtrip() {
+        let input = r#"{"name":"Jane","age":42,"tags":["a","b"]}"#;
+        let b: Borrowed = from_str_via_value(input).unwrap();
+        assert!(matches!(b.name, CowStr::Borrowed(_)));
+        assert_eq!(b.to_json_string(), input);
+
+        let b: Borrowed<'static> = b.into_static();
+        assert_eq!(b.tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_owned_without_phantom() {
+        let o: Owned = from_str_via_value(r#"{"x":1}"#).unwrap();
+        assert_eq!(o, Owned { x: 1, y: None });
+        assert_eq!(o.to_json_string(), r#"{"x":1,"y":null}"#);
+
+        let _: PhantomData<<Owned as WithLifetime<'static>>::Lifetimed> = PhantomData;
+    }
+
+    #[test]
+    fn test_generic_and_nested() {
+        let input = r#"{
+            "owned": {"x": 3, "y": 4},
+            "inner": {
+                "label": "people",
+                "items": [{"name":"Jane","age":42,"tags":[]}]
+            }
+        }"#;
+        let n: Nested = from_str_via_value(input).unwrap();
+        assert_eq!(n.inner.items[0].name, "Jane");
+
+        let serialized = n.to_json_string();
+        let roundtripped: Nested = from_str_via_value(&serialized).unwrap();
+        assert_eq!(n, roundtripped);
+        let value: crate::Value = from_str_via_value(&serialized).unwrap();
+        assert_eq!(n.to_value(), value);
+
+        let g: Generic<u32> = from_str_via_value(r#"{"label":"nums","items":[1,2]}"#).unwrap();
+        assert_eq!(g.items, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_owned_value_deserialize() {
+        fn parse(s: String) -> Nested<'static> {
+            owned_from_str_via_value(&s)
+                .map_err(|e| e.to_static())
+                .unwrap()
+        }
+
+        let n = parse(r#"{"owned":{"x":1},"inner":{"label":"l","items":[]}}"#.to_owned());
+        assert_eq!(n.inner.label, "l");
+    }
+
+    #[test]
+    fn test_missing_property() {
+        let err = from_str_via_value::<Borrowed>(r#"{"name":"Jane","tags":[]}"#).unwrap_err();
+        match err {
+            crate::json::MerdeJsonError::MerdeError(e) => {
+                assert!(
+                    matches!(e.without_path(), MerdeError::MissingProperty(prop) if *prop == "age")
+                );
+                assert_eq!(e.path().unwrap().to_string(), "$.age");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[derive(
+        Debug, PartialEq, ValueDeserialize, ValueSerialize, JsonSerialize, IntoStatic, WithLifetime,
+    )]
+    enum External<'s> {
+        Unit,
+        Newtype(CowStr<'s>),
+        Tuple(i32, CowStr<'s>),
+        Struct { a: i32, b: Option<CowStr<'s>> },
+    }
+
+    #[derive(
+        Debug, PartialEq, ValueDeserialize, ValueSerialize, JsonSerialize, IntoStatic, WithLifetime,
+    )]
+    #[merde(tag = "type")]
+    enum Internal<'s> {
+        Unit,
+        Newtype(Borrowed<'s>),
+        Struct { a: i32 },
+    }
+
+    #[derive(
+        Debug, PartialEq, ValueDeserialize, ValueSerialize, JsonSerialize, IntoStatic, WithLifetime,
+    )]
+    #[merde(tag = "t", content = "c")]
+    enum Adjacent<T> {
+        Unit,
+        Newtype(T),
+        Tuple(T, T),
+        Struct { a: T },
+    }
+
+    #[derive(
+        Debug, PartialEq, ValueDeserialize, ValueSerialize, JsonSerialize, IntoStatic, WithLifetime,
+    )]
+    #[merde(untagged)]
+    enum Untagged<'s> {
+        Unit,
+        Struct { a: i32 },
+        Tuple(i32, i32),
+        Newtype(CowStr<'s>),
+        Fallback(crate::Value<'s>),
+    }
+
+    fn roundtrip<T>(value: T, expected: &str)
+    where
+        T: JsonSerialize + ValueSerialize + OwnedValueDeserialize + PartialEq + std::fmt::Debug,
+    {
+        let serialized = value.to_json_string();
+        assert_eq!(serialized, expected);
+        assert_eq!(owned_from_str_via_value::<T>(&serialized).unwrap(), value);
+
+        let parsed: crate::Value = from_str_via_value(&serialized).unwrap();
+        assert_eq!(T::owned_from_value_ref(Some(&parsed)).unwrap(), value);
+        assert_eq!(value.to_value(), parsed);
+    }
+
+    #[test]
+    fn test_externally_tagged() {
+        roundtrip(External::Unit, r#""Unit""#);
+        roundtrip(External::Newtype("hi".into()), r#"{"Newtype":"hi"}"#);
+        roundtrip(External::Tuple(1, "x".into()), r#"{"Tuple":[1,"x"]}"#);
+        roundtrip(
+            External::Struct { a: 1, b: None },
+            r#"{"Struct":{"a":1,"b":null}}"#,
+        );
+
+        let unit: External = from_str_via_value(r#"{"Unit":null}"#).unwrap();
+        assert_eq!(unit, External::Unit);
+    }
+
+    #[test]
+    fn test_internally_tagged() {
+        roundtrip(Internal::Unit, r#"{"type":"Unit"}"#);
+        roundtrip(Internal::Struct { a: 3 }, r#"{"type":"Struct","a":3}"#);
+        roundtrip(
+            Internal::Newtype(Borrowed {
+                name: "Jane".into(),
+                age: 42,
+                tags: vec![],
+            }),
+            r#"{"type":"Newtype","name":"Jane","age":42,"tags":[]}"#,
+        );
+    }
+
+    #[test]
+    fn test_internally_tagged_map_content() {
+        use crate::{value, Map, Value};
+        use std::collections::HashMap;
+
+        #[derive(JsonSerialize, ValueSerialize)]
+        #[merde(tag = "type")]
+        enum Tagged<'s> {
+            Map(Map<'s>),
+            Counts(HashMap<&'static str, u32>),
+        }
+
+        let tagged = Tagged::Map(Map::new().with("a", Value::Int(1)));
+        assert_eq!(tagged.to_json_string(), r#"{"type":"Map","a":1}"#);
+        assert_eq!(tagged.to_value(), value!({ "type": "Map", "a": 1 }));
+
+        let tagged = Tagged::Counts(HashMap::from([("b", 2)]));
+        assert_eq!(tagged.to_json_string(), r#"{"type":"Counts","b":2}"#);
+        assert_eq!(tagged.to_value(), value!({ "type": "Counts", "b": 2 }));
+    }
+
+    #[test]
+    fn test_adjacently_tagged() {
+        roundtrip(Adjacent::<u8>::Unit, r#"{"t":"Unit"}"#);
+        roundtrip(Adjacent::Newtype(1u8), r#"{"t":"Newtype","c":1}"#);
+        roundtrip(Adjacent::Tuple(1u8, 2), r#"{"t":"Tuple","c":[1,2]}"#);
+        roundtrip(Adjacent::Struct { a: 1u8 }, r#"{"t":"Struct","c":{"a":1}}"#);
+    }
+
+    #[test]
+    fn test_untagged() {
+        roundtrip(Untagged::Unit, "null");
+        roundtrip(Untagged::Struct { a: 1 }, r#"{"a":1}"#);
+        roundtrip(Untagged::Tuple(1, 2), "[1,2]");
+        roundtrip(Untagged::Newtype("hi".into()), r#""hi""#);
+
+        // first match wins: this is not a valid `Tuple`, so it falls through
+        let v: Untagged = from_str_via_value("[1,2,3]").unwrap();
+        assert!(matches!(v, Untagged::Fallback(_)));
+    }
+
+    #[test]
+    fn test_enum_errors_mention_variant() {
+        use crate::json::MerdeJsonError;
+
+        let err = from_str_via_value::<External>(r#"{"Struct":{"b":"x"}}"#).unwrap_err();
+        match err {
+            MerdeJsonError::MerdeError(MerdeError::VariantError { variant, error }) => {
+                assert_eq!(variant, "Struct");
+                assert!(
+                    matches!(error.without_path(), MerdeError::MissingProperty(p) if *p == "a")
+                );
+                assert_eq!(error.path().unwrap().to_string(), "$.Struct.a");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+
+        let err = from_str_via_value::<Internal>(r#"{"type":"Nope"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Unknown variant: Nope");
+
+        let err = from_str_via_value::<Adjacent<u8>>(r#"{"t":"Newtype"}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: In variant Newtype: Missing property: c (at $.c)"
+        );
+
+        #[derive(Debug, ValueDeserialize)]
+        #[merde(untagged)]
+        #[allow(dead_code)]
+        enum Strict {
+            A(u8),
+            B { b: bool },
+        }
+        let err = from_str_via_value::<Strict>(r#""x""#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: No variant matched: tried A (Expected Int, found String); tried B (Expected Map, found String)"
+        );
+    }
+
+    fn default_retries() -> u32 {
+        3
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    #[merde(rename_all = "camelCase")]
+    struct Renamed<'s> {
+        #[merde(rename = "type")]
+        kind: CowStr<'s>,
+        user_id: u64,
+        #[merde(alias = "display_name", alias = "nick")]
+        display_name: CowStr<'s>,
+        #[merde(default)]
+        tags: Vec<CowStr<'s>>,
+        #[merde(default = "default_retries")]
+        max_retries: u32,
+        #[merde(default)]
+        owned: Option<Owned>,
+        #[merde(skip)]
+        cache: Option<u64>,
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    #[merde(tag = "kind", rename_all = "snake_case")]
+    enum Event {
+        UserCreated {
+            user_id: u64,
+        },
+        #[merde(rename = "bye", alias = "logout")]
+        LoggedOut,
+        #[merde(rename_all = "kebab-case")]
+        SettingChanged {
+            setting_name: u64,
+            #[merde(skip)]
+            previous: Option<u64>,
+        },
+    }
+
+    #[derive(
+        Debug,
+        PartialEq,
+        ValueDeserialize,
+        ValueSerialize,
+        JsonDeserialize,
+        JsonSerialize,
+        IntoStatic,
+        WithLifetime,
+    )]
+    #[merde(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct Raw {
+        r#type: u8,
+        #[merde(rename = "Else")]
+        r#else: u8,
+    }
+
+    #[test]
+    fn test_field_attributes() {
+        let input = r#"{"type":"admin","userId":7,"nick":"jd","cache":42}"#;
+        let r: Renamed = from_str_via_value(input).unwrap();
+        assert_eq!(
+            r,
+            Renamed {
+                kind: "admin".into(),
+                user_id: 7,
+                display_name: "jd".into(),
+                tags: vec![],
+                max_retries: 3,
+                owned: None,
+                cache: None,
+            }
+        );
+        assert_eq!(
+            r.to_json_string(),
+            r#"{"type":"admin","userId":7,"displayName":"jd","tags":[],"maxRetries":3,"owned":null}"#
+        );
+        assert_eq!(
+            crate::to_value(&r),
+            from_str_via_value::<crate::Value>(&r.to_json_string()).unwrap()
+        );
+
+        // the main key wins over aliases
+        let r = owned_from_str_via_value::<Renamed>(
+            r#"{"type":"a","userId":1,"displayName":"main","nick":"alias"}"#,
+        )
+        .unwrap();
+        assert_eq!(r.display_name, "main");
+
+        // renamed fields are no longer found under their Rust name
+        let err =
+            from_str_via_value::<Renamed>(r#"{"kind":"a","user_id":1,"nick":"x"}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: Missing property: type (at $.type)"
+        );
+
+        // defaults only kick in when the key is missing, not to paper over errors
+        let err =
+            from_str_via_value::<Renamed>(r#"{"type":"a","userId":1,"nick":"x","owned":{"y":1}}"#)
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: Missing property: x (at $.owned.x)"
+        );
+
+        let raw = Raw {
+            r#type: 1,
+            r#else: 2,
+        };
+        let s = raw.to_json_string();
+        assert_eq!(s, r#"{"TYPE":1,"Else":2}"#);
+        assert_eq!(owned_from_str_via_value::<Raw>(&s).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_variant_attributes() {
+        roundtrip(
+            Event::UserCreated { user_id: 1 },
+            r#"{"kind":"user_created","user_id":1}"#,
+        );
+        roundtrip(Event::LoggedOut, r#"{"kind":"bye"}"#);
+        roundtrip(
+            Event::SettingChanged {
+                setting_name: 4,
+                previous: None,
+            },
+            r#"{"kind":"setting_changed","setting-name":4}"#,
+        );
+
+        let e = owned_from_str_via_value::<Event>(r#"{"kind":"logout"}"#).unwrap();
+        assert_eq!(e, Event::LoggedOut);
+
+        let err = from_str_via_value::<Event>(r#"{"kind":"LoggedOut"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Unknown variant: LoggedOut");
+    }
+
+    #[derive(Debug, PartialEq, ValueDeserialize, JsonDeserialize, IntoStatic, WithLifetime)]
+    #[merde(deny_unknown_fields)]
+    struct Strict {
+        #[merde(alias = "b")]
+        a: u8,
+        #[merde(skip)]
+        skipped: u8,
+    }
+
+    #[derive(Debug, PartialEq, ValueDeserialize, IntoStatic, WithLifetime)]
+    #[merde(tag = "type", deny_unknown_fields)]
+    enum StrictEvent {
+        Ping,
+        Move { x: i32 },
+    }
+
+    #[test]
+    fn test_deny_unknown_fields() {
+        let s: Strict = from_str_via_value(r#"{"b":1}"#).unwrap();
+        assert_eq!(s, Strict { a: 1, skipped: 0 });
+
+        for input in [
+            r#"{"a":1,"zz":2,"skipped":3}"#,
+            r#"{"zz":2,"a":1,"skipped":3}"#,
+        ] {
+            let err = from_str_via_value::<Strict>(input).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Merde Error: Unknown property: skipped, zz"
+            );
+            let err = owned_from_str_via_value::<Strict>(input).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Merde Error: Unknown property: skipped, zz"
+            );
+        }
+
+        let e: StrictEvent = from_str_via_value(r#"{"type":"Move","x":1}"#).unwrap();
+        assert_eq!(e, StrictEvent::Move { x: 1 });
+        let err = from_str_via_value::<StrictEvent>(r#"{"type":"Ping","x":1}"#).unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Unknown property: x");
+        let err =
+            owned_from_str_via_value::<StrictEvent>(r#"{"type":"Move","x":1,"y":2}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: In variant Move: Unknown property: y"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_options() {
+        use crate::DeserializeOptions;
+
+        let input = r#"{"x":1,"z":3}"#;
+        assert!(from_str_via_value::<Owned>(input).is_ok());
+
+        let strict = DeserializeOptions::default().deny_unknown_fields(true);
+        let err = strict
+            .scope(|| owned_from_str_via_value::<Owned>(input))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Unknown property: z");
+
+        // nested types are checked too
+        let err = strict
+            .scope(|| {
+                from_str_via_value::<Nested>(
+                    r#"{"owned":{"x":1,"extra":true},"inner":{"label":"l","items":[]}}"#,
+                )
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: Unknown property: extra (at $.owned)"
+        );
+
+        // and the previous options are back once the scope ends
+        assert!(from_str_via_value::<Owned>(input).is_ok());
+    }
+
+    #[derive(Debug, PartialEq, ValueDeserialize, JsonDeserialize, IntoStatic, WithLifetime)]
+    #[allow(dead_code)]
+    struct Address {
+        zip: u32,
+    }
+
+    #[derive(Debug, PartialEq, ValueDeserialize, JsonDeserialize, IntoStatic, WithLifetime)]
+    #[allow(dead_code)]
+    struct User {
+        address: Address,
+        scores: std::collections::HashMap<String, (u8, bool)>,
+    }
+
+    #[derive(Debug, PartialEq, ValueDeserialize, JsonDeserialize, IntoStatic, WithLifetime)]
+    #[allow(dead_code)]
+    struct Users {
+        users: Vec<User>,
+    }
+
+    #[test]
+    fn test_error_paths() {
+        let user = r#"{"address":{"zip":1},"scores":{}}"#;
+        let bad_user = r#"{"address":{},"scores":{}}"#;
+        let input = format!(r#"{{"users":[{user},{user},{user},{bad_user}]}}"#);
+        for err in [
+            from_str_via_value::<Users>(&input).unwrap_err(),
+            owned_from_str_via_value::<Users>(&input).unwrap_err(),
+        ] {
+            assert_eq!(
+                err.to_string(),
+                "Merde Error: Missing property: zip (at $.users[3].address.zip)"
+            );
+        }
+
+        let input = r#"{"users":[{"address":{"zip":1},"scores":{"my key":[1,"yes"]}}]}"#;
+        let err = from_str_via_value::<Users>(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Merde Error: Expected Bool, found String (at $.users[0].scores["my key"][1])"#
+        );
+
+        let err = from_str_via_value::<External>(r#"{"Tuple":[1,2]}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: In variant Tuple: Expected String, found Int (at $.Tuple[1])"
+        );
+    }
+
+    #[test]
+    fn test_json_deserialize_matches_via_value() {
+        use crate::json::{from_str, owned_from_str};
+
+        fn check<'s, T>(input: &'s str)
+        where
+            T: JsonDeserialize<'s> + ValueDeserialize<'s> + PartialEq + std::fmt::Debug,
+        {
+            match (from_str::<T>(input), from_str_via_value::<T>(input)) {
+                (Ok(streamed), Ok(via_value)) => assert_eq!(streamed, via_value),
+                (Err(streamed), Err(via_value)) => {
+                    assert_eq!(streamed.to_string(), via_value.to_string())
+                }
+                (streamed, via_value) => panic!("{streamed:?} vs {via_value:?}"),
+            }
+        }
+
+        check::<Nested>(
+            r#"{"inner":{"label":"l","items":[{"name":"a","age":1,"tags":[]}]},"owned":{"x":1}}"#,
+        );
+        check::<Nested>(
+            r#"{"owned":{"x":1,"y":null,"z":[{"deep":[]}]},"inner":{"label":"l","items":[]}}"#,
+        );
+        check::<Nested>(
+            r#"{"owned":{"x":1},"inner":{"label":"l","items":[{"name":"a","age":"x"}]}}"#,
+        );
+        check::<Renamed>(r#"{"type":"admin","userId":7,"nick":"jd","cache":42}"#);
+        check::<Renamed>(r#"{"nick":"alias","type":"a","userId":1,"displayName":"main"}"#);
+        check::<Renamed>(r#"{"type":"a","userId":1,"nick":"x","owned":{"y":1}}"#);
+        check::<Renamed>(r#"{"kind":"a","user_id":1,"nick":"x"}"#);
+        check::<Raw>(r#"{"TYPE":1,"Else":2}"#);
+        check::<Strict>(r#"{"b":1}"#);
+        check::<Strict>(r#"{"zz":2,"a":1,"skipped":3}"#);
+        check::<Event>(r#"{"kind":"setting_changed","setting-name":4}"#);
+        check::<Event>(r#"{"kind":"LoggedOut"}"#);
+        check::<Users>(r#"{"users":[{"address":{"zip":1},"scores":{"my key":[1,"yes"]}}]}"#);
+        check::<Users>(r#"{"users":[{"address":{},"scores":{}}]}"#);
+
+        let b: Borrowed = from_str(r#"{"name":"Jane","age":42,"tags":["a"]}"#).unwrap();
+        assert!(matches!(b.name, CowStr::Borrowed(_)));
+        let o: Owned = owned_from_str(r#"{"x":1}"#).unwrap();
+        assert_eq!(o, Owned { x: 1, y: None });
+
+        let strict = crate::DeserializeOptions::default().deny_unknown_fields(true);
+        let err = strict
+            .scope(|| from_str::<Owned>(r#"{"x":1,"z":3}"#))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Unknown property: z");
+
+        let err = from_str::<Owned>(r#"{"x":1} {}"#).unwrap_err();
+        assert!(err.to_string().contains("trailing characters"), "{err}");
+    }
+
+    #[test]
+    fn test_spanned_errors() {
+        use crate::json::{from_str_via_value_spanned, MerdeJsonError};
+
+        let input = r#"{"users":[{"address":{"zip":"oops"},"scores":{}}]}"#;
+        match from_str_via_value_spanned::<Users>(input).unwrap_err() {
+            MerdeJsonError::SpannedMerdeError { err, span, source } => {
+                assert_eq!(&input[span], r#""oops""#);
+                assert_eq!(err.path().unwrap().to_string(), "$.users[0].address.zip");
+                assert_eq!(source.as_deref(), Some(input));
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+
+        // missing properties point at the object they're missing from
+        let input = r#"{"users":[{"address":{},"scores":{}}]}"#;
+        match from_str_via_value_spanned::<Users>(input).unwrap_err() {
+            MerdeJsonError::SpannedMerdeError { span, .. } => assert_eq!(&input[span], "{}"),
+            other => panic!("unexpected error: {other}"),
+        }
+
+        // syntax errors are reported as usual
+        assert!(matches!(
+            from_str_via_value_spanned::<Users>("{").unwrap_err(),
+            MerdeJsonError::JiterError { .. }
+        ));
     }
 }
 
@@ -65,6 +65,7 @@ impl<'s> Array<'s> {
     /// Gets a value from the array, returning an error if the index is out of bounds.
     ///
     /// Because this method knows the index, it transforms [MerdeError::MissingValue] into [MerdeError::IndexOutOfBounds].
+    /// Other errors get the index recorded in their path (see [MerdeError::WithPath]).
     ///
     /// It does not by itself throw an error if `self.get()` returns `None`, to allow
     /// for optional fields (via the [ValueDeserialize] implementation on the [Option] type).
@@ -77,7 +78,7 @@ impl<'s> Array<'s> {
                 index,
                 len: self.len(),
             },
-            _ => e,
+            _ => e.at_index(index),
         })
     }
 
@@ -91,7 +92,8 @@ impl<'s> Array<'s> {
                 index: self.len(),
                 len: self.len(),
             },
-            _ => e,
+            // the popped value was at what is now `self.len()`
+            _ => e.at_index(self.len()),
         })
     }
 
@@ -1,5 +1,5 @@
 use std::{
-    borrow::Cow,
+    borrow::{Borrow, Cow},
     fmt,
     hash::{Hash, Hasher},
     ops::Deref,
@@ -161,6 +161,13 @@ impl PartialEq<CowStr<'_>> for String {
 
 impl Eq for CowStr<'_> {}
 
+/// Lets maps keyed by [CowStr] be looked up with a plain `&str`.
+impl Borrow<str> for CowStr<'_> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
 impl Hash for CowStr<'_> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.deref().hash(state)
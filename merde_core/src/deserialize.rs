@@ -268,7 +268,8 @@ where
         match value {
             Some(Value::Array(arr)) => arr
                 .iter()
-                .map(|item| T::from_value_ref(Some(item)))
+                .enumerate()
+                .map(|(i, item)| T::from_value_ref(Some(item)).map_err(|e| e.at_index(i)))
                 .collect(),
             Some(v) => Err(MerdeError::MismatchedType {
                 expected: ValueType::Array,
@@ -290,8 +291,10 @@ where
             Some(Value::Map(obj)) => {
                 let mut map = std::collections::HashMap::new();
                 for (key, val) in obj.iter() {
-                    let parsed_key = K::from_str(key).map_err(|_| MerdeError::InvalidKey)?;
-                    let parsed_value = V::from_value_ref(Some(val))?;
+                    let parsed_key = K::from_str(key)
+                        .map_err(|_| MerdeError::InvalidKey.at_key(key.clone().into_static()))?;
+                    let parsed_value = V::from_value_ref(Some(val))
+                        .map_err(|e| e.at_key(key.clone().into_static()))?;
                     map.insert(parsed_key, parsed_value);
                 }
                 Ok(map)
@@ -401,7 +404,7 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 1 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
                 Ok((t1,))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -415,7 +418,8 @@ where
     fn from_value(value: Option<Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 1 => {
-                let t1 = T1::from_value(Some(arr.into_iter().next().unwrap()))?;
+                let t1 = T1::from_value(Some(arr.into_iter().next().unwrap()))
+                    .map_err(|e| e.at_index(0))?;
                 Ok((t1,))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -435,8 +439,8 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 2 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
                 Ok((t1, t2))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -451,8 +455,8 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 2 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
                 Ok((t1, t2))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -473,9 +477,9 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 3 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
                 Ok((t1, t2, t3))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -490,9 +494,9 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 3 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
                 Ok((t1, t2, t3))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -514,10 +518,10 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 4 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
-                let t4 = T4::from_value_ref(Some(&arr[3]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value_ref(Some(&arr[3])).map_err(|e| e.at_index(3))?;
                 Ok((t1, t2, t3, t4))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -532,10 +536,10 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 4 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
-                let t4 = T4::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(3))?;
                 Ok((t1, t2, t3, t4))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -558,11 +562,11 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 5 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
-                let t4 = T4::from_value_ref(Some(&arr[3]))?;
-                let t5 = T5::from_value_ref(Some(&arr[4]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value_ref(Some(&arr[3])).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value_ref(Some(&arr[4])).map_err(|e| e.at_index(4))?;
                 Ok((t1, t2, t3, t4, t5))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -577,11 +581,11 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 5 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
-                let t4 = T4::from_value(Some(iter.next().unwrap()))?;
-                let t5 = T5::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(4))?;
                 Ok((t1, t2, t3, t4, t5))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -605,12 +609,12 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 6 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
-                let t4 = T4::from_value_ref(Some(&arr[3]))?;
-                let t5 = T5::from_value_ref(Some(&arr[4]))?;
-                let t6 = T6::from_value_ref(Some(&arr[5]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value_ref(Some(&arr[3])).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value_ref(Some(&arr[4])).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value_ref(Some(&arr[5])).map_err(|e| e.at_index(5))?;
                 Ok((t1, t2, t3, t4, t5, t6))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -625,12 +629,12 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 6 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
-                let t4 = T4::from_value(Some(iter.next().unwrap()))?;
-                let t5 = T5::from_value(Some(iter.next().unwrap()))?;
-                let t6 = T6::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(5))?;
                 Ok((t1, t2, t3, t4, t5, t6))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -655,13 +659,13 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 7 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
-                let t4 = T4::from_value_ref(Some(&arr[3]))?;
-                let t5 = T5::from_value_ref(Some(&arr[4]))?;
-                let t6 = T6::from_value_ref(Some(&arr[5]))?;
-                let t7 = T7::from_value_ref(Some(&arr[6]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value_ref(Some(&arr[3])).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value_ref(Some(&arr[4])).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value_ref(Some(&arr[5])).map_err(|e| e.at_index(5))?;
+                let t7 = T7::from_value_ref(Some(&arr[6])).map_err(|e| e.at_index(6))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -676,13 +680,13 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 7 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
-                let t4 = T4::from_value(Some(iter.next().unwrap()))?;
-                let t5 = T5::from_value(Some(iter.next().unwrap()))?;
-                let t6 = T6::from_value(Some(iter.next().unwrap()))?;
-                let t7 = T7::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(5))?;
+                let t7 = T7::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(6))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -708,14 +712,14 @@ where
     fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
         match value {
             Some(Value::Array(arr)) if arr.len() == 8 => {
-                let t1 = T1::from_value_ref(Some(&arr[0]))?;
-                let t2 = T2::from_value_ref(Some(&arr[1]))?;
-                let t3 = T3::from_value_ref(Some(&arr[2]))?;
-                let t4 = T4::from_value_ref(Some(&arr[3]))?;
-                let t5 = T5::from_value_ref(Some(&arr[4]))?;
-                let t6 = T6::from_value_ref(Some(&arr[5]))?;
-                let t7 = T7::from_value_ref(Some(&arr[6]))?;
-                let t8 = T8::from_value_ref(Some(&arr[7]))?;
+                let t1 = T1::from_value_ref(Some(&arr[0])).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value_ref(Some(&arr[1])).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value_ref(Some(&arr[2])).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value_ref(Some(&arr[3])).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value_ref(Some(&arr[4])).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value_ref(Some(&arr[5])).map_err(|e| e.at_index(5))?;
+                let t7 = T7::from_value_ref(Some(&arr[6])).map_err(|e| e.at_index(6))?;
+                let t8 = T8::from_value_ref(Some(&arr[7])).map_err(|e| e.at_index(7))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7, t8))
             }
             Some(v) => Err(MerdeError::MismatchedType {
@@ -730,14 +734,14 @@ where
         match value {
             Some(Value::Array(arr)) if arr.len() == 8 => {
                 let mut iter = arr.into_iter();
-                let t1 = T1::from_value(Some(iter.next().unwrap()))?;
-                let t2 = T2::from_value(Some(iter.next().unwrap()))?;
-                let t3 = T3::from_value(Some(iter.next().unwrap()))?;
-                let t4 = T4::from_value(Some(iter.next().unwrap()))?;
-                let t5 = T5::from_value(Some(iter.next().unwrap()))?;
-                let t6 = T6::from_value(Some(iter.next().unwrap()))?;
-                let t7 = T7::from_value(Some(iter.next().unwrap()))?;
-                let t8 = T8::from_value(Some(iter.next().unwrap()))?;
+                let t1 = T1::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(0))?;
+                let t2 = T2::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(1))?;
+                let t3 = T3::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(2))?;
+                let t4 = T4::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(3))?;
+                let t5 = T5::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(4))?;
+                let t6 = T6::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(5))?;
+                let t7 = T7::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(6))?;
+                let t8 = T8::from_value(Some(iter.next().unwrap())).map_err(|e| e.at_index(7))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7, t8))
             }
             Some(v) => Err(MerdeError::MismatchedType {
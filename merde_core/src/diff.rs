@@ -0,0 +1,389 @@
+//! Structural diffs between two [Value] trees, see [Value::diff].
+
+use crate::{escape_pointer_token, PatchOperation, Value};
+
+/// Knobs that change what [Value::diff_with] considers equal.
+///
+/// ```
+/// use merde_core::{DiffOptions, Value};
+///
+/// let options = DiffOptions::default()
+///     .float_tolerance(0.01)
+///     .int_equals_float(true);
+/// assert!(Value::Float(1.001).diff_with(&Value::Int(1), &options).is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct DiffOptions {
+    /// Floats that are at most this far apart are considered equal. Defaults
+    /// to `0.0`, which only lets identical floats through.
+    pub float_tolerance: f64,
+
+    /// Consider integers and floats with the same value equal, e.g. `Int(1)`
+    /// and `Float(1.0)`. [DiffOptions::float_tolerance] applies to those too.
+    pub int_equals_float: bool,
+}
+
+impl DiffOptions {
+    /// Sets [DiffOptions::float_tolerance].
+    pub fn float_tolerance(mut self, tolerance: f64) -> Self {
+        self.float_tolerance = tolerance;
+        self
+    }
+
+    /// Sets [DiffOptions::int_equals_float].
+    pub fn int_equals_float(mut self, equal: bool) -> Self {
+        self.int_equals_float = equal;
+        self
+    }
+
+    fn floats_equal(&self, a: f64, b: f64) -> bool {
+        a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= self.float_tolerance
+    }
+}
+
+/// One difference found by [Value::diff]. Paths are [JSON Pointers](Value::pointer).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    /// `value` is only in the new tree
+    Added { path: String, value: &'a Value<'a> },
+
+    /// `value` is only in the old tree
+    Removed { path: String, value: &'a Value<'a> },
+
+    /// The value at `path` went from `old` to `new`
+    Changed {
+        path: String,
+        old: &'a Value<'a>,
+        new: &'a Value<'a>,
+    },
+}
+
+impl<'a> Change<'a> {
+    /// Where the change happened, as a JSON Pointer.
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+
+    /// The JSON Patch operation that makes this change.
+    pub fn to_patch_operation(&self) -> PatchOperation<'a> {
+        match self {
+            Change::Added { path, value } => PatchOperation::Add {
+                path: path.clone().into(),
+                value: (*value).clone(),
+            },
+            Change::Removed { path, .. } => PatchOperation::Remove {
+                path: path.clone().into(),
+            },
+            Change::Changed { path, new, .. } => PatchOperation::Replace {
+                path: path.clone().into(),
+                value: (*new).clone(),
+            },
+        }
+    }
+}
+
+/// The differences between two [Value] trees, see [Value::diff].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff<'a> {
+    changes: Vec<Change<'a>>,
+}
+
+impl<'a> Diff<'a> {
+    /// Whether the two trees are equal.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Every change, in an order in which they can be applied one after the other:
+    /// elements removed from the end of an array are listed last first.
+    pub fn changes(&self) -> &[Change<'a>] {
+        &self.changes
+    }
+
+    /// The JSON Patch (RFC 6902) that turns the old tree into the new one.
+    pub fn to_patch(&self) -> Vec<PatchOperation<'a>> {
+        self.changes
+            .iter()
+            .map(Change::to_patch_operation)
+            .collect()
+    }
+
+    /// Same as [Diff::to_patch], as a [Value] that can be serialized, e.g. to JSON.
+    pub fn to_patch_value(&self) -> Value<'a> {
+        Value::Array(
+            self.changes
+                .iter()
+                .map(|change| Value::from(change.to_patch_operation()))
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }
+}
+
+impl<'a> IntoIterator for Diff<'a> {
+    type Item = Change<'a>;
+    type IntoIter = std::vec::IntoIter<Change<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl Value<'_> {
+    /// Compares `self` (the old tree) to `new`, with the default [DiffOptions]:
+    /// values have to be identical to be equal, and `Int(1)` is not `Float(1.0)`.
+    ///
+    /// ```
+    /// use merde_core::{Array, Change, Map, Value};
+    ///
+    /// let old = Value::from(Map::new().with("a", 1).with("b", Array::new().with(1)));
+    /// let new = Value::from(Map::new().with("a", 2).with("b", Array::new().with(1).with(2)));
+    /// let diff = old.diff(&new);
+    /// let paths: Vec<&str> = diff.changes().iter().map(Change::path).collect();
+    /// assert_eq!(paths, ["/a", "/b/1"]);
+    ///
+    /// let mut patched = old.clone();
+    /// patched.apply_patch(&diff.to_patch()).unwrap();
+    /// assert_eq!(patched, new);
+    /// ```
+    pub fn diff<'a>(&'a self, new: &'a Value<'_>) -> Diff<'a> {
+        self.diff_with(new, &DiffOptions::default())
+    }
+
+    /// Compares `self` (the old tree) to `new`, see [Value::diff].
+    pub fn diff_with<'a>(&'a self, new: &'a Value<'_>, options: &DiffOptions) -> Diff<'a> {
+        let mut diff = Diff::default();
+        diff_into(self, new, options, &mut String::new(), &mut diff.changes);
+        diff
+    }
+}
+
+fn diff_into<'a>(
+    old: &'a Value<'a>,
+    new: &'a Value<'a>,
+    options: &DiffOptions,
+    path: &mut String,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let equal = match (old, new) {
+        (Value::Float(a), Value::Float(b)) => options.floats_equal(*a, *b),
+        (Value::Int(i), Value::Float(f)) | (Value::Float(f), Value::Int(i))
+            if options.int_equals_float =>
+        {
+            options.floats_equal(*i as f64, *f)
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            let common = old.len().min(new.len());
+            for (index, (old, new)) in old.iter().zip(new.iter()).enumerate() {
+                with_token(path, &index.to_string(), |path| {
+                    diff_into(old, new, options, path, changes)
+                });
+            }
+            for (index, value) in new.iter().enumerate().skip(common) {
+                changes.push(Change::Added {
+                    path: format!("{path}/{index}"),
+                    value,
+                });
+            }
+            // from the end, so that indices stay valid when applied in order
+            for (index, value) in old.iter().enumerate().skip(common).rev() {
+                changes.push(Change::Removed {
+                    path: format!("{path}/{index}"),
+                    value,
+                });
+            }
+            true
+        }
+        (Value::Map(old), Value::Map(new)) => {
+            for (key, old_value) in sorted(old.iter()) {
+                with_token(path, key, |path| match new.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, options, path, changes),
+                    None => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: old_value,
+                    }),
+                });
+            }
+            for (key, value) in sorted(new.iter()) {
+                if !old.contains_key(key) {
+                    with_token(path, key, |path| {
+                        changes.push(Change::Added {
+                            path: path.clone(),
+                            value,
+                        })
+                    });
+                }
+            }
+            true
+        }
+        (old, new) => old == new,
+    };
+    if !equal {
+        changes.push(Change::Changed {
+            path: path.clone(),
+            old,
+            new,
+        });
+    }
+}
+
+/// Runs `f` with `token` appended to `path`.
+fn with_token(path: &mut String, token: &str, f: impl FnOnce(&mut String)) {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&escape_pointer_token(token));
+    f(path);
+    path.truncate(len);
+}
+
+/// Map entries in a stable order: the order they're in with `preserve_order`,
+/// sorted by key otherwise.
+fn sorted<'m, K: AsRef<str> + 'm, V: 'm>(
+    entries: impl Iterator<Item = (&'m K, &'m V)>,
+) -> impl Iterator<Item = (&'m str, &'m V)> {
+    #[allow(unused_mut)]
+    let mut entries: Vec<(&str, &V)> = entries.map(|(k, v)| (k.as_ref(), v)).collect();
+    #[cfg(not(feature = "preserve_order"))]
+    entries.sort_by_key(|(k, _)| *k);
+    entries.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, DiffOptions};
+    use crate::{Array, Map, Value};
+
+    #[test]
+    fn test_diff() {
+        let old = Value::from(
+            Map::new()
+                .with("same", "x")
+                .with("changed", 1)
+                .with("removed", true)
+                .with(
+                    "a/b",
+                    Map::new().with("deep", Array::new().with(1).with(2).with(3)),
+                )
+                .with("kind", Array::new()),
+        );
+        let new = Value::from(
+            Map::new()
+                .with("same", "x")
+                .with("changed", 2)
+                .with("added", Value::Null)
+                .with("a/b", Map::new().with("deep", Array::new().with(1).with(5)))
+                .with("kind", Map::new()),
+        );
+        let diff = old.diff(&new);
+        let described: Vec<String> = diff
+            .changes()
+            .iter()
+            .map(|change| match change {
+                Change::Added { path, value } => format!("+ {path} {value:?}"),
+                Change::Removed { path, value } => format!("- {path} {value:?}"),
+                Change::Changed { path, old, new } => format!("~ {path} {old:?} {new:?}"),
+            })
+            .collect();
+
+        let mut expected = vec![
+            "~ /a~1b/deep/1 Int(2) Int(5)",
+            "- /a~1b/deep/2 Int(3)",
+            "~ /changed Int(1) Int(2)",
+            "~ /kind Array(Array([])) Map(Map({}))",
+            "- /removed Bool(true)",
+            "+ /added Null",
+        ];
+        if cfg!(feature = "preserve_order") {
+            // old keys in document order, then new ones
+            expected = vec![
+                "~ /changed Int(1) Int(2)",
+                "- /removed Bool(true)",
+                "~ /a~1b/deep/1 Int(2) Int(5)",
+                "- /a~1b/deep/2 Int(3)",
+                "~ /kind Array(Array([])) Map(Map({}))",
+                "+ /added Null",
+            ];
+        }
+        assert_eq!(described, expected);
+
+        let mut patched = old.clone();
+        patched.apply_patch(&diff.to_patch()).unwrap();
+        assert_eq!(patched, new);
+
+        assert!(old.diff(&old).is_empty());
+        let root = Value::Int(1).diff(&Value::Null);
+        assert_eq!(root.changes()[0].path(), "");
+    }
+
+    #[test]
+    fn test_array_tails() {
+        let long = Value::from(Array::new().with(0).with(1).with(2).with(3));
+        let short = Value::from(Array::new().with(0));
+
+        let shrink = long.diff(&short);
+        let paths: Vec<&str> = shrink.changes().iter().map(Change::path).collect();
+        assert_eq!(paths, ["/3", "/2", "/1"]);
+        let mut patched = long.clone();
+        patched.apply_patch(&shrink.to_patch()).unwrap();
+        assert_eq!(patched, short);
+
+        let grow = short.diff(&long);
+        let mut patched = short.clone();
+        patched.apply_patch(&grow.to_patch()).unwrap();
+        assert_eq!(patched, long);
+    }
+
+    #[test]
+    fn test_options() {
+        let old = Value::from(Array::new().with(1).with(0.5).with(f64::NAN));
+        let new = Value::from(Array::new().with(1.0).with(0.5000001).with(f64::NAN));
+        let paths = |options: &DiffOptions| {
+            old.diff_with(&new, options)
+                .into_iter()
+                .map(|change| change.path().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&DiffOptions::default()), ["/0", "/1"]);
+        assert_eq!(
+            paths(&DiffOptions::default().int_equals_float(true)),
+            ["/1"]
+        );
+        assert_eq!(paths(&DiffOptions::default().float_tolerance(1e-3)), ["/0"]);
+        assert!(paths(
+            &DiffOptions::default()
+                .float_tolerance(1e-3)
+                .int_equals_float(true)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_patch_value() {
+        let old = Value::from(Map::new().with("a", 1).with("b", 2));
+        let new = Value::from(Map::new().with("a", 3).with("c", 4));
+        assert_eq!(
+            old.diff(&new).to_patch_value(),
+            Value::from(
+                Array::new()
+                    .with(
+                        Map::new()
+                            .with("op", "replace")
+                            .with("path", "/a")
+                            .with("value", 3)
+                    )
+                    .with(Map::new().with("op", "remove").with("path", "/b"))
+                    .with(
+                        Map::new()
+                            .with("op", "add")
+                            .with("path", "/c")
+                            .with("value", 4)
+                    )
+            )
+        );
+    }
+}
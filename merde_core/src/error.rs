@@ -33,6 +33,61 @@ pub enum ValueType {
     Map,
 }
 
+/// One step of an [ErrorPath]: a key in a map, or an index in an array.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// A key in a [Map](crate::Map)
+    Key(CowStr<'static>),
+
+    /// An index in an [Array](crate::Array)
+    Index(usize),
+}
+
+/// Where in a document something went wrong, e.g. `$.users[3].address.zip`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ErrorPath {
+    // innermost segment first: errors bubble up from the inside, so that's
+    // the order segments are discovered in.
+    rev_segments: Vec<PathSegment>,
+}
+
+impl ErrorPath {
+    /// The segments of the path, from the root of the document inwards.
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &PathSegment> {
+        self.rev_segments.iter().rev()
+    }
+}
+
+/// Builds a path out of segments, from the root of the document inwards.
+impl FromIterator<PathSegment> for ErrorPath {
+    fn from_iter<I: IntoIterator<Item = PathSegment>>(iter: I) -> Self {
+        let mut rev_segments: Vec<PathSegment> = iter.into_iter().collect();
+        rev_segments.reverse();
+        ErrorPath { rev_segments }
+    }
+}
+
+impl std::fmt::Display for ErrorPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$")?;
+        for segment in self.segments() {
+            match segment {
+                PathSegment::Key(key) if is_identifier(key) => write!(f, ".{}", key)?,
+                PathSegment::Key(key) => write!(f, "[{:?}]", key.as_ref())?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `key` can be written as `.key` in a path, rather than `["key"]`
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
 /// A grab-bag of errors that can occur when deserializing.
 /// This isn't super clean, not my proudest moment.
 #[derive(Debug)]
@@ -61,9 +116,27 @@ pub enum MerdeError {
         len: usize,
     },
 
-    /// We encountered a property that we didn't expect.
+    /// We encountered a property that we didn't expect (or several, separated by commas).
     UnknownProperty(String),
 
+    /// We encountered an enum variant tag that doesn't match any variant.
+    UnknownVariant(String),
+
+    /// We found which enum variant to deserialize, but deserializing its content failed.
+    VariantError {
+        /// The name of the variant we attempted to deserialize.
+        variant: &'static str,
+
+        /// What went wrong while deserializing it.
+        error: Box<MerdeError>,
+    },
+
+    /// None of the variants of an untagged enum could be deserialized.
+    NoMatchingVariant {
+        /// Each variant we attempted, in declaration order, along with why it failed.
+        errors: Vec<(&'static str, MerdeError)>,
+    },
+
     /// For example, we had a `u8` field but the JSON value was bigger than `u8::MAX`.
     OutOfRange,
 
@@ -78,6 +151,99 @@ pub enum MerdeError {
 
     /// An I/O error occurred.
     Io(std::io::Error),
+
+    /// A JSON Pointer (see [Value::pointer](crate::Value::pointer)) is malformed,
+    /// or can't be followed the way it's written.
+    InvalidPointer(String),
+
+    /// One of the operations of a JSON Patch (see [Value::apply_patch](crate::Value::apply_patch))
+    /// couldn't be applied.
+    PatchFailed {
+        /// The position of the operation in the patch.
+        index: usize,
+
+        /// The name of the operation, e.g. `"add"`.
+        op: &'static str,
+
+        /// Why it couldn't be applied.
+        error: Box<MerdeError>,
+    },
+
+    /// A JSON Patch `test` operation found a different value than the one it expected.
+    PatchTestFailed,
+
+    /// Another error, along with where in the document it happened. Built up by
+    /// [Map::must_get](crate::Map::must_get), [Array::must_get](crate::Array::must_get),
+    /// etc. as the error bubbles up.
+    WithPath {
+        /// Where the error happened.
+        path: ErrorPath,
+
+        /// What went wrong there.
+        error: Box<MerdeError>,
+    },
+}
+
+impl MerdeError {
+    /// Records that this error happened under `key`.
+    pub fn at_key(self, key: impl Into<CowStr<'static>>) -> Self {
+        self.at(PathSegment::Key(key.into()))
+    }
+
+    /// Records that this error happened at `index`.
+    pub fn at_index(self, index: usize) -> Self {
+        self.at(PathSegment::Index(index))
+    }
+
+    pub(crate) fn at(self, segment: PathSegment) -> Self {
+        match self {
+            MerdeError::WithPath { mut path, error } => {
+                path.rev_segments.push(segment);
+                MerdeError::WithPath { path, error }
+            }
+            // keep the path next to the error it belongs to, so that it reads
+            // "In variant A: Missing property: x (at $.event.x)"
+            MerdeError::VariantError { variant, error } => MerdeError::VariantError {
+                variant,
+                error: Box::new(error.at(segment)),
+            },
+            MerdeError::NoMatchingVariant { errors } => MerdeError::NoMatchingVariant {
+                errors: errors
+                    .into_iter()
+                    .map(|(variant, error)| (variant, error.at(segment.clone())))
+                    .collect(),
+            },
+            error => MerdeError::WithPath {
+                path: ErrorPath {
+                    rev_segments: vec![segment],
+                },
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Where in the document this error happened, if known.
+    pub fn path(&self) -> Option<&ErrorPath> {
+        match self {
+            MerdeError::WithPath { path, .. } => Some(path),
+            MerdeError::VariantError { error, .. } => error.path(),
+            MerdeError::PatchFailed { error, .. } => error.path(),
+            // all attempts share the path to the enum itself
+            MerdeError::NoMatchingVariant { errors } => {
+                errors.first().and_then(|(_, error)| error.path())
+            }
+            _ => None,
+        }
+    }
+
+    /// The error itself, without the information about where it happened
+    /// (see [MerdeError::WithPath]).
+    pub fn without_path(&self) -> &MerdeError {
+        match self {
+            MerdeError::WithPath { error, .. } => error,
+            error => error,
+        }
+    }
 }
 
 impl From<std::io::Error> for MerdeError {
@@ -105,6 +271,20 @@ impl std::fmt::Display for MerdeError {
             MerdeError::UnknownProperty(prop) => {
                 write!(f, "Unknown property: {}", prop)
             }
+            MerdeError::UnknownVariant(variant) => {
+                write!(f, "Unknown variant: {}", variant)
+            }
+            MerdeError::VariantError { variant, error } => {
+                write!(f, "In variant {}: {}", variant, error)
+            }
+            MerdeError::NoMatchingVariant { errors } => {
+                write!(f, "No variant matched")?;
+                for (i, (variant, error)) in errors.iter().enumerate() {
+                    let sep = if i == 0 { ": " } else { "; " };
+                    write!(f, "{}tried {} ({})", sep, variant, error)?;
+                }
+                Ok(())
+            }
             MerdeError::OutOfRange => {
                 write!(f, "Value is out of range")
             }
@@ -120,6 +300,18 @@ impl std::fmt::Display for MerdeError {
             MerdeError::Io(e) => {
                 write!(f, "I/O error: {}", e)
             }
+            MerdeError::InvalidPointer(reason) => {
+                write!(f, "Invalid JSON pointer: {}", reason)
+            }
+            MerdeError::PatchFailed { index, op, error } => {
+                write!(f, "Patch operation {} ({}) failed: {}", index, op, error)
+            }
+            MerdeError::PatchTestFailed => {
+                write!(f, "Test failed: the value is different")
+            }
+            MerdeError::WithPath { path, error } => {
+                write!(f, "{} (at {})", error, path)
+            }
         }
     }
 }
@@ -1,3 +1,6 @@
+#[macro_use]
+mod macros;
+
 mod cowstr;
 pub use cowstr::CowStr;
 
@@ -6,9 +9,12 @@ pub use array::Array;
 
 mod map;
 pub use map::Map;
+pub use map::MapInner;
 
 mod error;
+pub use error::ErrorPath;
 pub use error::MerdeError;
+pub use error::PathSegment;
 pub use error::ValueType;
 
 mod into_static;
@@ -24,6 +30,22 @@ mod deserialize;
 pub use deserialize::OwnedValueDeserialize;
 pub use deserialize::ValueDeserialize;
 
+mod serialize;
+pub use serialize::ValueSerialize;
+pub use serialize::ValueSerializeMap;
+
+mod pointer;
+pub use pointer::escape_pointer_token;
+
+mod patch;
+pub use patch::PatchOperation;
+
+mod diff;
+pub use diff::{Change, Diff, DiffOptions};
+
+mod options;
+pub use options::DeserializeOptions;
+
 /// Interpret a &[`Value`] as an instance of type `T`. This may involve
 /// more cloning than [`from_value`].
 pub fn from_value_ref<'s, T>(value: &Value<'s>) -> Result<T, MerdeError>
@@ -41,9 +63,20 @@ where
     T::from_value(Some(value))
 }
 
+/// Turn a `T` into a [`Value`], borrowing from it where possible.
+pub fn to_value<T>(value: &T) -> Value<'_>
+where
+    T: ValueSerialize + ?Sized,
+{
+    value.to_value()
+}
+
 rubicon::compatibility_check! {
     ("merde_core_pkg_version", env!("CARGO_PKG_VERSION")),
 
     #[cfg(feature = "compact_str")]
     ("compact_str", "enabled")
+
+    #[cfg(feature = "preserve_order")]
+    ("preserve_order", "enabled")
 }
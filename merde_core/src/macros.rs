@@ -0,0 +1,305 @@
+/// Builds a [Value](crate::Value) out of JSON-like syntax.
+///
+/// Objects, arrays, `null`, `true`, `false` and numbers are written as they would
+/// be in JSON. Anything else is a Rust expression, turned into a value with
+/// [Into]: strings are borrowed rather than copied, so the resulting value can't
+/// outlive them.
+///
+/// ```
+/// use merde_core::{value, Array, Map, Value};
+///
+/// let name = String::from("Alice");
+/// let tags = Array::new().with("admin");
+/// let v = value!({
+///     "name": name.as_str(),
+///     "age": 30,
+///     "score": -1.5,
+///     "tags": tags,
+///     "friends": [{ "name": "Bob", "ok": true }, null],
+/// });
+/// assert_eq!(
+///     v,
+///     Value::from(
+///         Map::new()
+///             .with("name", "Alice")
+///             .with("age", 30)
+///             .with("score", -1.5)
+///             .with("tags", Array::new().with("admin"))
+///             .with(
+///                 "friends",
+///                 Array::new()
+///                     .with(Map::new().with("name", "Bob").with("ok", true))
+///                     .with(Value::Null)
+///             )
+///     )
+/// );
+/// ```
+///
+/// Keys are string literals, or Rust expressions that convert into a
+/// [CowStr](crate::CowStr): a bare identifier is a variable, not a string, and
+/// longer expressions need parentheses, as in `value!({ (format!("key{i}")): i })`.
+#[macro_export]
+macro_rules! value {
+    // Implementation note: this is a tt-muncher, in the style of `serde_json::json!`.
+    // Arrays are built up as a list of expressions, objects are inserted into as
+    // their entries are found.
+
+    //////////////////////////////////////////////////////////////////////////
+    // arrays: @array [elements so far] remaining tokens
+    //////////////////////////////////////////////////////////////////////////
+
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+
+    (@array [$($elems:expr),*]) => {
+        ::std::vec![$($elems),*]
+    };
+
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!(null)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!(true)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!(false)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!([$($array)*])] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] {$($map:tt)*} $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!({$($map)*})] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)* $crate::value!($next),] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::value!(@array [$($elems,)* $crate::value!($last)])
+    };
+
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::value!(@array [$($elems,)*] $($rest)*)
+    };
+
+    (@array [$($elems:expr),*] $unexpected:tt $($rest:tt)*) => {
+        $crate::value!(@unexpected $unexpected)
+    };
+
+    //////////////////////////////////////////////////////////////////////////
+    // objects: @object map (key so far) (remaining tokens) (copy of them, for errors)
+    // once the value is known: @object map [key] (value) remaining tokens
+    //////////////////////////////////////////////////////////////////////////
+
+    (@object $object:ident () () ()) => {};
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+        $crate::value!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) $unexpected:tt $($rest:tt)*) => {
+        $crate::value!(@unexpected $unexpected);
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!(null)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!(true)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!(false)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!([$($array)*])) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!({$($map)*})) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!($value)) , $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::value!(@object $object [$($key)+] ($crate::value!($value)));
+    };
+
+    // a key without a value
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        $crate::value!();
+    };
+
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        $crate::value!();
+    };
+
+    // a value without a key
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        $crate::value!(@unexpected $colon);
+    };
+
+    // a comma in the middle of a key
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        $crate::value!(@unexpected $comma);
+    };
+
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::value!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    // matches nothing, so that unexpected tokens are pointed at
+    (@unexpected) => {};
+
+    //////////////////////////////////////////////////////////////////////////
+    // entry points
+    //////////////////////////////////////////////////////////////////////////
+
+    (null) => {
+        $crate::Value::Null
+    };
+
+    (true) => {
+        $crate::Value::Bool(true)
+    };
+
+    (false) => {
+        $crate::Value::Bool(false)
+    };
+
+    ([]) => {
+        $crate::Value::Array($crate::Array::new())
+    };
+
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::Array($crate::Array::from($crate::value!(@array [] $($tt)+)))
+    };
+
+    ({}) => {
+        $crate::Value::Map($crate::Map::new())
+    };
+
+    ({ $($tt:tt)+ }) => {
+        $crate::Value::Map({
+            let mut object = $crate::Map::new();
+            $crate::value!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Array, CowStr, Map, Value};
+
+    #[test]
+    fn test_value_macro() {
+        assert_eq!(value!(null), Value::Null);
+        assert_eq!(value!(true), Value::Bool(true));
+        assert_eq!(value!(-3), Value::Int(-3));
+        assert_eq!(value!(2.5), Value::Float(2.5));
+        assert_eq!(value!([]), Value::Array(Array::new()));
+        assert_eq!(value!({}), Value::Map(Map::new()));
+        assert_eq!(
+            value!([1, -2, [null], {}, "x",]),
+            Value::Array(
+                Array::new()
+                    .with(1)
+                    .with(-2)
+                    .with(Array::new().with(Value::Null))
+                    .with(Map::new())
+                    .with("x")
+            )
+        );
+
+        let key = String::from("dynamic");
+        let n: i64 = 4;
+        assert_eq!(
+            value!({
+                "a": { "b": [true, false] },
+                (format!("{key}!")): n * 2,
+                key: null,
+            }),
+            Value::Map(
+                Map::new()
+                    .with(
+                        "a",
+                        Map::new().with("b", Array::new().with(true).with(false))
+                    )
+                    .with("dynamic!", 8)
+                    .with("dynamic", Value::Null)
+            )
+        );
+
+        assert_eq!(
+            value!({
+                "name": "John Doe",
+                "age": 42,
+                "address": {
+                    "street": "123 Main St",
+                    "city": "Anytown",
+                    "state": "CA",
+                    "zip": 12345
+                },
+                "friends": ["Alice", "Bob", "Charlie"]
+            }),
+            Value::Map(
+                Map::new()
+                    .with("name", Value::Str(CowStr::from("John Doe")))
+                    .with("age", Value::Int(42))
+                    .with(
+                        "address",
+                        Map::new()
+                            .with("street", Value::Str(CowStr::from("123 Main St")))
+                            .with("city", Value::Str(CowStr::from("Anytown")))
+                            .with("state", Value::Str(CowStr::from("CA")))
+                            .with("zip", Value::Int(12345))
+                    )
+                    .with(
+                        "friends",
+                        Array::new()
+                            .with(Value::from("Alice"))
+                            .with(Value::from("Bob"))
+                            .with(Value::from("Charlie"))
+                    )
+            )
+        );
+    }
+
+    #[test]
+    fn test_borrows() {
+        let s = String::from("borrowed");
+        let owned = String::from("owned");
+        let v = value!({ "b": s.as_str(), "o": owned, "v": vec![Value::Null] });
+        let map = v.as_map().unwrap();
+        assert!(matches!(
+            map.get("b"),
+            Some(Value::Str(CowStr::Borrowed("borrowed")))
+        ));
+        assert!(matches!(map.get("o"), Some(Value::Str(CowStr::Owned(_)))));
+        assert_eq!(map.get("v"), Some(&value!([null])));
+    }
+}
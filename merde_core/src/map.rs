@@ -1,22 +1,45 @@
 use std::{
+    borrow::Borrow,
     collections::HashMap,
+    hash::Hash,
     ops::{Deref, DerefMut},
 };
 
 use crate::{value::Value, CowStr, IntoStatic, MerdeError, ValueDeserialize};
 
+/// What a [Map] stores its entries in: a [HashMap] by default, or an
+/// [IndexMap](indexmap::IndexMap) with the `preserve_order` feature, so that
+/// keys come back out in the order they were inserted (or parsed) in.
+#[cfg(not(feature = "preserve_order"))]
+pub type MapInner<'s> = HashMap<CowStr<'s>, Value<'s>>;
+
+/// What a [Map] stores its entries in: a [HashMap] by default, or an
+/// [IndexMap](indexmap::IndexMap) with the `preserve_order` feature, so that
+/// keys come back out in the order they were inserted (or parsed) in.
+#[cfg(feature = "preserve_order")]
+pub type MapInner<'s> = indexmap::IndexMap<CowStr<'s>, Value<'s>>;
+
+#[cfg(not(feature = "preserve_order"))]
+type MapIntoIter<'s> = std::collections::hash_map::IntoIter<CowStr<'s>, Value<'s>>;
+
+#[cfg(feature = "preserve_order")]
+type MapIntoIter<'s> = indexmap::map::IntoIter<CowStr<'s>, Value<'s>>;
+
 /// A map, dictionary, object, whatever — with string keys.
+///
+/// Iteration order is unspecified, unless the `preserve_order` feature is
+/// enabled, see [MapInner].
 #[derive(Debug, PartialEq, Clone)]
 #[repr(transparent)]
-pub struct Map<'s>(pub HashMap<CowStr<'s>, Value<'s>>);
+pub struct Map<'s>(pub MapInner<'s>);
 
 impl<'s> Map<'s> {
     pub fn new() -> Self {
-        Map(HashMap::new())
+        Map(MapInner::new())
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
-        Map(HashMap::with_capacity(capacity))
+        Map(MapInner::with_capacity(capacity))
     }
 
     pub fn with(mut self, key: impl Into<CowStr<'s>>, value: impl Into<Value<'s>>) -> Self {
@@ -24,9 +47,26 @@ impl<'s> Map<'s> {
         self
     }
 
-    pub fn into_inner(self) -> HashMap<CowStr<'s>, Value<'s>> {
+    pub fn into_inner(self) -> MapInner<'s> {
         self.0
     }
+
+    /// Removes a key from the map, returning its value if it was there. With
+    /// `preserve_order`, the remaining keys stay in order.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value<'s>>
+    where
+        CowStr<'s>: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        {
+            self.0.remove(key)
+        }
+        #[cfg(feature = "preserve_order")]
+        {
+            self.0.shift_remove(key)
+        }
+    }
 }
 
 impl IntoStatic for Map<'_> {
@@ -43,7 +83,7 @@ impl IntoStatic for Map<'_> {
 
 impl<'s> IntoIterator for Map<'s> {
     type Item = (CowStr<'s>, Value<'s>);
-    type IntoIter = std::collections::hash_map::IntoIter<CowStr<'s>, Value<'s>>;
+    type IntoIter = MapIntoIter<'s>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
@@ -58,12 +98,32 @@ impl<'s> Default for Map<'s> {
 
 impl<'s> From<HashMap<CowStr<'s>, Value<'s>>> for Map<'s> {
     fn from(v: HashMap<CowStr<'s>, Value<'s>>) -> Self {
+        #[cfg(not(feature = "preserve_order"))]
+        {
+            Map(v)
+        }
+        #[cfg(feature = "preserve_order")]
+        {
+            Map(v.into_iter().collect())
+        }
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<'s> From<MapInner<'s>> for Map<'s> {
+    fn from(v: MapInner<'s>) -> Self {
         Map(v)
     }
 }
 
+impl<'s> FromIterator<(CowStr<'s>, Value<'s>)> for Map<'s> {
+    fn from_iter<I: IntoIterator<Item = (CowStr<'s>, Value<'s>)>>(iter: I) -> Self {
+        Map(iter.into_iter().collect())
+    }
+}
+
 impl<'s> Deref for Map<'s> {
-    type Target = HashMap<CowStr<'s>, Value<'s>>;
+    type Target = MapInner<'s>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -79,7 +139,8 @@ impl DerefMut for Map<'_> {
 impl<'s> Map<'s> {
     /// Gets a value from the object, returning an error if the key is missing.
     ///
-    /// Because this method knows the key name, it transforms [MerdeError::MissingValue] into [MerdeError::MissingProperty].
+    /// Because this method knows the key name, it transforms [MerdeError::MissingValue] into [MerdeError::MissingProperty],
+    /// and records the key in the error's path (see [MerdeError::WithPath]).
     ///
     /// It does not by itself throw an error if `self.get()` returns `None`, to allow
     /// for optional fields (via the [ValueDeserialize] implementation on the [Option] type).
@@ -89,14 +150,15 @@ impl<'s> Map<'s> {
     {
         let key = key.into();
         T::from_value_ref(self.get(&key)).map_err(|e| match e {
-            MerdeError::MissingValue => MerdeError::MissingProperty(key),
-            _ => e,
+            MerdeError::MissingValue => MerdeError::MissingProperty(key.clone()).at_key(key),
+            _ => e.at_key(key),
         })
     }
 
     /// Removes a value from the object, returning an error if the key is missing.
     ///
-    /// Because this method knows the key name, it transforms [MerdeError::MissingValue] into [MerdeError::MissingProperty].
+    /// Because this method knows the key name, it transforms [MerdeError::MissingValue] into [MerdeError::MissingProperty],
+    /// and records the key in the error's path (see [MerdeError::WithPath]).
     ///
     /// It does not by itself throw an error if `self.remove()` returns `None`, to allow
     /// for optional fields (via the [ValueDeserialize] implementation on the [Option] type).
@@ -106,8 +168,45 @@ impl<'s> Map<'s> {
     {
         let key = key.into();
         T::from_value(self.remove(&key)).map_err(|e| match e {
-            MerdeError::MissingValue => MerdeError::MissingProperty(key),
-            _ => e,
+            MerdeError::MissingValue => MerdeError::MissingProperty(key.clone()).at_key(key),
+            _ => e.at_key(key),
         })
     }
+
+    /// Returns [MerdeError::UnknownProperty] if the object has any key that's not
+    /// in `known`. All unknown keys are listed, sorted and separated by commas.
+    pub fn deny_unknown_keys(&self, known: &[&str]) -> Result<(), MerdeError> {
+        let mut unknown: Vec<&str> = self
+            .keys()
+            .map(|k| k.as_ref())
+            .filter(|k| !known.contains(k))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort_unstable();
+        Err(MerdeError::UnknownProperty(unknown.join(", ")))
+    }
+}
+
+#[cfg(all(test, feature = "preserve_order"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preserves_insertion_order() {
+        let mut map = Map::new()
+            .with("zebra", 1)
+            .with("apple", 2)
+            .with("mango", 3)
+            .with("kiwi", 4);
+        map.insert("apple".into(), Value::Int(5));
+        assert_eq!(map.remove(&CowStr::from("zebra")), Some(Value::Int(1)));
+        let keys: Vec<&str> = map.keys().map(|k| k.as_ref()).collect();
+        assert_eq!(keys, ["apple", "mango", "kiwi"]);
+
+        let map = map.into_static();
+        let keys: Vec<CowStr> = map.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["apple", "mango", "kiwi"]);
+    }
 }
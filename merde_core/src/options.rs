@@ -0,0 +1,61 @@
+use std::cell::Cell;
+
+/// Knobs that change how derived [ValueDeserialize](crate::ValueDeserialize) impls
+/// behave, for every type at once.
+///
+/// Options apply to the current thread, for the duration of [DeserializeOptions::scope]:
+///
+/// ```
+/// use merde_core::DeserializeOptions;
+///
+/// DeserializeOptions::default()
+///     .deny_unknown_fields(true)
+///     .scope(|| {
+///         assert!(DeserializeOptions::current().deny_unknown_fields);
+///     });
+/// assert!(!DeserializeOptions::current().deny_unknown_fields);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeserializeOptions {
+    /// Fail with [MerdeError::UnknownProperty](crate::MerdeError::UnknownProperty)
+    /// when a map has keys that don't correspond to any field, as if every type
+    /// had `#[merde(deny_unknown_fields)]`.
+    pub deny_unknown_fields: bool,
+}
+
+rubicon::thread_local! {
+    static OPTIONS: Cell<DeserializeOptions> = const {
+        Cell::new(DeserializeOptions {
+            deny_unknown_fields: false,
+        })
+    };
+}
+
+impl DeserializeOptions {
+    /// Sets [DeserializeOptions::deny_unknown_fields].
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// The options in effect on the current thread.
+    pub fn current() -> Self {
+        OPTIONS.with(|o| o.get())
+    }
+
+    /// Runs `f` with these options in effect on the current thread. The previous
+    /// options are restored afterwards, even if `f` panics.
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        struct Restore(DeserializeOptions);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                OPTIONS.with(|o| o.set(self.0));
+            }
+        }
+
+        let _restore = Restore(OPTIONS.with(|o| o.replace(self)));
+        f()
+    }
+}
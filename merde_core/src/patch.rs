@@ -0,0 +1,647 @@
+//! [JSON Patch](https://www.rfc-editor.org/rfc/rfc6902) and
+//! [JSON Merge Patch](https://www.rfc-editor.org/rfc/rfc7396) for [Value].
+
+use crate::{CowStr, Map, MerdeError, Value, ValueDeserialize, ValueSerialize, ValueType};
+
+/// One operation of a JSON Patch (RFC 6902). A patch is a list of them, which
+/// deserializes from a JSON array, see [Value::apply_patch].
+///
+/// Paths are [JSON Pointers](Value::pointer).
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchOperation<'s> {
+    /// Adds `value` at `path`, see [Value::pointer_insert]
+    Add { path: CowStr<'s>, value: Value<'s> },
+
+    /// Removes the value at `path`, which must exist
+    Remove { path: CowStr<'s> },
+
+    /// Replaces the value at `path`, which must exist
+    Replace { path: CowStr<'s>, value: Value<'s> },
+
+    /// Removes the value at `from` and adds it at `path`
+    Move { from: CowStr<'s>, path: CowStr<'s> },
+
+    /// Adds a copy of the value at `from` at `path`
+    Copy { from: CowStr<'s>, path: CowStr<'s> },
+
+    /// Checks that the value at `path` is equal to `value`
+    Test { path: CowStr<'s>, value: Value<'s> },
+}
+
+impl<'s> PatchOperation<'s> {
+    /// The name of the operation, as spelled in the `op` member: `"add"`, `"remove"`, etc.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    fn apply(&self, target: &mut Value<'s>) -> Result<(), MerdeError> {
+        match self {
+            PatchOperation::Add { path, value } => {
+                target.pointer_insert(path, value.clone())?;
+            }
+            PatchOperation::Remove { path } => {
+                target.pointer_remove(path)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                *target.pointer_target_mut(path)? = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                if from == path {
+                    // still has to exist
+                    target.pointer_target(from)?;
+                    return Ok(());
+                }
+                if path
+                    .strip_prefix(from.as_ref())
+                    .is_some_and(|rest| rest.starts_with('/'))
+                {
+                    return Err(MerdeError::InvalidPointer(format!(
+                        "can't move {from:?} into one of its own children"
+                    )));
+                }
+                let value = target.pointer_remove(from)?;
+                target.pointer_insert(path, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let (value, _) = target.pointer_target(from)?;
+                target.pointer_insert(path, value.clone())?;
+            }
+            PatchOperation::Test { path, value } => {
+                let (actual, segments) = target.pointer_target(path)?;
+                if !json_eq(actual, value) {
+                    return Err(crate::pointer::at_path(
+                        MerdeError::PatchTestFailed,
+                        &segments,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Equality as JSON Patch's `test` operation sees it: numbers are equal if their
+/// values are, whether they're integers or floats, and maps are equal regardless
+/// of the order of their keys.
+pub(crate) fn json_eq(a: &Value<'_>, b: &Value<'_>) -> bool {
+    match (a, b) {
+        (Value::Int(i), Value::Float(f)) | (Value::Float(f), Value::Int(i)) => {
+            *f == *i as f64 && *f as i64 == *i
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, a)| b.get(k).is_some_and(|b| json_eq(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+impl<'s> ValueDeserialize<'s> for PatchOperation<'s> {
+    fn from_value(value: Option<Value<'s>>) -> Result<Self, MerdeError> {
+        let mut map = match value {
+            Some(Value::Map(map)) => map,
+            Some(v) => {
+                return Err(MerdeError::MismatchedType {
+                    expected: ValueType::Map,
+                    found: v.value_type(),
+                })
+            }
+            None => return Err(MerdeError::MissingValue),
+        };
+        // members other than the ones the operation uses are ignored (RFC 6902, section 4)
+        let op: CowStr<'s> = map.must_remove("op")?;
+        Ok(match op.as_ref() {
+            "add" => PatchOperation::Add {
+                path: map.must_remove("path")?,
+                value: map.must_remove("value")?,
+            },
+            "remove" => PatchOperation::Remove {
+                path: map.must_remove("path")?,
+            },
+            "replace" => PatchOperation::Replace {
+                path: map.must_remove("path")?,
+                value: map.must_remove("value")?,
+            },
+            "move" => PatchOperation::Move {
+                from: map.must_remove("from")?,
+                path: map.must_remove("path")?,
+            },
+            "copy" => PatchOperation::Copy {
+                from: map.must_remove("from")?,
+                path: map.must_remove("path")?,
+            },
+            "test" => PatchOperation::Test {
+                path: map.must_remove("path")?,
+                value: map.must_remove("value")?,
+            },
+            _ => return Err(MerdeError::UnknownVariant(op.to_string()).at_key("op")),
+        })
+    }
+
+    #[inline(always)]
+    fn from_value_ref<'val>(value: Option<&'val Value<'s>>) -> Result<Self, MerdeError> {
+        Self::from_value(value.cloned())
+    }
+}
+
+impl ValueSerialize for PatchOperation<'_> {
+    fn to_value(&self) -> Value<'_> {
+        self.clone().into()
+    }
+}
+
+impl<'s> From<PatchOperation<'s>> for Value<'s> {
+    fn from(op: PatchOperation<'s>) -> Self {
+        let map = Map::new().with("op", op.name());
+        let map = match op {
+            PatchOperation::Add { path, value }
+            | PatchOperation::Replace { path, value }
+            | PatchOperation::Test { path, value } => map.with("path", path).with("value", value),
+            PatchOperation::Remove { path } => map.with("path", path),
+            PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                map.with("from", from).with("path", path)
+            }
+        };
+        Value::Map(map)
+    }
+}
+
+impl<'s> Value<'s> {
+    /// Applies a JSON Patch (RFC 6902) to `self`, e.g. one deserialized from
+    /// `[{"op": "add", "path": "/a", "value": 1}]`.
+    ///
+    /// Operations are applied in order. If one of them fails, the error says which
+    /// one (see [MerdeError::PatchFailed]) and `self` is left untouched: a patch
+    /// is applied entirely, or not at all.
+    ///
+    /// ```
+    /// use merde_core::{from_value, Array, Map, PatchOperation, Value};
+    ///
+    /// let patch: Vec<PatchOperation> = from_value(Value::from(Array::new().with(
+    ///     Map::new().with("op", "add").with("path", "/b").with("value", 2),
+    /// )))
+    /// .unwrap();
+    /// let mut doc = Value::from(Map::new().with("a", 1));
+    /// doc.apply_patch(&patch).unwrap();
+    /// assert_eq!(doc, Value::from(Map::new().with("a", 1).with("b", 2)));
+    /// ```
+    pub fn apply_patch(&mut self, patch: &[PatchOperation<'s>]) -> Result<(), MerdeError> {
+        let mut patched = self.clone();
+        for (index, op) in patch.iter().enumerate() {
+            op.apply(&mut patched)
+                .map_err(|error| MerdeError::PatchFailed {
+                    index,
+                    op: op.name(),
+                    error: Box::new(error),
+                })?;
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Applies a JSON Merge Patch (RFC 7396) to `self`: maps in `patch` are merged
+    /// into maps in `self` recursively, `null` removes a key, and anything else
+    /// replaces what was there.
+    pub fn merge_patch(&mut self, patch: &Value<'s>) {
+        let Value::Map(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if !matches!(self, Value::Map(_)) {
+            *self = Value::Map(Map::new());
+        }
+        let Value::Map(target) = self else {
+            unreachable!()
+        };
+        for (key, value) in patch.iter() {
+            if let Value::Null = value {
+                target.remove(key);
+            } else {
+                target
+                    .entry(key.clone())
+                    .or_insert(Value::Null)
+                    .merge_patch(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PatchOperation;
+    use crate::{from_value, Array, Map, MerdeError, Value, ValueSerialize};
+
+    fn obj<'s>(entries: Vec<(&'s str, Value<'s>)>) -> Value<'s> {
+        Value::Map(
+            entries
+                .into_iter()
+                .fold(Map::new(), |map, (k, v)| map.with(k, v)),
+        )
+    }
+
+    fn arr(items: Vec<Value<'_>>) -> Value<'_> {
+        Value::Array(Array::from(items))
+    }
+
+    fn s(s: &str) -> Value<'_> {
+        Value::from(s)
+    }
+
+    fn patch(ops: Vec<Value<'_>>) -> Vec<PatchOperation<'_>> {
+        from_value(arr(ops)).unwrap()
+    }
+
+    /// Applies `ops` to `doc`, making sure a failed patch leaves `doc` as it was
+    fn apply<'s>(doc: &Value<'s>, ops: Vec<Value<'s>>) -> Result<Value<'s>, MerdeError> {
+        let mut patched = doc.clone();
+        let res = patched.apply_patch(&patch(ops));
+        match res {
+            Ok(()) => Ok(patched),
+            Err(e) => {
+                assert_eq!(&patched, doc);
+                Err(e)
+            }
+        }
+    }
+
+    // RFC 6902, appendix A
+    #[test]
+    fn test_rfc6902_examples() {
+        // A.1. Adding an Object Member
+        let doc = obj(vec![("foo", s("bar"))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/baz")),
+            ("value", s("qux")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![("baz", s("qux")), ("foo", s("bar"))])
+        );
+
+        // A.2. Adding an Array Element
+        let doc = obj(vec![("foo", arr(vec![s("bar"), s("baz")]))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/foo/1")),
+            ("value", s("qux")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![("foo", arr(vec![s("bar"), s("qux"), s("baz")]))])
+        );
+
+        // A.3. Removing an Object Member
+        let doc = obj(vec![("baz", s("qux")), ("foo", s("bar"))]);
+        let ops = vec![obj(vec![("op", s("remove")), ("path", s("/baz"))])];
+        assert_eq!(apply(&doc, ops).unwrap(), obj(vec![("foo", s("bar"))]));
+
+        // A.4. Removing an Array Element
+        let doc = obj(vec![("foo", arr(vec![s("bar"), s("qux"), s("baz")]))]);
+        let ops = vec![obj(vec![("op", s("remove")), ("path", s("/foo/1"))])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![("foo", arr(vec![s("bar"), s("baz")]))])
+        );
+
+        // A.5. Replacing a Value
+        let doc = obj(vec![("baz", s("qux")), ("foo", s("bar"))]);
+        let ops = vec![obj(vec![
+            ("op", s("replace")),
+            ("path", s("/baz")),
+            ("value", s("boo")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![("baz", s("boo")), ("foo", s("bar"))])
+        );
+
+        // A.6. Moving a Value
+        let doc = obj(vec![
+            ("foo", obj(vec![("bar", s("baz")), ("waldo", s("fred"))])),
+            ("qux", obj(vec![("corge", s("grault"))])),
+        ]);
+        let ops = vec![obj(vec![
+            ("op", s("move")),
+            ("from", s("/foo/waldo")),
+            ("path", s("/qux/thud")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![
+                ("foo", obj(vec![("bar", s("baz"))])),
+                (
+                    "qux",
+                    obj(vec![("corge", s("grault")), ("thud", s("fred"))])
+                ),
+            ])
+        );
+
+        // A.7. Moving an Array Element
+        let doc = obj(vec![(
+            "foo",
+            arr(vec![s("all"), s("grass"), s("cows"), s("eat")]),
+        )]);
+        let ops = vec![obj(vec![
+            ("op", s("move")),
+            ("from", s("/foo/1")),
+            ("path", s("/foo/3")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![(
+                "foo",
+                arr(vec![s("all"), s("cows"), s("eat"), s("grass")])
+            )])
+        );
+
+        // A.8. Testing a Value: Success
+        let doc = obj(vec![
+            ("baz", s("qux")),
+            ("foo", arr(vec![s("a"), Value::Int(2), s("c")])),
+        ]);
+        let ops = vec![
+            obj(vec![
+                ("op", s("test")),
+                ("path", s("/baz")),
+                ("value", s("qux")),
+            ]),
+            obj(vec![
+                ("op", s("test")),
+                ("path", s("/foo/1")),
+                ("value", Value::Int(2)),
+            ]),
+        ];
+        assert_eq!(apply(&doc, ops).unwrap(), doc);
+
+        // A.9. Testing a Value: Error
+        let doc = obj(vec![("baz", s("qux"))]);
+        let ops = vec![obj(vec![
+            ("op", s("test")),
+            ("path", s("/baz")),
+            ("value", s("bar")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap_err().to_string(),
+            "Patch operation 0 (test) failed: Test failed: the value is different (at $.baz)"
+        );
+
+        // A.10. Adding a Nested Member Object
+        let doc = obj(vec![("foo", s("bar"))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/child")),
+            ("value", obj(vec![("grandchild", obj(vec![]))])),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![
+                ("foo", s("bar")),
+                ("child", obj(vec![("grandchild", obj(vec![]))])),
+            ])
+        );
+
+        // A.11. Ignoring Unrecognized Elements
+        let doc = obj(vec![("foo", s("bar"))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/baz")),
+            ("value", s("qux")),
+            ("xyz", Value::Int(123)),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![("foo", s("bar")), ("baz", s("qux"))])
+        );
+
+        // A.12. Adding to a Nonexistent Target
+        let doc = obj(vec![("foo", s("bar"))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/baz/bat")),
+            ("value", s("qux")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap_err().to_string(),
+            "Patch operation 0 (add) failed: Missing property: baz (at $.baz)"
+        );
+
+        // A.13. Invalid JSON Patch Document: this one has a duplicate "op" member,
+        // which is for the JSON parser to deal with, there's no way to tell from a [Value].
+
+        // A.14. ~ Escape Ordering
+        let doc = obj(vec![("/", Value::Int(9)), ("~1", Value::Int(10))]);
+        let ops = vec![obj(vec![
+            ("op", s("test")),
+            ("path", s("/~01")),
+            ("value", Value::Int(10)),
+        ])];
+        assert_eq!(apply(&doc, ops).unwrap(), doc);
+
+        // A.15. Comparing Strings and Numbers
+        let ops = vec![obj(vec![
+            ("op", s("test")),
+            ("path", s("/~01")),
+            ("value", s("10")),
+        ])];
+        assert!(matches!(
+            apply(&doc, ops),
+            Err(MerdeError::PatchFailed {
+                index: 0,
+                op: "test",
+                ..
+            })
+        ));
+
+        // A.16. Adding an Array Value
+        let doc = obj(vec![("foo", arr(vec![s("bar")]))]);
+        let ops = vec![obj(vec![
+            ("op", s("add")),
+            ("path", s("/foo/-")),
+            ("value", arr(vec![s("abc"), s("def")])),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![(
+                "foo",
+                arr(vec![s("bar"), arr(vec![s("abc"), s("def")])])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_atomicity_and_errors() {
+        let doc = obj(vec![("a", arr(vec![Value::Int(1)])), ("b", obj(vec![]))]);
+
+        // the first two operations succeed, the third one makes the whole patch fail
+        let ops = vec![
+            obj(vec![("op", s("remove")), ("path", s("/b"))]),
+            obj(vec![
+                ("op", s("add")),
+                ("path", s("/a/0")),
+                ("value", Value::Int(0)),
+            ]),
+            obj(vec![
+                ("op", s("replace")),
+                ("path", s("/a/5")),
+                ("value", Value::Null),
+            ]),
+        ];
+        let err = apply(&doc, ops).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Patch operation 2 (replace) failed: Index out of bounds: index 5 is not valid for length 2 (at $.a[5])"
+        );
+        assert_eq!(err.path().unwrap().to_string(), "$.a[5]");
+
+        let ops = vec![obj(vec![
+            ("op", s("move")),
+            ("from", s("/a")),
+            ("path", s("/a/0")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap_err().to_string(),
+            r#"Patch operation 0 (move) failed: Invalid JSON pointer: can't move "/a" into one of its own children"#
+        );
+
+        let ops = vec![obj(vec![
+            ("op", s("copy")),
+            ("from", s("/a")),
+            ("path", s("/b/a")),
+        ])];
+        assert_eq!(
+            apply(&doc, ops).unwrap(),
+            obj(vec![
+                ("a", arr(vec![Value::Int(1)])),
+                ("b", obj(vec![("a", arr(vec![Value::Int(1)]))])),
+            ])
+        );
+
+        // numbers are compared by value
+        let ops = vec![obj(vec![
+            ("op", s("test")),
+            ("path", s("/a/0")),
+            ("value", Value::Float(1.0)),
+        ])];
+        assert_eq!(apply(&doc, ops).unwrap(), doc);
+
+        // malformed patches
+        let err =
+            from_value::<Vec<PatchOperation>>(arr(vec![obj(vec![("op", s("jump"))])])).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown variant: jump (at $[0].op)");
+        let err = from_value::<Vec<PatchOperation>>(arr(vec![
+            obj(vec![("op", s("remove")), ("path", s("/a"))]),
+            obj(vec![("op", s("add")), ("path", s("/a"))]),
+        ]))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Missing property: value (at $[1].value)");
+    }
+
+    #[test]
+    fn test_to_value() {
+        let ops = vec![
+            obj(vec![
+                ("op", s("add")),
+                ("path", s("/a")),
+                ("value", Value::Null),
+            ]),
+            obj(vec![("op", s("remove")), ("path", s("/a"))]),
+            obj(vec![
+                ("op", s("move")),
+                ("from", s("/a")),
+                ("path", s("/b")),
+            ]),
+        ];
+        assert_eq!(patch(ops.clone()).to_value(), arr(ops));
+    }
+
+    // RFC 7396, appendix A
+    #[test]
+    fn test_rfc7396_examples() {
+        let null = || Value::Null;
+        let cases = vec![
+            (
+                obj(vec![("a", s("b"))]),
+                obj(vec![("a", s("c"))]),
+                obj(vec![("a", s("c"))]),
+            ),
+            (
+                obj(vec![("a", s("b"))]),
+                obj(vec![("b", s("c"))]),
+                obj(vec![("a", s("b")), ("b", s("c"))]),
+            ),
+            (
+                obj(vec![("a", s("b"))]),
+                obj(vec![("a", null())]),
+                obj(vec![]),
+            ),
+            (
+                obj(vec![("a", s("b")), ("b", s("c"))]),
+                obj(vec![("a", null())]),
+                obj(vec![("b", s("c"))]),
+            ),
+            (
+                obj(vec![("a", arr(vec![s("b")]))]),
+                obj(vec![("a", s("c"))]),
+                obj(vec![("a", s("c"))]),
+            ),
+            (
+                obj(vec![("a", s("c"))]),
+                obj(vec![("a", arr(vec![s("b")]))]),
+                obj(vec![("a", arr(vec![s("b")]))]),
+            ),
+            (
+                obj(vec![("a", obj(vec![("b", s("c"))]))]),
+                obj(vec![("a", obj(vec![("b", s("d")), ("c", null())]))]),
+                obj(vec![("a", obj(vec![("b", s("d"))]))]),
+            ),
+            (
+                obj(vec![("a", arr(vec![obj(vec![("b", s("c"))])]))]),
+                obj(vec![("a", arr(vec![Value::Int(1)]))]),
+                obj(vec![("a", arr(vec![Value::Int(1)]))]),
+            ),
+            (
+                arr(vec![s("a"), s("b")]),
+                arr(vec![s("c"), s("d")]),
+                arr(vec![s("c"), s("d")]),
+            ),
+            (
+                obj(vec![("a", s("b"))]),
+                arr(vec![s("c")]),
+                arr(vec![s("c")]),
+            ),
+            (obj(vec![("a", s("foo"))]), null(), null()),
+            (obj(vec![("a", s("foo"))]), s("bar"), s("bar")),
+            (
+                obj(vec![("e", null())]),
+                obj(vec![("a", Value::Int(1))]),
+                obj(vec![("e", null()), ("a", Value::Int(1))]),
+            ),
+            (
+                arr(vec![Value::Int(1), Value::Int(2)]),
+                obj(vec![("a", s("b")), ("c", null())]),
+                obj(vec![("a", s("b"))]),
+            ),
+            (
+                obj(vec![]),
+                obj(vec![("a", obj(vec![("bb", obj(vec![("ccc", null())]))]))]),
+                obj(vec![("a", obj(vec![("bb", obj(vec![]))]))]),
+            ),
+        ];
+        for (mut target, patch, expected) in cases {
+            target.merge_patch(&patch);
+            assert_eq!(target, expected, "patch: {patch:?}");
+        }
+    }
+}
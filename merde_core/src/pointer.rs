@@ -0,0 +1,442 @@
+//! [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901) support for [Value]:
+//! strings like `/users/0/name` that designate a value inside a document.
+
+use std::borrow::Cow;
+
+use crate::{MerdeError, PathSegment, Value, ValueDeserialize, ValueType};
+
+/// Splits a JSON Pointer into its reference tokens, unescaping `~1` into `/`
+/// and `~0` into `~`. The empty pointer designates the whole document, and
+/// has no tokens.
+pub(crate) fn parse_pointer(pointer: &str) -> Result<Vec<Cow<'_, str>>, MerdeError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(MerdeError::InvalidPointer(format!(
+            "{pointer:?} doesn't start with a slash"
+        )));
+    };
+    rest.split('/')
+        .map(|token| {
+            if !token.contains('~') {
+                return Ok(Cow::Borrowed(token));
+            }
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some('0') => unescaped.push('~'),
+                        Some('1') => unescaped.push('/'),
+                        _ => {
+                            return Err(MerdeError::InvalidPointer(format!(
+                                "{pointer:?} has a `~` that isn't followed by `0` or `1`"
+                            )))
+                        }
+                    },
+                    c => unescaped.push(c),
+                }
+            }
+            Ok(Cow::Owned(unescaped))
+        })
+        .collect()
+}
+
+/// Escapes `token` so it can be used as part of a JSON Pointer: `~` becomes
+/// `~0` and `/` becomes `~1`.
+pub fn escape_pointer_token(token: &str) -> Cow<'_, str> {
+    if token.contains(['~', '/']) {
+        Cow::Owned(token.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// Parses an array index the way RFC 6901 spells them: digits, without leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    let valid = token == "0"
+        || (token.starts_with(|c: char| matches!(c, '1'..='9'))
+            && token.bytes().all(|b| b.is_ascii_digit()));
+    if valid {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Follows `token` one level down from `value`.
+fn child<'v, 's>(value: &'v Value<'s>, token: &str) -> Option<&'v Value<'s>> {
+    match value {
+        Value::Map(map) => map.get(token),
+        Value::Array(arr) => arr.get(parse_index(token)?),
+        _ => None,
+    }
+}
+
+fn child_mut<'v, 's>(value: &'v mut Value<'s>, token: &str) -> Option<&'v mut Value<'s>> {
+    match value {
+        Value::Map(map) => map.get_mut(token),
+        Value::Array(arr) => arr.get_mut(parse_index(token)?),
+        _ => None,
+    }
+}
+
+/// How `token` shows up in error paths when it's followed from `value`.
+fn segment(value: &Value<'_>, token: &str) -> PathSegment {
+    match (value, parse_index(token)) {
+        (Value::Array(_), Some(index)) => PathSegment::Index(index),
+        _ => PathSegment::Key(token.to_owned().into()),
+    }
+}
+
+/// Why `token` can't be followed from `value`.
+fn not_found(value: &Value<'_>, token: &str) -> MerdeError {
+    match value {
+        Value::Map(_) => {
+            MerdeError::MissingProperty(token.to_owned().into()).at_key(token.to_owned())
+        }
+        Value::Array(arr) => {
+            let index = match (token, parse_index(token)) {
+                ("-", _) => arr.len(),
+                (_, Some(index)) => index,
+                (_, None) => {
+                    return MerdeError::InvalidPointer(format!("{token:?} is not an array index"))
+                }
+            };
+            MerdeError::IndexOutOfBounds {
+                index,
+                len: arr.len(),
+            }
+            .at_index(index)
+        }
+        other => MerdeError::MismatchedType {
+            expected: if parse_index(token).is_some() {
+                ValueType::Array
+            } else {
+                ValueType::Map
+            },
+            found: other.value_type(),
+        },
+    }
+}
+
+/// Records `path` (root first) in `error`.
+pub(crate) fn at_path(error: MerdeError, path: &[PathSegment]) -> MerdeError {
+    path.iter().rev().fold(error, |e, s| e.at(s.clone()))
+}
+
+impl<'s> Value<'s> {
+    /// Looks up a value by [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901),
+    /// e.g. `/users/0/name`. The empty string designates `self`.
+    ///
+    /// Returns `None` if the pointer is malformed, or doesn't lead anywhere.
+    ///
+    /// ```
+    /// use merde_core::{Array, Map, Value};
+    ///
+    /// let value = Value::from(Map::new().with("a/b", Array::new().with(1).with(2)));
+    /// assert_eq!(value.pointer("/a~1b/1"), Some(&Value::Int(2)));
+    /// assert_eq!(value.pointer("/a~1b/2"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&Value<'s>> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| child(value, token))
+    }
+
+    /// Like [Value::pointer], but lets you modify the value that's found.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value<'s>> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))
+    }
+
+    /// Looks up a value by JSON Pointer, see [Value::pointer], and deserializes it.
+    ///
+    /// Errors carry the path the pointer leads to: a missing key is reported as
+    /// [MerdeError::MissingProperty], a missing array element as [MerdeError::IndexOutOfBounds].
+    /// Like [Map::must_get](crate::Map::must_get), this doesn't fail when `T` is an [Option].
+    pub fn pointer_get<T>(&self, pointer: &str) -> Result<T, MerdeError>
+    where
+        T: ValueDeserialize<'s>,
+    {
+        let tokens = parse_pointer(pointer)?;
+        let mut path = Vec::new();
+        match self.walk(&tokens, &mut path) {
+            Ok(value) => T::from_value_ref(Some(value)).map_err(|e| at_path(e, &path)),
+            Err(e) => {
+                let absent = matches!(
+                    e.without_path(),
+                    MerdeError::MissingProperty(_) | MerdeError::IndexOutOfBounds { .. }
+                );
+                match T::from_value_ref(None) {
+                    Ok(t) if absent => Ok(t),
+                    _ => Err(at_path(e, &path)),
+                }
+            }
+        }
+    }
+
+    /// Adds `value` at the place designated by a JSON Pointer, the way JSON Patch's
+    /// `add` operation does: an existing key in a map is replaced (and its previous
+    /// value returned), an index in an array is inserted at, shifting the elements
+    /// after it, and `-` appends to an array. The empty pointer replaces `self`.
+    ///
+    /// Everything but the last token must lead to an existing map or array.
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: Value<'s>,
+    ) -> Result<Option<Value<'s>>, MerdeError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        let mut path = Vec::new();
+        let parent = self
+            .walk_mut(parents, &mut path)
+            .map_err(|e| at_path(e, &path))?;
+        match parent {
+            Value::Map(map) => Ok(map.insert(last.clone().into_owned().into(), value)),
+            Value::Array(arr) if last == "-" => {
+                arr.push(value);
+                Ok(None)
+            }
+            Value::Array(arr) if parse_index(last).is_some_and(|index| index <= arr.len()) => {
+                arr.insert(parse_index(last).unwrap(), value);
+                Ok(None)
+            }
+            parent => Err(at_path(not_found(parent, last), &path)),
+        }
+    }
+
+    /// Removes the value designated by a JSON Pointer and returns it. Elements after
+    /// it in an array are shifted. The empty pointer leaves [Value::Null] in place
+    /// of `self`.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<Value<'s>, MerdeError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(std::mem::replace(self, Value::Null));
+        };
+        let mut path = Vec::new();
+        let parent = self
+            .walk_mut(parents, &mut path)
+            .map_err(|e| at_path(e, &path))?;
+        let removed = match parent {
+            Value::Map(map) => map.remove(last.as_ref()),
+            Value::Array(arr) => match parse_index(last) {
+                Some(index) if index < arr.len() => Some(arr.remove(index)),
+                _ => None,
+            },
+            _ => None,
+        };
+        removed.ok_or_else(|| at_path(not_found(parent, last), &path))
+    }
+
+    /// Like [Value::pointer], but says why the pointer doesn't lead anywhere.
+    /// Also returns the path to the value, for error reporting.
+    pub(crate) fn pointer_target(
+        &self,
+        pointer: &str,
+    ) -> Result<(&Value<'s>, Vec<PathSegment>), MerdeError> {
+        let tokens = parse_pointer(pointer)?;
+        let mut path = Vec::new();
+        match self.walk(&tokens, &mut path) {
+            Ok(value) => Ok((value, path)),
+            Err(e) => Err(at_path(e, &path)),
+        }
+    }
+
+    /// Like [Value::pointer_mut], but says why the pointer doesn't lead anywhere.
+    pub(crate) fn pointer_target_mut(
+        &mut self,
+        pointer: &str,
+    ) -> Result<&mut Value<'s>, MerdeError> {
+        let tokens = parse_pointer(pointer)?;
+        let mut path = Vec::new();
+        self.walk_mut(&tokens, &mut path)
+            .map_err(|e| at_path(e, &path))
+    }
+
+    /// Follows `tokens` from `self`, recording the way in `path`. On failure, `path`
+    /// leads to the value `not_found` complains about.
+    fn walk<'v>(
+        &'v self,
+        tokens: &[Cow<'_, str>],
+        path: &mut Vec<PathSegment>,
+    ) -> Result<&'v Value<'s>, MerdeError> {
+        let mut current = self;
+        for token in tokens {
+            let next = child(current, token).ok_or_else(|| not_found(current, token))?;
+            path.push(segment(current, token));
+            current = next;
+        }
+        Ok(current)
+    }
+
+    fn walk_mut<'v>(
+        &'v mut self,
+        tokens: &[Cow<'_, str>],
+        path: &mut Vec<PathSegment>,
+    ) -> Result<&'v mut Value<'s>, MerdeError> {
+        let mut current = self;
+        for token in tokens {
+            if child(current, token).is_none() {
+                return Err(not_found(current, token));
+            }
+            path.push(segment(current, token));
+            current = child_mut(current, token).unwrap();
+        }
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_pointer_token, parse_pointer};
+    use crate::{Array, Map, MerdeError, Value};
+
+    /// The example document from RFC 6901, section 5
+    fn rfc_document() -> Value<'static> {
+        Value::Map(
+            Map::new()
+                .with("foo", Array::new().with("bar").with("baz"))
+                .with("", 0)
+                .with("a/b", 1)
+                .with("c%d", 2)
+                .with("e^f", 3)
+                .with("g|h", 4)
+                .with("i\\j", 5)
+                .with("k\"l", 6)
+                .with(" ", 7)
+                .with("m~n", 8),
+        )
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        let doc = rfc_document();
+        assert_eq!(doc.pointer(""), Some(&doc));
+        assert_eq!(
+            doc.pointer("/foo"),
+            Some(&Value::Array(Array::new().with("bar").with("baz")))
+        );
+        assert_eq!(doc.pointer("/foo/0"), Some(&Value::from("bar")));
+        for (pointer, expected) in [
+            ("/", 0),
+            ("/a~1b", 1),
+            ("/c%d", 2),
+            ("/e^f", 3),
+            ("/g|h", 4),
+            ("/i\\j", 5),
+            ("/k\"l", 6),
+            ("/ ", 7),
+            ("/m~0n", 8),
+        ] {
+            assert_eq!(
+                doc.pointer(pointer),
+                Some(&Value::Int(expected)),
+                "{pointer}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_syntax() {
+        assert_eq!(parse_pointer("/~01/~10").unwrap(), ["~1", "/0"]);
+        for invalid in ["foo", "/~", "/~2", "/a~"] {
+            assert!(
+                matches!(parse_pointer(invalid), Err(MerdeError::InvalidPointer(_))),
+                "{invalid}"
+            );
+        }
+
+        let doc = rfc_document();
+        for missing in ["foo", "/foo/01", "/foo/-", "/foo/2", "/foo/0/x", "/nope"] {
+            assert_eq!(doc.pointer(missing), None, "{missing}");
+        }
+
+        assert_eq!(escape_pointer_token("plain"), "plain");
+        assert_eq!(escape_pointer_token("~a/b"), "~0a~1b");
+    }
+
+    #[test]
+    fn test_pointer_get() {
+        let doc = rfc_document();
+        assert_eq!(doc.pointer_get::<String>("/foo/1").unwrap(), "baz");
+        assert_eq!(doc.pointer_get::<Option<u8>>("/nope").unwrap(), None);
+        assert_eq!(doc.pointer_get::<Option<u8>>("/foo/7").unwrap(), None);
+
+        let err = doc.pointer_get::<u8>("/foo/7").unwrap_err();
+        assert!(matches!(
+            err.without_path(),
+            MerdeError::IndexOutOfBounds { index: 7, len: 2 }
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "$.foo[7]");
+
+        let err = doc.pointer_get::<u8>("/a~1b/c").unwrap_err();
+        assert_eq!(err.to_string(), r#"Expected Map, found Int (at $["a/b"])"#);
+
+        let err = doc.pointer_get::<u8>("/foo/0").unwrap_err();
+        assert_eq!(err.to_string(), "Expected Int, found String (at $.foo[0])");
+
+        let err = doc.pointer_get::<u8>("/foo/x").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Invalid JSON pointer: "x" is not an array index (at $.foo)"#
+        );
+
+        assert_eq!(doc.pointer_get::<Option<u8>>("/nope/deeper").unwrap(), None);
+        let err = doc.pointer_get::<u8>("/nope/deeper").unwrap_err();
+        assert_eq!(err.to_string(), "Missing property: nope (at $.nope)");
+    }
+
+    #[test]
+    fn test_mutation() {
+        let mut doc = rfc_document();
+        *doc.pointer_mut("/foo/0").unwrap() = Value::Bool(true);
+        assert_eq!(doc.pointer("/foo/0"), Some(&Value::Bool(true)));
+
+        assert_eq!(doc.pointer_insert("/foo/1", Value::Int(1)).unwrap(), None);
+        assert_eq!(doc.pointer_insert("/foo/-", Value::Int(3)).unwrap(), None);
+        assert_eq!(doc.pointer_insert("/foo/4", Value::Int(4)).unwrap(), None);
+        assert_eq!(
+            doc.pointer("/foo"),
+            Some(&Value::Array(
+                Array::new().with(true).with(1).with("baz").with(3).with(4)
+            ))
+        );
+        assert_eq!(
+            doc.pointer_insert("/m~0n", Value::Null).unwrap(),
+            Some(Value::Int(8))
+        );
+        assert_eq!(doc.pointer_insert("/new", Value::Null).unwrap(), None);
+
+        let err = doc.pointer_insert("/foo/9", Value::Null).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Index out of bounds: index 9 is not valid for length 5 (at $.foo[9])"
+        );
+        let err = doc.pointer_insert("/a/b", Value::Null).unwrap_err();
+        assert_eq!(err.to_string(), "Missing property: a (at $.a)");
+
+        assert_eq!(doc.pointer_remove("/foo/1").unwrap(), Value::Int(1));
+        assert_eq!(doc.pointer_remove("/a~1b").unwrap(), Value::Int(1));
+        assert_eq!(doc.pointer("/a~1b"), None);
+        let err = doc.pointer_remove("/foo/4").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Index out of bounds: index 4 is not valid for length 4 (at $.foo[4])"
+        );
+        let err = doc.pointer_remove("/e^f/x").unwrap_err();
+        assert_eq!(err.to_string(), r#"Expected Map, found Int (at $["e^f"])"#);
+
+        let old = doc.pointer_insert("", Value::Int(0)).unwrap();
+        assert!(matches!(old, Some(Value::Map(_))));
+        assert_eq!(doc.pointer_remove("").unwrap(), Value::Int(0));
+        assert_eq!(doc, Value::Null);
+    }
+}
@@ -0,0 +1,278 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{Array, CowStr, Map, Value};
+
+/// Types that can be turned into a [`Value`], the other way around from
+/// [`ValueDeserialize`](crate::ValueDeserialize).
+///
+/// Implementations are provided for everything that can be serialized to JSON:
+/// primitive types, strings, [`Vec`], [`HashMap`], [`Option`] (`None` is [`Value::Null`]),
+/// tuples (as arrays), etc.
+///
+/// Strings are borrowed from `self` rather than cloned, call
+/// [`IntoStatic::into_static`](crate::IntoStatic::into_static) on the result
+/// if you need it to outlive `self`.
+pub trait ValueSerialize {
+    /// Builds a [`Value`] out of `self`.
+    fn to_value(&self) -> Value<'_>;
+}
+
+/// [`ValueSerialize`] types that always serialize as a [`Value::Map`].
+///
+/// Internally tagged enums (`#[merde(tag = "...")]`) require it of the content
+/// of their newtype variants, since the tag is added to that map. It's derived
+/// along with `ValueSerialize` for structs, and implemented for [`Map`] and [`HashMap`].
+pub trait ValueSerializeMap: ValueSerialize {
+    /// Builds a [`Map`] out of `self`, which `to_value` wraps in a [`Value::Map`].
+    fn to_map(&self) -> Map<'_>;
+}
+
+impl<T> ValueSerialize for &T
+where
+    T: ?Sized + ValueSerialize,
+{
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        (**self).to_value()
+    }
+}
+
+impl<T> ValueSerializeMap for &T
+where
+    T: ?Sized + ValueSerializeMap,
+{
+    #[inline(always)]
+    fn to_map(&self) -> Map<'_> {
+        (**self).to_map()
+    }
+}
+
+impl ValueSerialize for Value<'_> {
+    fn to_value(&self) -> Value<'_> {
+        self.clone()
+    }
+}
+
+impl ValueSerialize for Map<'_> {
+    fn to_value(&self) -> Value<'_> {
+        Value::Map(self.clone())
+    }
+}
+
+impl ValueSerializeMap for Map<'_> {
+    fn to_map(&self) -> Map<'_> {
+        self.clone()
+    }
+}
+
+impl ValueSerialize for Array<'_> {
+    fn to_value(&self) -> Value<'_> {
+        Value::Array(self.clone())
+    }
+}
+
+impl ValueSerialize for str {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        Value::Str(CowStr::Borrowed(self))
+    }
+}
+
+impl ValueSerialize for String {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        self.as_str().to_value()
+    }
+}
+
+impl ValueSerialize for Cow<'_, str> {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        self.as_ref().to_value()
+    }
+}
+
+impl ValueSerialize for CowStr<'_> {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        self.as_ref().to_value()
+    }
+}
+
+macro_rules! impl_value_serialize_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ValueSerialize for $ty {
+                #[inline(always)]
+                fn to_value(&self) -> Value<'_> {
+                    Value::Int(*self as i64)
+                }
+            }
+        )*
+    };
+}
+
+impl_value_serialize_for_int!(u8, u16, u32, i8, i16, i32, i64, isize);
+
+macro_rules! impl_value_serialize_for_big_uint {
+    ($($ty:ty),*) => {
+        $(
+            /// Values that don't fit in an `i64` become a (lossy) [`Value::Float`].
+            impl ValueSerialize for $ty {
+                #[inline(always)]
+                fn to_value(&self) -> Value<'_> {
+                    match i64::try_from(*self) {
+                        Ok(i) => Value::Int(i),
+                        Err(_) => Value::Float(*self as f64),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_value_serialize_for_big_uint!(u64, usize);
+
+impl ValueSerialize for f32 {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        Value::Float(*self as f64)
+    }
+}
+
+impl ValueSerialize for f64 {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        Value::Float(*self)
+    }
+}
+
+impl ValueSerialize for bool {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        Value::Bool(*self)
+    }
+}
+
+impl<T: ValueSerialize> ValueSerialize for Option<T> {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: ValueSerialize> ValueSerialize for [T] {
+    fn to_value(&self) -> Value<'_> {
+        Value::Array(self.iter().map(|v| v.to_value()).collect::<Vec<_>>().into())
+    }
+}
+
+impl<T: ValueSerialize> ValueSerialize for Vec<T> {
+    #[inline(always)]
+    fn to_value(&self) -> Value<'_> {
+        self.as_slice().to_value()
+    }
+}
+
+impl<K: AsRef<str>, V: ValueSerialize> ValueSerialize for HashMap<K, V> {
+    fn to_value(&self) -> Value<'_> {
+        Value::Map(self.to_map())
+    }
+}
+
+impl<K: AsRef<str>, V: ValueSerialize> ValueSerializeMap for HashMap<K, V> {
+    fn to_map(&self) -> Map<'_> {
+        self.iter()
+            .map(|(k, v)| (CowStr::Borrowed(k.as_ref()), v.to_value()))
+            .collect()
+    }
+}
+
+macro_rules! impl_value_serialize_for_wrapper {
+    ($($wrapper:ident)::+) => {
+        impl<T: ?Sized + ValueSerialize> ValueSerialize for $($wrapper)::+<T> {
+            #[inline(always)]
+            fn to_value(&self) -> Value<'_> {
+                (**self).to_value()
+            }
+        }
+
+        impl<T: ?Sized + ValueSerializeMap> ValueSerializeMap for $($wrapper)::+<T> {
+            #[inline(always)]
+            fn to_map(&self) -> Map<'_> {
+                (**self).to_map()
+            }
+        }
+    };
+}
+
+impl_value_serialize_for_wrapper!(Box);
+impl_value_serialize_for_wrapper!(std::rc::Rc);
+impl_value_serialize_for_wrapper!(std::sync::Arc);
+
+macro_rules! impl_value_serialize_for_tuple {
+    ($($ty:ident $index:tt),+) => {
+        impl<$($ty: ValueSerialize),+> ValueSerialize for ($($ty,)+) {
+            fn to_value(&self) -> Value<'_> {
+                Value::Array(Array::new()$(.with(self.$index.to_value()))+)
+            }
+        }
+    };
+}
+
+impl_value_serialize_for_tuple!(T1 0);
+impl_value_serialize_for_tuple!(T1 0, T2 1);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2, T4 3);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_value_serialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, rc::Rc, sync::Arc};
+
+    use super::ValueSerialize;
+    use crate::{from_value, Array, CowStr, Map, Value};
+
+    #[test]
+    fn test_to_value() {
+        assert_eq!(3u8.to_value(), Value::Int(3));
+        assert_eq!(u64::MAX.to_value(), Value::Float(u64::MAX as f64));
+        assert_eq!(1.5f32.to_value(), Value::Float(1.5));
+        assert_eq!(Some(true).to_value(), Value::Bool(true));
+        assert_eq!(None::<bool>.to_value(), Value::Null);
+        assert_eq!(
+            (1, "two", vec![Box::new(3)]).to_value(),
+            Value::Array(Array::new().with(1).with("two").with(Array::new().with(3)))
+        );
+        assert_eq!(
+            HashMap::from([("a", Rc::new(1)), ("b", Rc::new(2))]).to_value(),
+            Value::Map(Map::new().with("a", 1).with("b", 2))
+        );
+
+        let owned = String::from("borrowed");
+        assert!(matches!(
+            owned.to_value(),
+            Value::Str(CowStr::Borrowed("borrowed"))
+        ));
+        let shared: Arc<str> = "shared".into();
+        assert_eq!(shared.to_value(), Value::Str("shared".into()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        type Tuple<'s> = (Vec<Option<u32>>, HashMap<String, (bool, i64)>, CowStr<'s>);
+
+        let original: Tuple = (
+            vec![Some(1u32), None],
+            HashMap::from([(String::from("k"), (true, -4i64))]),
+            CowStr::from("s"),
+        );
+        let back: Tuple = from_value(original.to_value()).unwrap();
+        assert_eq!(back, original);
+    }
+}
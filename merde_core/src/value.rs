@@ -100,7 +100,7 @@ impl<'s> From<Vec<Value<'s>>> for Value<'s> {
 
 impl<'s> From<HashMap<CowStr<'s>, Value<'s>>> for Value<'s> {
     fn from(v: HashMap<CowStr<'s>, Value<'s>>) -> Self {
-        Value::Map(Map(v))
+        Value::Map(v.into())
     }
 }
 
@@ -40,7 +40,7 @@ impl<'a, 's, B: ToOwned + ?Sized + 's> WithLifetime<'s> for Cow<'a, B> {
     type Lifetimed = Cow<'s, B>;
 }
 
-impl<'a, 's> WithLifetime<'s> for &'a str {
+impl<'s> WithLifetime<'s> for &str {
     type Lifetimed = &'s str;
 }
 
@@ -0,0 +1,122 @@
+use syn::LitStr;
+
+/// The casing conventions `#[merde(rename_all = "...")]` knows about.
+#[derive(Clone, Copy)]
+pub(crate) enum RenameRule {
+    /// `camelCase`
+    Camel,
+    /// `snake_case`
+    Snake,
+    /// `kebab-case`
+    Kebab,
+    /// `SCREAMING_SNAKE_CASE`
+    ScreamingSnake,
+    /// `PascalCase`
+    Pascal,
+}
+
+impl RenameRule {
+    pub(crate) fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "PascalCase" => RenameRule::Pascal,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "unknown rename rule, expected one of: camelCase, snake_case, kebab-case, SCREAMING_SNAKE_CASE, PascalCase",
+                ))
+            }
+        })
+    }
+
+    /// Applies the rule to a Rust identifier, which may be `snake_case` (fields)
+    /// or `PascalCase` (variants).
+    pub(crate) fn apply(self, ident: &str) -> String {
+        let words = words(ident);
+        match self {
+            RenameRule::Camel => {
+                let mut out = String::new();
+                for (i, word) in words.iter().enumerate() {
+                    if i == 0 {
+                        out.push_str(word);
+                    } else {
+                        out.push_str(&capitalize(word));
+                    }
+                }
+                out
+            }
+            RenameRule::Pascal => words.iter().map(|w| capitalize(w)).collect(),
+            RenameRule::Snake => words.join("_"),
+            RenameRule::Kebab => words.join("-"),
+            RenameRule::ScreamingSnake => words.join("_").to_uppercase(),
+        }
+    }
+}
+
+/// Splits an identifier into lowercase words, on underscores and before
+/// uppercase letters: `user_id` and `UserId` both give `["user", "id"]`.
+fn words(ident: &str) -> Vec<String> {
+    let ident = ident.strip_prefix("r#").unwrap_or(ident);
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in ident.chars() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else if c.is_uppercase() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            current.extend(c.to_lowercase());
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenameRule::*;
+
+    #[test]
+    fn test_fields() {
+        for (rule, expected) in [
+            (Camel, "userId"),
+            (Snake, "user_id"),
+            (Kebab, "user-id"),
+            (ScreamingSnake, "USER_ID"),
+            (Pascal, "UserId"),
+        ] {
+            assert_eq!(rule.apply("user_id"), expected);
+        }
+    }
+
+    #[test]
+    fn test_variants() {
+        for (rule, expected) in [
+            (Camel, "stillActive"),
+            (Snake, "still_active"),
+            (Kebab, "still-active"),
+            (ScreamingSnake, "STILL_ACTIVE"),
+            (Pascal, "StillActive"),
+        ] {
+            assert_eq!(rule.apply("StillActive"), expected);
+        }
+    }
+}
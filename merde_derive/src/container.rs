@@ -0,0 +1,421 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, spanned::Spanned, Attribute, Data, DeriveInput, ExprPath, Fields, GenericParam,
+    Generics, Ident, Lifetime, LifetimeParam, LitStr, Type, WherePredicate,
+};
+
+use crate::case::RenameRule;
+
+/// A struct or enum we know how to derive impls for: zero or one lifetime
+/// parameter, any number of type parameters.
+pub(crate) struct Container<'a> {
+    pub(crate) ident: &'a Ident,
+    pub(crate) generics: &'a Generics,
+    pub(crate) lifetime: Option<&'a Lifetime>,
+    pub(crate) data: ContainerData<'a>,
+    /// `#[merde(deny_unknown_fields)]`
+    pub(crate) deny_unknown_fields: bool,
+}
+
+pub(crate) enum ContainerData<'a> {
+    /// A struct with named fields
+    Struct(Vec<Field<'a>>),
+
+    /// An enum, and how it's represented (see [`Repr`])
+    Enum(Repr, Vec<Variant<'a>>),
+}
+
+/// How enum variants are told apart, see <https://serde.rs/enum-representations.html>
+pub(crate) enum Repr {
+    /// `{"Variant": content}`, or `"Variant"` for unit variants
+    External,
+
+    /// `{"tag": "Variant", ...fields}`
+    Internal { tag: String },
+
+    /// `{"tag": "Variant", "content": content}`
+    Adjacent { tag: String, content: String },
+
+    /// Just the content, the first variant that deserializes wins.
+    Untagged,
+}
+
+pub(crate) struct Variant<'a> {
+    pub(crate) ident: &'a Ident,
+    /// The tag this variant is serialized as
+    pub(crate) name: String,
+    /// Other tags accepted when deserializing
+    pub(crate) aliases: Vec<String>,
+    pub(crate) style: Style,
+    pub(crate) fields: Vec<Field<'a>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    /// `Variant`
+    Unit,
+    /// `Variant(T)`
+    Newtype,
+    /// `Variant(T, U, ...)`
+    Tuple,
+    /// `Variant { a: T, ... }`
+    Struct,
+}
+
+pub(crate) struct Field<'a> {
+    /// `None` for tuple fields
+    pub(crate) ident: Option<&'a Ident>,
+    pub(crate) ty: &'a Type,
+    /// The key this field is serialized as, `None` for tuple fields
+    pub(crate) name: Option<String>,
+    /// Other keys accepted when deserializing
+    pub(crate) aliases: Vec<String>,
+    /// What to use when the key is missing (or the field is skipped)
+    pub(crate) default: Option<FieldDefault>,
+    /// Never serialized, always filled with its default when deserializing
+    pub(crate) skip: bool,
+}
+
+pub(crate) enum FieldDefault {
+    /// `#[merde(default)]`
+    Trait,
+    /// `#[merde(default = "path::to::fn")]`
+    Path(ExprPath),
+}
+
+impl Field<'_> {
+    /// The key this field is stored under in a map
+    pub(crate) fn key(&self) -> &str {
+        self.name.as_deref().expect("tuple fields don't have keys")
+    }
+
+    /// Every key this field may be found under when deserializing, the main
+    /// one first
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.key()).chain(self.aliases.iter().map(|a| a.as_str()))
+    }
+
+    /// An expression that builds this field's default value
+    pub(crate) fn default_expr(&self) -> Option<TokenStream> {
+        match &self.default {
+            Some(FieldDefault::Trait) => Some(quote!(::core::default::Default::default())),
+            Some(FieldDefault::Path(path)) => Some(quote!(#path())),
+            None if self.skip => Some(quote!(::core::default::Default::default())),
+            None => None,
+        }
+    }
+}
+
+impl Variant<'_> {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Every tag this variant may be found under when deserializing, the main
+    /// one first
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name()).chain(self.aliases.iter().map(|a| a.as_str()))
+    }
+
+    /// Identifiers to bind this variant's fields to when matching on it, e.g.
+    /// `__f0, __f1`
+    pub(crate) fn bindings(&self) -> Vec<Ident> {
+        (0..self.fields.len())
+            .map(|i| format_ident!("__f{}", i))
+            .collect()
+    }
+
+    /// A pattern (or constructor expression) for this variant, e.g. `Self::A(__f0, __f1)`
+    /// or `Self::B { x: __f0 }`, where each field is given by `values`.
+    pub(crate) fn construct(&self, path: &TokenStream, values: &[TokenStream]) -> TokenStream {
+        let ident = self.ident;
+        match self.style {
+            Style::Unit => quote!(#path::#ident),
+            Style::Newtype | Style::Tuple => quote!(#path::#ident(#(#values),*)),
+            Style::Struct => {
+                let names = self.fields.iter().map(|f| f.ident);
+                quote!(#path::#ident { #(#names: #values),* })
+            }
+        }
+    }
+}
+
+fn fields_from_ast(fields: &Fields, rename_all: Option<RenameRule>) -> syn::Result<Vec<Field<'_>>> {
+    fields
+        .iter()
+        .map(|f| {
+            let attrs = NameAttrs::from_ast(&f.attrs, AttrTarget::Field)?;
+            let Some(ident) = f.ident.as_ref() else {
+                if let Some(span) = attrs.span {
+                    return Err(syn::Error::new(
+                        span,
+                        "merde field attributes are only supported on named fields",
+                    ));
+                }
+                return Ok(Field {
+                    ident: None,
+                    ty: &f.ty,
+                    name: None,
+                    aliases: Vec::new(),
+                    default: None,
+                    skip: false,
+                });
+            };
+            let name = attrs.name(&ident.unraw().to_string(), rename_all);
+            Ok(Field {
+                ident: Some(ident),
+                ty: &f.ty,
+                name: Some(name),
+                aliases: attrs.aliases,
+                default: attrs.default,
+                skip: attrs.skip,
+            })
+        })
+        .collect()
+}
+
+impl<'a> Container<'a> {
+    pub(crate) fn from_ast(input: &'a DeriveInput) -> syn::Result<Self> {
+        let mut lifetimes = input.generics.lifetimes();
+        let lifetime = lifetimes.next().map(|lt| &lt.lifetime);
+        if let Some(extra) = lifetimes.next() {
+            return Err(syn::Error::new_spanned(
+                extra,
+                "merde can only derive impls for types with zero or one lifetime parameter",
+            ));
+        }
+
+        let attrs = ContainerAttrs::from_ast(&input.attrs)?;
+
+        let data = match &input.data {
+            Data::Struct(data) => {
+                if let Some(span) = attrs.enum_only_span {
+                    return Err(syn::Error::new(
+                        span,
+                        "`tag`, `content` and `untagged` only apply to enums",
+                    ));
+                }
+                match &data.fields {
+                    Fields::Named(_) => {
+                        ContainerData::Struct(fields_from_ast(&data.fields, attrs.rename_all)?)
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            &input.ident,
+                            "merde can only derive impls for structs with named fields",
+                        ))
+                    }
+                }
+            }
+            Data::Enum(data) => {
+                let repr = attrs.repr()?;
+                let variants = data
+                    .variants
+                    .iter()
+                    .map(|v| {
+                        let style = match &v.fields {
+                            Fields::Unit => Style::Unit,
+                            Fields::Unnamed(f) if f.unnamed.len() == 1 => Style::Newtype,
+                            Fields::Unnamed(_) => Style::Tuple,
+                            Fields::Named(_) => Style::Struct,
+                        };
+                        if style == Style::Tuple && matches!(repr, Repr::Internal { .. }) {
+                            return Err(syn::Error::new_spanned(
+                                v,
+                                "internally tagged enums cannot have tuple variants",
+                            ));
+                        }
+                        let variant_attrs = NameAttrs::from_ast(&v.attrs, AttrTarget::Variant)?;
+                        Ok(Variant {
+                            ident: &v.ident,
+                            name: variant_attrs
+                                .name(&v.ident.unraw().to_string(), attrs.rename_all),
+                            aliases: variant_attrs.aliases,
+                            style,
+                            fields: fields_from_ast(&v.fields, variant_attrs.rename_all)?,
+                        })
+                    })
+                    .collect::<syn::Result<_>>()?;
+                ContainerData::Enum(repr, variants)
+            }
+            Data::Union(_) => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "merde cannot derive impls for unions",
+                ))
+            }
+        };
+
+        Ok(Container {
+            ident: &input.ident,
+            generics: &input.generics,
+            lifetime,
+            data,
+            deny_unknown_fields: attrs.deny_unknown_fields,
+        })
+    }
+
+    /// Type parameter identifiers, in declaration order.
+    pub(crate) fn type_params(&self) -> impl Iterator<Item = &'a Ident> {
+        self.generics.type_params().map(|tp| &tp.ident)
+    }
+
+    /// The field types of an internally tagged enum's newtype variants: the tag
+    /// is added to what they serialize as, so it has to be a map.
+    pub(crate) fn internally_tagged_newtypes(&self) -> Vec<&'a Type> {
+        match &self.data {
+            ContainerData::Enum(Repr::Internal { .. }, variants) => variants
+                .iter()
+                .filter(|v| v.style == Style::Newtype)
+                .map(|v| v.fields[0].ty)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the struct's generics with an extra lifetime parameter prepended
+    /// if the struct doesn't have one, along with the lifetime that the trait
+    /// should be implemented for (`ValueDeserialize<'s>`, etc.)
+    pub(crate) fn generics_with_lifetime(&self, name: &str) -> (Generics, Lifetime) {
+        match self.lifetime {
+            Some(lt) => (self.generics.clone(), lt.clone()),
+            None => {
+                let lt = Lifetime::new(name, Span::call_site());
+                let mut generics = self.generics.clone();
+                generics
+                    .params
+                    .insert(0, GenericParam::Lifetime(LifetimeParam::new(lt.clone())));
+                (generics, lt)
+            }
+        }
+    }
+}
+
+/// Container-level `#[merde(...)]` attributes
+#[derive(Default)]
+struct ContainerAttrs {
+    tag: Option<LitStr>,
+    content: Option<LitStr>,
+    untagged: Option<Span>,
+    enum_only_span: Option<Span>,
+    /// Applies to field names for structs, and to variant names for enums
+    rename_all: Option<RenameRule>,
+    deny_unknown_fields: bool,
+}
+
+impl ContainerAttrs {
+    fn from_ast(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = ContainerAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("merde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    out.tag = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("content") {
+                    out.content = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("untagged") {
+                    out.untagged = Some(meta.path.span());
+                } else if meta.path.is_ident("rename_all") {
+                    out.rename_all = Some(RenameRule::from_lit(&meta.value()?.parse()?)?);
+                    return Ok(());
+                } else if meta.path.is_ident("deny_unknown_fields") {
+                    out.deny_unknown_fields = true;
+                    return Ok(());
+                } else {
+                    return Err(meta.error("unknown merde container attribute"));
+                }
+                out.enum_only_span.get_or_insert(meta.path.span());
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+
+    fn repr(&self) -> syn::Result<Repr> {
+        match (&self.tag, &self.content, self.untagged) {
+            (None, None, None) => Ok(Repr::External),
+            (Some(tag), None, None) => Ok(Repr::Internal { tag: tag.value() }),
+            (Some(tag), Some(content), None) => Ok(Repr::Adjacent {
+                tag: tag.value(),
+                content: content.value(),
+            }),
+            (None, None, Some(_)) => Ok(Repr::Untagged),
+            (None, Some(content), None) => Err(syn::Error::new_spanned(
+                content,
+                "`content` requires `tag` to be set as well",
+            )),
+            (_, _, Some(span)) => Err(syn::Error::new(
+                span,
+                "`untagged` cannot be combined with `tag` or `content`",
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AttrTarget {
+    Field,
+    Variant,
+}
+
+/// Field-level and variant-level `#[merde(...)]` attributes
+#[derive(Default)]
+struct NameAttrs {
+    rename: Option<String>,
+    aliases: Vec<String>,
+    /// fields only
+    default: Option<FieldDefault>,
+    /// fields only
+    skip: bool,
+    /// variants only, applies to the fields of a struct variant
+    rename_all: Option<RenameRule>,
+    /// Where the first attribute was found, if any
+    span: Option<Span>,
+}
+
+impl NameAttrs {
+    fn from_ast(attrs: &[Attribute], target: AttrTarget) -> syn::Result<Self> {
+        let mut out = NameAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("merde")) {
+            attr.parse_nested_meta(|meta| {
+                out.span.get_or_insert(meta.path.span());
+                if meta.path.is_ident("rename") {
+                    out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("alias") {
+                    out.aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if target == AttrTarget::Field && meta.path.is_ident("default") {
+                    out.default = Some(if meta.input.peek(syn::Token![=]) {
+                        FieldDefault::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        FieldDefault::Trait
+                    });
+                } else if target == AttrTarget::Field && meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if target == AttrTarget::Variant && meta.path.is_ident("rename_all") {
+                    out.rename_all = Some(RenameRule::from_lit(&meta.value()?.parse()?)?);
+                } else {
+                    return Err(meta.error(match target {
+                        AttrTarget::Field => "unknown merde field attribute",
+                        AttrTarget::Variant => "unknown merde variant attribute",
+                    }));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+
+    /// The serialized name: an explicit `rename` wins over the container's `rename_all`
+    fn name(&self, ident: &str, rename_all: Option<RenameRule>) -> String {
+        match (&self.rename, rename_all) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply(ident),
+            (None, None) => ident.to_string(),
+        }
+    }
+}
+
+/// Adds `predicate` to the where clause of `generics`, creating it if needed.
+pub(crate) fn add_predicate(generics: &mut Generics, predicate: WherePredicate) {
+    generics.make_where_clause().predicates.push(predicate);
+}
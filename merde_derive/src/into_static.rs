@@ -0,0 +1,83 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, GenericParam};
+
+use crate::container::{add_predicate, Container, ContainerData};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+
+    // Owned structs are their own `'static` variant, just like with `derive!`.
+    if cont.generics.params.is_empty() {
+        return quote! {
+            #[automatically_derived]
+            impl ::merde::IntoStatic for #ident {
+                type Output = #ident;
+
+                #[inline(always)]
+                fn into_static(self) -> Self::Output {
+                    self
+                }
+            }
+        };
+    }
+
+    let mut generics = cont.generics.clone();
+    for tp in cont.type_params() {
+        add_predicate(&mut generics, parse_quote!(#tp: ::merde::IntoStatic));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let output_args = cont.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => quote!('static),
+        GenericParam::Type(tp) => {
+            let tp = &tp.ident;
+            quote!(<#tp as ::merde::IntoStatic>::Output)
+        }
+        GenericParam::Const(cp) => {
+            let cp = &cp.ident;
+            quote!(#cp)
+        }
+    });
+
+    let body = match &cont.data {
+        ContainerData::Struct(fields) => {
+            let names = fields.iter().map(|f| f.ident);
+            quote! {
+                #ident {
+                    #(#names: self.#names.into_static(),)*
+                }
+            }
+        }
+        ContainerData::Enum(_, variants) => {
+            let path = quote!(#ident);
+            let arms = variants.iter().map(|v| {
+                let bindings = v.bindings();
+                let values: Vec<_> = bindings.iter().map(|b| quote!(#b)).collect();
+                let converted: Vec<_> = bindings.iter().map(|b| quote!(#b.into_static())).collect();
+                let pattern = v.construct(&quote!(Self), &values);
+                let built = v.construct(&path, &converted);
+                quote!(#pattern => #built,)
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::IntoStatic for #ident #ty_generics #where_clause {
+            type Output = #ident<#(#output_args),*>;
+
+            fn into_static(self) -> Self::Output {
+                #[allow(unused_imports)]
+                use ::merde::IntoStatic;
+
+                #body
+            }
+        }
+    }
+}
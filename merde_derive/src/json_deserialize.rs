@@ -0,0 +1,149 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse_quote;
+
+use crate::container::{add_predicate, Container, ContainerData, Field};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+    let (_, ty_generics, _) = cont.generics.split_for_impl();
+
+    let (mut generics, lt) = cont.generics_with_lifetime("'s");
+    for tp in cont.type_params() {
+        add_predicate(
+            &mut generics,
+            parse_quote!(#tp: ::merde::json::JsonDeserialize<#lt>),
+        );
+    }
+
+    let body = match &cont.data {
+        ContainerData::Struct(fields) => struct_body(fields, cont.deny_unknown_fields),
+        ContainerData::Enum(..) => {
+            // enums need to look at the tag before they know what to do with the
+            // rest, which may come first: read the whole thing and let the
+            // `ValueDeserialize` impl sort it out.
+            add_predicate(
+                &mut generics,
+                parse_quote!(Self: ::merde::ValueDeserialize<#lt>),
+            );
+            quote! {
+                let value = de.deserialize_value()?;
+                Ok(<Self as ::merde::ValueDeserialize<#lt>>::from_value(Some(value))?)
+            }
+        }
+    };
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::json::JsonDeserialize<#lt> for #ident #ty_generics #where_clause {
+            fn json_deserialize(
+                de: &mut ::merde::json::JsonDeserializer<#lt>,
+            ) -> Result<Self, ::merde::json::MerdeJsonError<#lt>> {
+                #body
+            }
+        }
+    }
+}
+
+/// Reads the object key by key into a local per field, skipping over values
+/// nobody asked for, then builds the struct.
+fn struct_body(fields: &[Field], deny: bool) -> TokenStream {
+    let locals: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__f{}", i))
+        .collect();
+
+    let arms = fields
+        .iter()
+        .zip(&locals)
+        .filter(|(f, _)| !f.skip)
+        .map(|(f, local)| {
+            let keys: Vec<_> = f.keys().collect();
+            let read = quote! {
+                ::merde::json::JsonDeserialize::json_deserialize(de)
+                    .map_err(|e| e.at_key(::merde::IntoStatic::into_static(key.clone())))?
+            };
+            if f.aliases.is_empty() {
+                return quote! {
+                    #(#keys)|* => {
+                        #local = Some(#read);
+                    }
+                };
+            }
+            // like the `ValueDeserialize` impl, the main key wins over aliases,
+            // and earlier aliases over later ones, no matter the order they come in.
+            quote! {
+                #(#keys)|* => {
+                    let rank = [#(#keys),*].iter().position(|k| *k == &*key);
+                    if #local.as_ref().map_or(true, |(r, _)| rank <= *r) {
+                        #local = Some((rank, #read));
+                    } else {
+                        de.skip_value()?;
+                    }
+                }
+            }
+        });
+
+    let read_locals = fields
+        .iter()
+        .zip(&locals)
+        .filter(|(f, _)| !f.skip)
+        .map(|(_, l)| l);
+    let names = fields.iter().map(|f| f.ident);
+    let values = fields.iter().zip(&locals).map(|(f, local)| {
+        if f.skip {
+            return f.default_expr();
+        }
+        let key = f.key();
+        let fallback = f.default_expr().unwrap_or_else(|| {
+            quote! {
+                ::merde::json::JsonDeserialize::json_missing().map_err(|e| match e {
+                    ::merde::MerdeError::MissingValue => {
+                        ::merde::MerdeError::MissingProperty(#key.into()).at_key(#key)
+                    }
+                    e => e.at_key(#key),
+                })?
+            }
+        });
+        let value = if f.aliases.is_empty() {
+            quote!(value)
+        } else {
+            quote!((_, value))
+        };
+        Some(quote! {
+            match #local {
+                Some(#value) => value,
+                None => #fallback,
+            }
+        })
+    });
+
+    quote! {
+        let deny = #deny || ::merde::DeserializeOptions::current().deny_unknown_fields;
+        let mut unknown: Vec<String> = Vec::new();
+        #(
+            let mut #read_locals = None;
+        )*
+
+        let mut next = de.begin_object()?;
+        while let Some(key) = next {
+            match &*key {
+                #(#arms)*
+                _ => {
+                    if deny {
+                        unknown.push(key.to_string());
+                    }
+                    de.skip_value()?;
+                }
+            }
+            next = de.next_key()?;
+        }
+
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(::merde::MerdeError::UnknownProperty(unknown.join(", ")).into());
+        }
+
+        Ok(Self { #(#names: #values,)* })
+    }
+}
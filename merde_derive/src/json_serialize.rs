@@ -0,0 +1,162 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, parse_quote_spanned, spanned::Spanned, Ident};
+
+use crate::container::{add_predicate, Container, ContainerData, Repr, Style, Variant};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+
+    let mut generics = cont.generics.clone();
+    for tp in cont.type_params() {
+        add_predicate(
+            &mut generics,
+            parse_quote!(#tp: ::merde::json::JsonSerialize),
+        );
+    }
+    for ty in cont.internally_tagged_newtypes() {
+        add_predicate(
+            &mut generics,
+            parse_quote_spanned!(ty.span()=> #ty: ::merde::json::JsonSerializeObject),
+        );
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // structs always write objects, which internally tagged enums rely on
+    let (body, object_impl) = match &cont.data {
+        ContainerData::Struct(fields) => {
+            let fields: Vec<_> = fields.iter().filter(|f| !f.skip).collect();
+            let names = fields.iter().map(|f| f.ident);
+            let keys = fields.iter().map(|f| f.key());
+            let object_impl = quote! {
+                #[automatically_derived]
+                impl #impl_generics ::merde::json::JsonSerializeObject for #ident #ty_generics #where_clause {
+                    fn json_serialize_pairs(&self, guard: &mut ::merde::json::ObjectGuard<'_, '_>) {
+                        #(
+                            guard.pair(#keys, &self.#names);
+                        )*
+                    }
+                }
+            };
+            let body = quote! {
+                ::merde::json::JsonSerializeObject::json_serialize_pairs(
+                    self,
+                    &mut serializer.write_obj(),
+                );
+            };
+            (body, object_impl)
+        }
+        ContainerData::Enum(repr, variants) => {
+            let arms = variants.iter().map(|v| {
+                let bindings = v.bindings();
+                let values: Vec<_> = bindings
+                    .iter()
+                    .zip(&v.fields)
+                    .map(|(b, f)| if f.skip { quote!(_) } else { quote!(#b) })
+                    .collect();
+                let pattern = v.construct(&quote!(Self), &values);
+                let body = variant_body(repr, v);
+                quote!(#pattern => { #body })
+            });
+            let body = quote! {
+                match self {
+                    #(#arms)*
+                }
+            };
+            (body, TokenStream::new())
+        }
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::json::JsonSerialize for #ident #ty_generics #where_clause {
+            fn json_serialize(&self, serializer: &mut ::merde::json::JsonSerializer) {
+                #body
+            }
+        }
+
+        #object_impl
+    }
+}
+
+/// Writes the content of a variant (without any tag): `null` for unit variants,
+/// the field itself for newtype variants, an array for tuple variants and an
+/// object for struct variants.
+fn write_content(v: &Variant) -> TokenStream {
+    let bindings = v.bindings();
+    match v.style {
+        Style::Unit => quote!(serializer.write_null();),
+        Style::Newtype => quote!(::merde::json::JsonSerialize::json_serialize(__f0, serializer);),
+        Style::Tuple => quote! {
+            let mut guard = serializer.write_arr();
+            #(guard.elem(#bindings);)*
+        },
+        Style::Struct => {
+            let (keys, bindings) = serialized_fields(v);
+            quote! {
+                #[allow(unused_mut)]
+                let mut guard = serializer.write_obj();
+                #(guard.pair(#keys, #bindings);)*
+            }
+        }
+    }
+}
+
+/// Keys and bindings of the fields of a struct variant that aren't skipped
+fn serialized_fields<'a>(v: &'a Variant) -> (Vec<&'a str>, Vec<Ident>) {
+    v.fields
+        .iter()
+        .zip(v.bindings())
+        .filter(|(f, _)| !f.skip)
+        .map(|(f, b)| (f.key(), b))
+        .unzip()
+}
+
+fn variant_body(repr: &Repr, v: &Variant) -> TokenStream {
+    let name = v.name();
+    let content = write_content(v);
+    match repr {
+        Repr::External => match v.style {
+            Style::Unit => quote!(serializer.write_str(#name);),
+            _ => quote! {
+                let mut guard = serializer.write_obj();
+                guard.pair_with(#name, |serializer| { #content });
+            },
+        },
+        Repr::Internal { tag } => match v.style {
+            Style::Unit => quote! {
+                let mut guard = serializer.write_obj();
+                guard.pair(#tag, &#name);
+            },
+            Style::Newtype => quote! {
+                let mut guard = serializer.write_obj();
+                guard.pair(#tag, &#name);
+                ::merde::json::JsonSerializeObject::json_serialize_pairs(__f0, &mut guard);
+            },
+            Style::Struct => {
+                let (keys, bindings) = serialized_fields(v);
+                quote! {
+                    let mut guard = serializer.write_obj();
+                    guard.pair(#tag, &#name);
+                    #(guard.pair(#keys, #bindings);)*
+                }
+            }
+            Style::Tuple => unreachable!("rejected when parsing the container"),
+        },
+        Repr::Adjacent {
+            tag,
+            content: content_key,
+        } => match v.style {
+            Style::Unit => quote! {
+                let mut guard = serializer.write_obj();
+                guard.pair(#tag, &#name);
+            },
+            _ => quote! {
+                let mut guard = serializer.write_obj();
+                guard.pair(#tag, &#name);
+                guard.pair_with(#content_key, |serializer| { #content });
+            },
+        },
+        Repr::Untagged => content,
+    }
+}
@@ -0,0 +1,90 @@
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod case;
+mod container;
+mod into_static;
+mod json_deserialize;
+mod json_serialize;
+mod value_deserialize;
+mod value_serialize;
+mod with_lifetime;
+
+use container::Container;
+
+fn expand(input: TokenStream, f: fn(&Container) -> proc_macro2::TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match Container::from_ast(&input) {
+        Ok(cont) => f(&cont).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives `merde::ValueDeserialize` for a struct with named fields, or an enum.
+///
+/// Each field is looked up by name in a [`Value::Map`](https://docs.rs/merde/latest/merde/enum.Value.html),
+/// and a missing required field results in `MerdeError::MissingProperty`.
+///
+/// Enums are externally tagged (`{"Variant": content}`) by default. Use
+/// `#[merde(tag = "type")]` for internal tagging, `#[merde(tag = "t", content = "c")]`
+/// for adjacent tagging, and `#[merde(untagged)]` to try each variant in order.
+///
+/// Fields can be renamed with `#[merde(rename = "...")]`, accept other keys with
+/// `#[merde(alias = "...")]`, fall back to `Default::default()` (or a function) when
+/// missing with `#[merde(default)]` / `#[merde(default = "path")]`, or be left out
+/// entirely with `#[merde(skip)]`. `#[merde(rename_all = "camelCase")]` on the
+/// container renames every field (or every variant, for enums).
+///
+/// With `#[merde(deny_unknown_fields)]` on the container, keys that don't match
+/// any field result in `MerdeError::UnknownProperty`. `merde::DeserializeOptions`
+/// turns that on for every type at once.
+///
+/// To use it with `OwnedValueDeserialize`, also derive `IntoStatic` and `WithLifetime`.
+#[proc_macro_derive(ValueDeserialize, attributes(merde))]
+pub fn derive_value_deserialize(input: TokenStream) -> TokenStream {
+    expand(input, value_deserialize::expand)
+}
+
+/// Derives `merde::ValueSerialize` for a struct with named fields, building a
+/// `Value::Map` out of it. Enums use the same representation as `JsonSerialize`,
+/// and `#[merde(...)]` attributes are honored the same way.
+#[proc_macro_derive(ValueSerialize, attributes(merde))]
+pub fn derive_value_serialize(input: TokenStream) -> TokenStream {
+    expand(input, value_serialize::expand)
+}
+
+/// Derives `merde::json::JsonSerialize` for a struct with named fields, writing
+/// it as a JSON object, with fields in declaration order. Enums are written using
+/// the same representation `ValueDeserialize` expects.
+#[proc_macro_derive(JsonSerialize, attributes(merde))]
+pub fn derive_json_serialize(input: TokenStream) -> TokenStream {
+    expand(input, json_serialize::expand)
+}
+
+/// Derives `merde::json::JsonDeserialize` for a struct with named fields, reading it
+/// straight from JSON text instead of going through a `Value` first. It honors the
+/// same `#[merde(...)]` attributes as `ValueDeserialize`.
+///
+/// Enums are read into a `Value` and handed to their `ValueDeserialize` impl, so
+/// they need to derive that as well.
+#[proc_macro_derive(JsonDeserialize, attributes(merde))]
+pub fn derive_json_deserialize(input: TokenStream) -> TokenStream {
+    expand(input, json_deserialize::expand)
+}
+
+/// Derives `merde::IntoStatic`: the lifetime parameter (if any) becomes `'static`,
+/// and type parameters become their `IntoStatic::Output`.
+#[proc_macro_derive(IntoStatic, attributes(merde))]
+pub fn derive_into_static(input: TokenStream) -> TokenStream {
+    expand(input, into_static::expand)
+}
+
+/// Derives `merde::WithLifetime`, which lets `OwnedValueDeserialize` work with
+/// types that borrow from their input.
+#[proc_macro_derive(WithLifetime, attributes(merde))]
+pub fn derive_with_lifetime(input: TokenStream) -> TokenStream {
+    expand(input, with_lifetime::expand)
+}
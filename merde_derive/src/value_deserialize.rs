@@ -0,0 +1,455 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+
+use crate::container::{add_predicate, Container, ContainerData, Field, Repr, Style, Variant};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+    let (_, ty_generics, _) = cont.generics.split_for_impl();
+
+    let (mut generics, lt) = cont.generics_with_lifetime("'s");
+    for tp in cont.type_params() {
+        add_predicate(
+            &mut generics,
+            parse_quote!(#tp: ::merde::ValueDeserialize<#lt>),
+        );
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let deny = cont.deny_unknown_fields;
+    let (from_value_ref, from_value) = match &cont.data {
+        ContainerData::Struct(fields) => (
+            struct_body(fields, Access::Ref, deny),
+            struct_body(fields, Access::Owned, deny),
+        ),
+        ContainerData::Enum(Repr::Untagged, variants) => (
+            untagged_body(variants, deny),
+            // every variant gets a shot at the value, so we can't give it away
+            quote!(Self::from_value_ref(value.as_ref())),
+        ),
+        ContainerData::Enum(repr, variants) => (
+            enum_body(repr, variants, Access::Ref, deny),
+            enum_body(repr, variants, Access::Owned, deny),
+        ),
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::ValueDeserialize<#lt> for #ident #ty_generics #where_clause {
+            fn from_value_ref<'val>(
+                value: Option<&'val ::merde::Value<#lt>>,
+            ) -> Result<Self, ::merde::MerdeError> {
+                #from_value_ref
+            }
+
+            fn from_value(
+                value: Option<::merde::Value<#lt>>,
+            ) -> Result<Self, ::merde::MerdeError> {
+                #from_value
+            }
+        }
+    }
+}
+
+/// Whether we're generating `from_value_ref` (borrowing the [Value]) or
+/// `from_value` (taking ownership of it).
+#[derive(Clone, Copy)]
+enum Access {
+    Ref,
+    Owned,
+}
+
+impl Access {
+    fn deserialize_fn(self) -> TokenStream {
+        match self {
+            Access::Ref => quote!(::merde::ValueDeserialize::from_value_ref),
+            Access::Owned => quote!(::merde::ValueDeserialize::from_value),
+        }
+    }
+
+    fn into_map(self) -> TokenStream {
+        match self {
+            Access::Ref => quote!(as_map),
+            Access::Owned => quote!(into_map),
+        }
+    }
+
+    fn must_get(self) -> TokenStream {
+        match self {
+            Access::Ref => quote!(must_get),
+            Access::Owned => quote!(must_remove),
+        }
+    }
+
+    fn map_binding(self) -> TokenStream {
+        match self {
+            Access::Ref => quote!(map),
+            Access::Owned => quote!(mut map),
+        }
+    }
+}
+
+fn struct_body(fields: &[Field], access: Access, deny: bool) -> TokenStream {
+    let into_map = access.into_map();
+    let map = access.map_binding();
+    let fields = map_fields(&quote!(Self), fields, access, deny, None);
+    quote! {
+        #[allow(unused_mut)]
+        let #map = value.ok_or(::merde::MerdeError::MissingValue)?.#into_map()?;
+        Ok(#fields)
+    }
+}
+
+/// Builds `#path { a: map.must_get("a")?, ... }` out of a map binding named `map`.
+/// `tag` is an extra key that's allowed to be in the map, for internally tagged enums.
+fn map_fields(
+    path: &TokenStream,
+    fields: &[Field],
+    access: Access,
+    deny: bool,
+    tag: Option<&str>,
+) -> TokenStream {
+    let known = fields
+        .iter()
+        .filter(|f| !f.skip)
+        .flat_map(|f| f.keys())
+        .chain(tag);
+    let check = check_unknown_keys(known, deny);
+    let names = fields.iter().map(|f| f.ident);
+    let values = fields.iter().map(|f| field_value(f, access));
+    quote!({
+        #check
+        #path { #(#names: #values,)* }
+    })
+}
+
+/// Makes sure `map` only has keys from `known`, if the container has
+/// `#[merde(deny_unknown_fields)]`, or if [DeserializeOptions] ask for it.
+fn check_unknown_keys<'a>(known: impl Iterator<Item = &'a str>, deny: bool) -> TokenStream {
+    let check = quote!(map.deny_unknown_keys(&[#(#known),*])?;);
+    if deny {
+        check
+    } else {
+        quote! {
+            if ::merde::DeserializeOptions::current().deny_unknown_fields {
+                #check
+            }
+        }
+    }
+}
+
+/// Reads a single field out of `map`, looking at its aliases and falling back
+/// to its default if it has one.
+fn field_value(field: &Field, access: Access) -> TokenStream {
+    let must_get = access.must_get();
+    let key = field.key();
+    let default = field.default_expr();
+
+    if field.skip {
+        return quote!(#default);
+    }
+    if field.aliases.is_empty() && default.is_none() {
+        return quote!(map.#must_get(#key)?);
+    }
+
+    let keys = field.keys();
+    let fallback = default.unwrap_or_else(|| quote!(map.#must_get(#key)?));
+    quote! {
+        match [#(#keys),*]
+            .into_iter()
+            .find(|k| map.contains_key(&::merde::CowStr::from(*k)))
+        {
+            Some(key) => map.#must_get(key)?,
+            None => #fallback,
+        }
+    }
+}
+
+/// Builds the variant out of `content`, which is either a `Value` or a `&Value`,
+/// for the external and adjacent representations
+fn variant_from_content(variant: &Variant, access: Access, deny: bool) -> TokenStream {
+    let from_value = access.deserialize_fn();
+    match variant.style {
+        Style::Unit => {
+            let built = variant.construct(&quote!(Self), &[]);
+            quote! {
+                match content {
+                    ::merde::Value::Null => Ok(#built),
+                    v => Err(::merde::MerdeError::MismatchedType {
+                        expected: ::merde::ValueType::Null,
+                        found: v.value_type(),
+                    }),
+                }
+            }
+        }
+        Style::Newtype => {
+            let built = variant.construct(&quote!(Self), &[quote!(#from_value(Some(content))?)]);
+            quote!(Ok(#built))
+        }
+        Style::Tuple => tuple_variant(variant, &quote!(#from_value(Some(content))?)),
+        Style::Struct => {
+            let into_map = access.into_map();
+            let map = access.map_binding();
+            let fields = map_fields(&variant_path(variant), &variant.fields, access, deny, None);
+            quote! {
+                #[allow(unused_mut)]
+                let #map = content.#into_map()?;
+                Ok(#fields)
+            }
+        }
+    }
+}
+
+fn variant_path(variant: &Variant) -> TokenStream {
+    let ident = variant.ident;
+    quote!(Self::#ident)
+}
+
+/// Tuple variants are deserialized like tuples, then destructured.
+fn tuple_variant(variant: &Variant, tuple: &TokenStream) -> TokenStream {
+    let bindings = variant.bindings();
+    let values: Vec<_> = bindings.iter().map(|b| quote!(#b)).collect();
+    let built = variant.construct(&quote!(Self), &values);
+    quote! {
+        let (#(#bindings,)*) = #tuple;
+        Ok(#built)
+    }
+}
+
+/// Runs `body` (which evaluates to a `Result<Self, MerdeError>`) and records
+/// which variant we were attempting if it fails.
+fn in_variant(variant: &Variant, body: TokenStream) -> TokenStream {
+    let name = variant.name();
+    quote! {
+        (move || -> Result<Self, ::merde::MerdeError> { #body })().map_err(|e| {
+            ::merde::MerdeError::VariantError {
+                variant: #name,
+                error: Box::new(e),
+            }
+        })
+    }
+}
+
+fn enum_body(repr: &Repr, variants: &[Variant], access: Access, deny: bool) -> TokenStream {
+    let into_map = access.into_map();
+    let map = access.map_binding();
+    let must_get = access.must_get();
+
+    match repr {
+        Repr::External => {
+            let iter = match access {
+                Access::Ref => quote!(iter),
+                Access::Owned => quote!(into_iter),
+            };
+            let unit_arms = variants.iter().map(|v| {
+                let names = v.names();
+                let body = match v.style {
+                    Style::Unit => {
+                        let built = v.construct(&quote!(Self), &[]);
+                        quote!(Ok(#built))
+                    }
+                    _ => in_variant(
+                        v,
+                        quote! {
+                            Err(::merde::MerdeError::MismatchedType {
+                                expected: ::merde::ValueType::Map,
+                                found: ::merde::ValueType::String,
+                            })
+                        },
+                    ),
+                };
+                quote!(#(#names)|* => #body,)
+            });
+            let map_arms = variants.iter().map(|v| {
+                let names = v.names();
+                let body = in_variant(v, variant_from_content(v, access, deny));
+                // the content lives under the tag
+                quote!(#(#names)|* => #body.map_err(|e| e.at_key(tag.to_owned())),)
+            });
+            quote! {
+                match value.ok_or(::merde::MerdeError::MissingValue)? {
+                    ::merde::Value::Str(tag) => {
+                        let tag: &str = &tag;
+                        match tag {
+                            #(#unit_arms)*
+                            other => Err(::merde::MerdeError::UnknownVariant(other.to_string())),
+                        }
+                    }
+                    ::merde::Value::Map(map) => {
+                        let mut entries = map.#iter();
+                        let (tag, content) = entries.next().ok_or(::merde::MerdeError::MissingValue)?;
+                        if let Some((extra, _)) = entries.next() {
+                            return Err(::merde::MerdeError::UnknownProperty(extra.to_string()));
+                        }
+                        let tag: &str = &tag;
+                        match tag {
+                            #(#map_arms)*
+                            other => Err(::merde::MerdeError::UnknownVariant(other.to_string())),
+                        }
+                    }
+                    v => Err(::merde::MerdeError::MismatchedType {
+                        expected: ::merde::ValueType::Map,
+                        found: v.value_type(),
+                    }),
+                }
+            }
+        }
+        Repr::Internal { tag } => {
+            let arms = variants.iter().map(|v| {
+                let names = v.names();
+                let body = match v.style {
+                    Style::Unit => {
+                        let built = v.construct(&quote!(Self), &[]);
+                        let check = check_unknown_keys(std::iter::once(tag.as_str()), deny);
+                        quote!({
+                            #check
+                            Ok(#built)
+                        })
+                    }
+                    Style::Newtype => {
+                        // the content gets the map, minus the tag
+                        let (take_map, from_value) = match access {
+                            Access::Ref => (
+                                quote! {
+                                    let mut map = map.clone();
+                                    map.remove(&::merde::CowStr::from(#tag));
+                                },
+                                quote!(::merde::ValueDeserialize::from_value),
+                            ),
+                            Access::Owned => (quote!(), access.deserialize_fn()),
+                        };
+                        let built = v.construct(
+                            &quote!(Self),
+                            &[quote!(#from_value(Some(::merde::Value::Map(map)))?)],
+                        );
+                        in_variant(
+                            v,
+                            quote! {
+                                #take_map
+                                Ok(#built)
+                            },
+                        )
+                    }
+                    Style::Struct => {
+                        let fields =
+                            map_fields(&variant_path(v), &v.fields, access, deny, Some(tag));
+                        in_variant(v, quote!(Ok(#fields)))
+                    }
+                    Style::Tuple => unreachable!("rejected when parsing the container"),
+                };
+                quote!(#(#names)|* => #body,)
+            });
+            quote! {
+                #[allow(unused_mut)]
+                let #map = value.ok_or(::merde::MerdeError::MissingValue)?.#into_map()?;
+                let tag: ::merde::CowStr<'_> = map.#must_get(#tag)?;
+                match &*tag {
+                    #(#arms)*
+                    other => Err(::merde::MerdeError::UnknownVariant(other.to_string())),
+                }
+            }
+        }
+        Repr::Adjacent { tag, content } => {
+            let arms = variants.iter().map(|v| {
+                let names = v.names();
+                let body = match v.style {
+                    Style::Unit => {
+                        let built = v.construct(&quote!(Self), &[]);
+                        quote!(Ok(#built))
+                    }
+                    Style::Newtype => {
+                        let built = v.construct(&quote!(Self), &[quote!(map.#must_get(#content)?)]);
+                        in_variant(v, quote!(Ok(#built)))
+                    }
+                    Style::Tuple => in_variant(v, tuple_variant(v, &quote!(map.#must_get(#content)?))),
+                    Style::Struct => {
+                        let fields = map_fields(&variant_path(v), &v.fields, access, deny, None);
+                        let content_map = match access {
+                            Access::Ref => quote!(let map: &::merde::Map = map.get(&::merde::CowStr::from(#content))),
+                            Access::Owned => quote!(let mut map: ::merde::Map = map.remove(&::merde::CowStr::from(#content))),
+                        };
+                        let body = in_variant(
+                            v,
+                            quote! {
+                                #content_map
+                                    .ok_or_else(|| ::merde::MerdeError::MissingProperty(#content.into()))?
+                                    .#into_map()?;
+                                Ok(#fields)
+                            },
+                        );
+                        // same as what `must_get` does for the other variants
+                        quote!(#body.map_err(|e| e.at_key(#content)))
+                    }
+                };
+                quote!(#(#names)|* => #body,)
+            });
+            let check = check_unknown_keys([tag.as_str(), content.as_str()].into_iter(), deny);
+            quote! {
+                #[allow(unused_mut)]
+                let #map = value.ok_or(::merde::MerdeError::MissingValue)?.#into_map()?;
+                #check
+                let tag: ::merde::CowStr<'_> = map.#must_get(#tag)?;
+                match &*tag {
+                    #(#arms)*
+                    other => Err(::merde::MerdeError::UnknownVariant(other.to_string())),
+                }
+            }
+        }
+        Repr::Untagged => unreachable!("untagged enums are handled by untagged_body"),
+    }
+}
+
+/// Tries every variant in order, returns the first one that deserializes.
+fn untagged_body(variants: &[Variant], deny: bool) -> TokenStream {
+    let attempts = variants.iter().map(|v| {
+        let name = v.name();
+        let body = match v.style {
+            Style::Unit => {
+                let built = v.construct(&quote!(Self), &[]);
+                quote! {
+                    match value {
+                        ::merde::Value::Null => Ok(#built),
+                        v => Err(::merde::MerdeError::MismatchedType {
+                            expected: ::merde::ValueType::Null,
+                            found: v.value_type(),
+                        }),
+                    }
+                }
+            }
+            Style::Newtype => {
+                let built = v.construct(
+                    &quote!(Self),
+                    &[quote!(::merde::ValueDeserialize::from_value_ref(Some(
+                        value
+                    ))?)],
+                );
+                quote!(Ok(#built))
+            }
+            Style::Tuple => tuple_variant(
+                v,
+                &quote!(::merde::ValueDeserialize::from_value_ref(Some(value))?),
+            ),
+            Style::Struct => {
+                let fields = map_fields(&variant_path(v), &v.fields, Access::Ref, deny, None);
+                quote! {
+                    let map = value.as_map()?;
+                    Ok(#fields)
+                }
+            }
+        };
+        quote! {
+            match (|| -> Result<Self, ::merde::MerdeError> { #body })() {
+                Ok(v) => return Ok(v),
+                Err(e) => errors.push((#name, e)),
+            }
+        }
+    });
+
+    quote! {
+        let value = value.ok_or(::merde::MerdeError::MissingValue)?;
+        let mut errors = Vec::new();
+        #(#attempts)*
+        Err(::merde::MerdeError::NoMatchingVariant { errors })
+    }
+}
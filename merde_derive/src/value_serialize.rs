@@ -0,0 +1,168 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, parse_quote_spanned, spanned::Spanned};
+
+use crate::container::{add_predicate, Container, ContainerData, Field, Repr, Style, Variant};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+
+    let mut generics = cont.generics.clone();
+    for tp in cont.type_params() {
+        add_predicate(&mut generics, parse_quote!(#tp: ::merde::ValueSerialize));
+    }
+    for ty in cont.internally_tagged_newtypes() {
+        add_predicate(
+            &mut generics,
+            parse_quote_spanned!(ty.span()=> #ty: ::merde::ValueSerializeMap),
+        );
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // structs always serialize as maps, which internally tagged enums rely on
+    let (body, map_impl) = match &cont.data {
+        ContainerData::Struct(fields) => {
+            let values = fields
+                .iter()
+                .filter(|f| !f.skip)
+                .map(|f| {
+                    let name = f.ident;
+                    quote!(&self.#name)
+                })
+                .collect::<Vec<_>>();
+            let map = build_map(quote!(::merde::Map::new()), fields, &values);
+            let map_impl = quote! {
+                #[automatically_derived]
+                impl #impl_generics ::merde::ValueSerializeMap for #ident #ty_generics #where_clause {
+                    fn to_map(&self) -> ::merde::Map<'_> {
+                        #map
+                    }
+                }
+            };
+            (
+                quote!(::merde::Value::Map(::merde::ValueSerializeMap::to_map(
+                    self
+                ))),
+                map_impl,
+            )
+        }
+        ContainerData::Enum(repr, variants) => {
+            let arms = variants.iter().map(|v| {
+                let bindings = v.bindings();
+                let values: Vec<_> = bindings
+                    .iter()
+                    .zip(&v.fields)
+                    .map(|(b, f)| if f.skip { quote!(_) } else { quote!(#b) })
+                    .collect();
+                let pattern = v.construct(&quote!(Self), &values);
+                let body = variant_body(repr, v);
+                quote!(#pattern => { #body })
+            });
+            let body = quote! {
+                match self {
+                    #(#arms)*
+                }
+            };
+            (body, TokenStream::new())
+        }
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::ValueSerialize for #ident #ty_generics #where_clause {
+            fn to_value(&self) -> ::merde::Value<'_> {
+                #body
+            }
+        }
+
+        #map_impl
+    }
+}
+
+/// Adds the fields that aren't skipped to the `map` expression, `values` being
+/// expressions that evaluate to a reference to each of them.
+fn build_map(map: TokenStream, fields: &[Field], values: &[TokenStream]) -> TokenStream {
+    let keys = fields.iter().filter(|f| !f.skip).map(|f| f.key());
+    quote! {
+        #map #(.with(#keys, ::merde::ValueSerialize::to_value(#values)))*
+    }
+}
+
+/// The values of the fields of a struct variant that aren't skipped
+fn serialized_values(v: &Variant) -> Vec<TokenStream> {
+    v.fields
+        .iter()
+        .zip(v.bindings())
+        .filter(|(f, _)| !f.skip)
+        .map(|(_, b)| quote!(#b))
+        .collect()
+}
+
+/// The content of a variant (without any tag): `Null` for unit variants,
+/// the field itself for newtype variants, an array for tuple variants and a
+/// map for struct variants.
+fn content(v: &Variant) -> TokenStream {
+    let bindings = v.bindings();
+    match v.style {
+        Style::Unit => quote!(::merde::Value::Null),
+        Style::Newtype => quote!(::merde::ValueSerialize::to_value(__f0)),
+        Style::Tuple => quote! {
+            ::merde::Value::Array(
+                ::merde::Array::new()
+                    #(.with(::merde::ValueSerialize::to_value(#bindings)))*
+            )
+        },
+        Style::Struct => {
+            let map = build_map(
+                quote!(::merde::Map::new()),
+                &v.fields,
+                &serialized_values(v),
+            );
+            quote!(::merde::Value::Map(#map))
+        }
+    }
+}
+
+fn variant_body(repr: &Repr, v: &Variant) -> TokenStream {
+    let name = v.name();
+    let content = content(v);
+    match repr {
+        Repr::External => match v.style {
+            Style::Unit => quote!(::merde::Value::Str(::merde::CowStr::Borrowed(#name))),
+            _ => quote!(::merde::Value::Map(::merde::Map::new().with(#name, #content))),
+        },
+        Repr::Internal { tag } => match v.style {
+            Style::Unit => quote!(::merde::Value::Map(::merde::Map::new().with(#tag, #name))),
+            // the tag goes first, like in the JSON output
+            Style::Newtype => quote! {
+                let map = ::merde::ValueSerializeMap::to_map(__f0);
+                let mut tagged = ::merde::Map::with_capacity(map.len() + 1).with(#tag, #name);
+                tagged.extend(map);
+                ::merde::Value::Map(tagged)
+            },
+            Style::Struct => {
+                let map = build_map(
+                    quote!(::merde::Map::new().with(#tag, #name)),
+                    &v.fields,
+                    &serialized_values(v),
+                );
+                quote!(::merde::Value::Map(#map))
+            }
+            Style::Tuple => unreachable!("rejected when parsing the container"),
+        },
+        Repr::Adjacent {
+            tag,
+            content: content_key,
+        } => match v.style {
+            Style::Unit => quote!(::merde::Value::Map(::merde::Map::new().with(#tag, #name))),
+            _ => quote! {
+                ::merde::Value::Map(
+                    ::merde::Map::new()
+                        .with(#tag, #name)
+                        .with(#content_key, #content)
+                )
+            },
+        },
+        Repr::Untagged => content,
+    }
+}
@@ -0,0 +1,50 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, GenericParam};
+
+use crate::container::{add_predicate, Container};
+
+pub(crate) fn expand(cont: &Container) -> TokenStream {
+    let ident = cont.ident;
+    let (_, ty_generics, _) = cont.generics.split_for_impl();
+
+    // The struct's own lifetime (if any) gets swapped for the instantiated one.
+    let mut generics = cont.generics.clone();
+    let inst: syn::Lifetime = parse_quote!('instantiated_lifetime);
+    generics.params.insert(0, parse_quote!(#inst));
+    for tp in cont.type_params() {
+        add_predicate(
+            &mut generics,
+            parse_quote!(#tp: ::merde::WithLifetime<#inst>),
+        );
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    if cont.generics.params.is_empty() {
+        return quote! {
+            #[automatically_derived]
+            impl<#inst> ::merde::WithLifetime<#inst> for #ident {
+                type Lifetimed = #ident;
+            }
+        };
+    }
+
+    let lifetimed_args = cont.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => quote!(#inst),
+        GenericParam::Type(tp) => {
+            let tp = &tp.ident;
+            quote!(<#tp as ::merde::WithLifetime<#inst>>::Lifetimed)
+        }
+        GenericParam::Const(cp) => {
+            let cp = &cp.ident;
+            quote!(#cp)
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics ::merde::WithLifetime<#inst> for #ident #ty_generics #where_clause {
+            type Lifetimed = #ident<#(#lifetimed_args),*>;
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Deserializing straight from JSON text, without building a [Value] tree first.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use merde_core::{Array, CowStr, IntoStatic, Map, MerdeError, Value, ValueDeserialize, ValueType};
+
+use crate::jiter_lite::errors::JiterError;
+use crate::jiter_lite::jiter::Jiter;
+use crate::jiter_lite::parse::Peek;
+use crate::parser::{cowify, jiter_to_value_with_peek};
+use crate::MerdeJsonError;
+
+/// Pulls values out of JSON text one token at a time, for [JsonDeserialize]
+/// implementations.
+///
+/// Strings are borrowed from the input whenever they don't contain escape sequences.
+pub struct JsonDeserializer<'s> {
+    src: &'s [u8],
+    iter: Jiter<'s>,
+}
+
+impl<'s> JsonDeserializer<'s> {
+    /// Prepares to deserialize the JSON text in `src`.
+    pub fn new(src: &'s [u8]) -> Self {
+        Self {
+            src,
+            iter: Jiter::new(src),
+        }
+    }
+
+    /// Reads the next value into a [Value] tree. This is how types that don't
+    /// have a streaming implementation get deserialized.
+    pub fn deserialize_value(&mut self) -> Result<Value<'s>, MerdeJsonError<'s>> {
+        let peek = self.iter.peek().map_err(jiter_error)?;
+        jiter_to_value_with_peek(self.src, peek, &mut self.iter).map_err(jiter_error)
+    }
+
+    /// If the next value is `null`, consumes it and returns `true`.
+    pub fn next_null(&mut self) -> Result<bool, MerdeJsonError<'s>> {
+        if self.iter.peek().map_err(jiter_error)? == Peek::Null {
+            self.iter.known_null().map_err(jiter_error)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Starts reading an object, returns its first key, or `None` if it's empty.
+    ///
+    /// The value for each key must be consumed (or skipped, see [JsonDeserializer::skip_value])
+    /// before calling [JsonDeserializer::next_key].
+    pub fn begin_object(&mut self) -> Result<Option<CowStr<'s>>, MerdeJsonError<'s>> {
+        if self.iter.peek().map_err(jiter_error)? != Peek::Object {
+            return Err(self.mismatch(ValueType::Map));
+        }
+        let src = self.src;
+        let key = self.iter.known_object().map_err(jiter_error)?;
+        Ok(key.map(|k| cowify(src, k)))
+    }
+
+    /// Returns the next key of the object we're reading, or `None` if we've
+    /// reached its end.
+    pub fn next_key(&mut self) -> Result<Option<CowStr<'s>>, MerdeJsonError<'s>> {
+        let src = self.src;
+        let key = self.iter.next_key().map_err(jiter_error)?;
+        Ok(key.map(|k| cowify(src, k)))
+    }
+
+    /// Starts reading an array, returns `true` if it has at least one element.
+    pub fn begin_array(&mut self) -> Result<bool, MerdeJsonError<'s>> {
+        if self.iter.peek().map_err(jiter_error)? != Peek::Array {
+            return Err(self.mismatch(ValueType::Array));
+        }
+        Ok(self.iter.known_array().map_err(jiter_error)?.is_some())
+    }
+
+    /// Moves on to the next element of the array we're reading, returns `false`
+    /// if we've reached its end.
+    pub fn next_element(&mut self) -> Result<bool, MerdeJsonError<'s>> {
+        Ok(self.iter.array_step().map_err(jiter_error)?.is_some())
+    }
+
+    /// Consumes the next value without deserializing it.
+    pub fn skip_value(&mut self) -> Result<(), MerdeJsonError<'s>> {
+        let peek = self.iter.peek().map_err(jiter_error)?;
+        match peek {
+            Peek::String => {
+                self.iter.known_bytes().map_err(jiter_error)?;
+            }
+            Peek::Array => {
+                let mut more = self.iter.known_array().map_err(jiter_error)?.is_some();
+                while more {
+                    self.skip_value()?;
+                    more = self.next_element()?;
+                }
+            }
+            Peek::Object => {
+                let mut more = self.iter.known_object().map_err(jiter_error)?.is_some();
+                while more {
+                    self.skip_value()?;
+                    more = self.iter.next_key().map_err(jiter_error)?.is_some();
+                }
+            }
+            _ => {
+                // scalars don't allocate
+                jiter_to_value_with_peek(self.src, peek, &mut self.iter).map_err(jiter_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure there's nothing but whitespace left in the input.
+    pub fn finish(&mut self) -> Result<(), MerdeJsonError<'s>> {
+        self.iter.finish().map_err(jiter_error)
+    }
+
+    /// The number of bytes consumed so far.
+    pub fn consumed(&self) -> usize {
+        self.iter.current_index()
+    }
+
+    /// Builds a [MerdeError::MismatchedType] error for the next value, consuming it.
+    fn mismatch(&mut self, expected: ValueType) -> MerdeJsonError<'s> {
+        match self.deserialize_value() {
+            Ok(found) => MerdeError::MismatchedType {
+                expected,
+                found: found.value_type(),
+            }
+            .into(),
+            Err(e) => e,
+        }
+    }
+}
+
+fn jiter_error<'s>(err: JiterError) -> MerdeJsonError<'s> {
+    MerdeJsonError::JiterError { err, source: None }
+}
+
+/// Types that can be deserialized straight from JSON text, without going
+/// through a [Value] first (which is what [ValueDeserialize] does).
+///
+/// This saves allocating a map for every object in the input. It can be derived
+/// for structs with `#[derive(JsonDeserialize)]`, see the `merde` crate.
+pub trait JsonDeserialize<'s>
+where
+    Self: Sized,
+{
+    /// Reads the next value from `de`.
+    fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>>;
+
+    /// What a missing object property of this type deserializes to:
+    /// [MerdeError::MissingValue] by default, `None` for [Option].
+    #[inline(always)]
+    fn json_missing() -> Result<Self, MerdeError> {
+        Err(MerdeError::MissingValue)
+    }
+}
+
+/// These have nothing to gain from streaming, so they're read as a [Value]
+/// and handed to their [ValueDeserialize] implementation.
+macro_rules! impl_json_deserialize_via_value {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl<'s> JsonDeserialize<'s> for $ty {
+                #[inline(always)]
+                fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+                    Ok(<$ty as ValueDeserialize<'s>>::from_value(Some(de.deserialize_value()?))?)
+                }
+            }
+        )*
+    };
+}
+
+impl_json_deserialize_via_value! {
+    u8, u16, u32, u64, usize,
+    i8, i16, i32, i64, isize,
+    bool,
+    String, CowStr<'s>, Cow<'s, str>,
+    Value<'s>, Array<'s>, Map<'s>,
+}
+
+impl<'s, T> JsonDeserialize<'s> for Option<T>
+where
+    T: JsonDeserialize<'s>,
+{
+    fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+        if de.next_null()? {
+            Ok(None)
+        } else {
+            T::json_deserialize(de).map(Some)
+        }
+    }
+
+    #[inline(always)]
+    fn json_missing() -> Result<Self, MerdeError> {
+        Ok(None)
+    }
+}
+
+impl<'s, T> JsonDeserialize<'s> for Vec<T>
+where
+    T: JsonDeserialize<'s>,
+{
+    fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+        let mut vec = Vec::new();
+        let mut more = de.begin_array()?;
+        while more {
+            let item = T::json_deserialize(de).map_err(|e| e.at_index(vec.len()))?;
+            vec.push(item);
+            more = de.next_element()?;
+        }
+        Ok(vec)
+    }
+}
+
+impl<'s, K, V> JsonDeserialize<'s> for HashMap<K, V>
+where
+    K: FromStr + Eq + Hash + 's,
+    V: JsonDeserialize<'s>,
+    K::Err: std::fmt::Debug,
+{
+    fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+        let mut map = HashMap::new();
+        let mut next = de.begin_object()?;
+        while let Some(key) = next {
+            let parsed_key = K::from_str(&key)
+                .map_err(|_| MerdeError::InvalidKey.at_key(key.clone().into_static()))?;
+            let parsed_value =
+                V::json_deserialize(de).map_err(|e| e.at_key(key.clone().into_static()))?;
+            map.insert(parsed_key, parsed_value);
+            next = de.next_key()?;
+        }
+        Ok(map)
+    }
+}
+
+macro_rules! impl_json_deserialize_for_wrapper {
+    ($($wrapper:ident)::+) => {
+        impl<'s, T> JsonDeserialize<'s> for $($wrapper)::+<T>
+        where
+            T: JsonDeserialize<'s>,
+        {
+            #[inline(always)]
+            fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+                T::json_deserialize(de).map(Into::into)
+            }
+
+            #[inline(always)]
+            fn json_missing() -> Result<Self, MerdeError> {
+                T::json_missing().map(Into::into)
+            }
+        }
+    };
+}
+
+impl_json_deserialize_for_wrapper!(Box);
+impl_json_deserialize_for_wrapper!(std::rc::Rc);
+impl_json_deserialize_for_wrapper!(std::sync::Arc);
+
+macro_rules! impl_json_deserialize_for_tuple {
+    ($($ty:ident $index:tt),+) => {
+        impl<'s, $($ty),+> JsonDeserialize<'s> for ($($ty,)+)
+        where
+            $($ty: JsonDeserialize<'s>),+
+        {
+            fn json_deserialize(de: &mut JsonDeserializer<'s>) -> Result<Self, MerdeJsonError<'s>> {
+                // same error as the via-value path when the array has the wrong length
+                let wrong_len = || MerdeError::MismatchedType {
+                    expected: ValueType::Array,
+                    found: ValueType::Array,
+                };
+                let mut more = de.begin_array()?;
+                let tuple = ($(
+                    {
+                        if !more {
+                            return Err(wrong_len().into());
+                        }
+                        let item = $ty::json_deserialize(de).map_err(|e| e.at_index($index))?;
+                        more = de.next_element()?;
+                        item
+                    },
+                )+);
+                if more {
+                    return Err(wrong_len().into());
+                }
+                Ok(tuple)
+            }
+        }
+    };
+}
+
+impl_json_deserialize_for_tuple!(T1 0);
+impl_json_deserialize_for_tuple!(T1 0, T2 1);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2, T4 3);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_json_deserialize_for_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use merde_core::{Array, CowStr, Map, Value};
+
+    use super::{JsonDeserialize, JsonDeserializer};
+
+    fn de<'s, T: JsonDeserialize<'s>>(src: &'s str) -> T {
+        let mut de = JsonDeserializer::new(src.as_bytes());
+        let t = T::json_deserialize(&mut de).unwrap();
+        de.finish().unwrap();
+        t
+    }
+
+    #[test]
+    fn test_containers() {
+        assert_eq!(de::<Vec<u8>>(" [1, 2 ,3] "), vec![1, 2, 3]);
+        assert_eq!(de::<Vec<u8>>("[]"), Vec::<u8>::new());
+        assert_eq!(de::<Option<Vec<u8>>>("null"), None);
+        assert_eq!(
+            de::<(u8, bool, String)>(r#"[1,true,"x"]"#),
+            (1, true, "x".into())
+        );
+        assert_eq!(
+            de::<HashMap<u32, Option<bool>>>(r#"{"1":true,"2":null}"#),
+            HashMap::from([(1, Some(true)), (2, None)])
+        );
+        assert_eq!(
+            de::<Vec<Value>>(r#"[{"a":[null]}, 1.5]"#),
+            vec![
+                Value::Map(Map::new().with("a", Array::new().with(Value::Null))),
+                Value::Float(1.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings_are_borrowed() {
+        let v: Vec<CowStr> = de(r#"["plain", "esc\naped"]"#);
+        assert!(matches!(v[0], CowStr::Borrowed("plain")));
+        assert!(matches!(v[1], CowStr::Owned(_)));
+        assert_eq!(v[1], "esc\naped");
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut de = JsonDeserializer::new(br#"[{"a":[1,{"b":"c"}],"d":null}, "e", 3]"#);
+        assert!(de.begin_array().unwrap());
+        de.skip_value().unwrap();
+        assert!(de.next_element().unwrap());
+        de.skip_value().unwrap();
+        assert!(de.next_element().unwrap());
+        assert_eq!(u8::json_deserialize(&mut de).unwrap(), 3);
+        assert!(!de.next_element().unwrap());
+        de.finish().unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let src = r#"[[1,2],[3,"x"]]"#;
+        let err = Vec::<Vec<u8>>::json_deserialize(&mut JsonDeserializer::new(src.as_bytes()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Merde Error: Expected Int, found String (at $[1][1])"
+        );
+
+        let err = <(u8, u8)>::json_deserialize(&mut JsonDeserializer::new(b"[1]")).unwrap_err();
+        assert_eq!(err.to_string(), "Merde Error: Expected Array, found Array");
+    }
+}
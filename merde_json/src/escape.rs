@@ -0,0 +1,218 @@
+//! Writing JSON strings, with escaping.
+
+/// Which characters [JsonSerializer::write_str](crate::JsonSerializer::write_str)
+/// escapes, on top of the ones JSON requires (quotes, backslashes and control characters).
+///
+/// ```
+/// use merde_json::{EscapeConfig, JsonSerialize, JsonSerializer};
+///
+/// let mut s = JsonSerializer::new().escaping(EscapeConfig::default().ascii_only(true).html_safe(true));
+/// "<café 🦀>".json_serialize(&mut s);
+/// assert_eq!(s.into_inner(), br#""\u003ccaf\u00e9 \ud83e\udd80\u003e""#);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EscapeConfig {
+    /// Escape every non-ASCII character as `\uXXXX` (using surrogate pairs
+    /// outside of the Basic Multilingual Plane), so the output is pure ASCII.
+    pub ascii_only: bool,
+
+    /// Escape `<`, `>` and `&`, so the output can be embedded in HTML, in a
+    /// `<script>` tag for example.
+    pub html_safe: bool,
+
+    /// Escape U+2028 LINE SEPARATOR and U+2029 PARAGRAPH SEPARATOR, which are
+    /// valid in JSON strings but weren't in JavaScript strings before ES2019.
+    pub line_separators: bool,
+}
+
+impl EscapeConfig {
+    /// Sets [EscapeConfig::ascii_only].
+    pub fn ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Sets [EscapeConfig::html_safe].
+    pub fn html_safe(mut self, html_safe: bool) -> Self {
+        self.html_safe = html_safe;
+        self
+    }
+
+    /// Sets [EscapeConfig::line_separators].
+    pub fn line_separators(mut self, line_separators: bool) -> Self {
+        self.line_separators = line_separators;
+        self
+    }
+
+    fn table(self) -> &'static [bool; 256] {
+        let index = self.ascii_only as usize
+            | (self.html_safe as usize) << 1
+            | (self.line_separators as usize) << 2;
+        &TABLES[index]
+    }
+}
+
+/// For every combination of [EscapeConfig] flags, which bytes can't be copied
+/// as-is. Non-ASCII bytes in there are the first byte of characters that might
+/// need escaping, the rest of the character gets looked at separately.
+static TABLES: [[bool; 256]; 8] = {
+    let mut tables = [[false; 256]; 8];
+    let mut index = 0;
+    while index < 8 {
+        let table = &mut tables[index];
+        let mut b = 0;
+        while b < 0x20 {
+            table[b] = true;
+            b += 1;
+        }
+        table[b'"' as usize] = true;
+        table[b'\\' as usize] = true;
+        // DEL, and the first byte of U+0080 to U+009F: also control characters
+        table[0x7f] = true;
+        table[0xc2] = true;
+        if index & 1 != 0 {
+            let mut b = 0x80;
+            while b < 0x100 {
+                table[b] = true;
+                b += 1;
+            }
+        }
+        if index & 2 != 0 {
+            table[b'<' as usize] = true;
+            table[b'>' as usize] = true;
+            table[b'&' as usize] = true;
+        }
+        if index & 4 != 0 {
+            // the first byte of U+2028 and U+2029
+            table[0xe2] = true;
+        }
+        index += 1;
+    }
+    tables
+};
+
+/// Writes `value` as a quoted JSON string. Runs of characters that don't need
+/// escaping are copied in one go.
+pub(crate) fn write_escaped(buffer: &mut Vec<u8>, value: &str, config: EscapeConfig) {
+    let table = config.table();
+    let bytes = value.as_bytes();
+
+    buffer.push(b'"');
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if !table[b as usize] {
+            i += 1;
+            continue;
+        }
+
+        buffer.extend_from_slice(&bytes[start..i]);
+        if b < 0x80 {
+            match b {
+                b'"' => buffer.extend_from_slice(b"\\\""),
+                b'\\' => buffer.extend_from_slice(b"\\\\"),
+                b'\n' => buffer.extend_from_slice(b"\\n"),
+                b'\r' => buffer.extend_from_slice(b"\\r"),
+                b'\t' => buffer.extend_from_slice(b"\\t"),
+                _ => write_u_escape(buffer, b as u16),
+            }
+            i += 1;
+        } else {
+            // `i` is always on a char boundary: we only ever skip whole characters
+            let c = value[i..].chars().next().unwrap();
+            let len = c.len_utf8();
+            let escape = config.ascii_only
+                || c.is_control()
+                || (config.line_separators && matches!(c, '\u{2028}' | '\u{2029}'));
+            if escape {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    write_u_escape(buffer, *unit);
+                }
+            } else {
+                buffer.extend_from_slice(&bytes[i..i + len]);
+            }
+            i += len;
+        }
+        start = i;
+    }
+    buffer.extend_from_slice(&bytes[start..]);
+    buffer.push(b'"');
+}
+
+fn write_u_escape(buffer: &mut Vec<u8>, unit: u16) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    buffer.extend_from_slice(&[
+        b'\\',
+        b'u',
+        HEX[(unit >> 12) as usize],
+        HEX[(unit >> 8 & 0xf) as usize],
+        HEX[(unit >> 4 & 0xf) as usize],
+        HEX[(unit & 0xf) as usize],
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{write_escaped, EscapeConfig};
+    use crate::from_str_via_value;
+
+    fn escape(value: &str, config: EscapeConfig) -> String {
+        let mut buffer = Vec::new();
+        write_escaped(&mut buffer, value, config);
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_escapes() {
+        let default = EscapeConfig::default();
+        assert_eq!(escape("plain", default), r#""plain""#);
+        assert_eq!(
+            escape("a\"b\\c\nd\re\tf\u{1}g\u{7f}h\u{85}", default),
+            r#""a\"b\\c\nd\re\tf\u0001g\u007fh\u0085""#
+        );
+        assert_eq!(escape("é\u{a0}🦀 <&>", default), "\"é\u{a0}🦀 <&>\"");
+        assert_eq!(escape("\u{2028}\u{2029}", default), "\"\u{2028}\u{2029}\"");
+
+        assert_eq!(
+            escape("é🦀\u{2028}", default.ascii_only(true)),
+            r#""\u00e9\ud83e\udd80\u2028""#
+        );
+        assert_eq!(
+            escape("</script>&", default.html_safe(true)),
+            r#""\u003c/script\u003e\u0026""#
+        );
+        assert_eq!(
+            escape("a\u{2028}b\u{2029}c…", default.line_separators(true)),
+            "\"a\\u2028b\\u2029c…\""
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            s in any::<String>(),
+            ascii_only in any::<bool>(),
+            html_safe in any::<bool>(),
+            line_separators in any::<bool>(),
+        ) {
+            let config = EscapeConfig { ascii_only, html_safe, line_separators };
+            let json = escape(&s, config);
+            if ascii_only {
+                prop_assert!(json.is_ascii());
+            }
+            if html_safe {
+                prop_assert!(!json.contains(['<', '>', '&']));
+            }
+            if line_separators {
+                let separators = ['\u{2028}', '\u{2029}'];
+                prop_assert!(!json.contains(separators));
+            }
+            prop_assert_eq!(from_str_via_value::<String>(&json).unwrap(), s);
+        }
+    }
+}
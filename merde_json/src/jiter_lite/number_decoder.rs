@@ -266,6 +266,25 @@ impl IntParse {
             IntChunk::Float => return Ok((Self::Float, new_index)),
         };
 
+        // we've read 19 digits, which may still fit in an i64 (it goes up to
+        // 9223372036854775807) if that's all there is
+        match decode_int_chunk_fallback(data, new_index, ongoing) {
+            (IntChunk::Float, end) => return Ok((Self::Float, end)),
+            (IntChunk::Done(value), end) if end == new_index => {
+                if positive && value <= i64::MAX as u64 {
+                    return Ok((Self::Int(NumberInt::Int(value as i64)), end));
+                }
+                if !positive && value <= i64::MAX as u64 + 1 {
+                    // -(2**63) is i64::MIN, which wrapping_neg leaves as-is
+                    return Ok((
+                        Self::Int(NumberInt::Int((value as i64).wrapping_neg())),
+                        end,
+                    ));
+                }
+            }
+            _ => {}
+        }
+
         // number is too big for i64, we need to use a BigInt,
         // or error out if num-bigint is not enabled
 
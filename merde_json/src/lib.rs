@@ -2,7 +2,18 @@
 #![doc = include_str!("../README.md")]
 
 mod jiter_lite;
-mod parser;
+pub mod parser;
+
+mod deserialize;
+pub use deserialize::{JsonDeserialize, JsonDeserializer};
+
+mod escape;
+pub use escape::EscapeConfig;
+
+pub mod ndjson;
+
+mod reader;
+pub use reader::JsonReader;
 
 use jiter_lite::errors::JiterError;
 use merde_core::{
@@ -14,20 +25,124 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::Write;
 
+/// How much [JsonSerializer::from_writer] buffers before writing to the underlying writer.
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+
 /// Writes JSON to a `Vec<u8>`. None of its methods can fail, since it doesn't target
 /// an `io::Write`. You can provide your own buffer via `JsonSerializer::from_vec`.
 ///
 /// When you're done with the serializer, you can call `JsonSerializer::into_inner` to
 /// get the buffer back.
+///
+/// To write to an `io::Write` without holding the whole document in memory, see
+/// [JsonSerializer::from_writer].
+///
+/// Output is compact by default, see [JsonSerializer::pretty] for indented output.
 #[derive(Default)]
-pub struct JsonSerializer {
+pub struct JsonSerializer<'w> {
     buffer: Vec<u8>,
+    /// Where the buffer gets flushed to, see [JsonSerializer::from_writer]
+    writer: Option<&'w mut dyn Write>,
+    /// The first error we got from `writer`, after which nothing else is written
+    io_error: Option<std::io::Error>,
+    /// How many bytes were flushed to `writer` so far
+    flushed: usize,
+    /// Where the array that might still get inlined (see [PrettyConfig::inline_arrays])
+    /// starts, counting flushed bytes: nothing past that gets flushed until it's closed
+    hold: Option<usize>,
+    pretty: Option<PrettyConfig>,
+    /// How many objects and arrays we're nested in
+    depth: usize,
+    /// How many objects and arrays we've started so far, lets an array tell
+    /// whether it has nested containers
+    containers: usize,
+    non_finite: NonFiniteFloats,
+    /// The first non-finite float we came across, with [NonFiniteFloats::Error]
+    non_finite_error: Option<f64>,
+    escaping: EscapeConfig,
 }
 
-impl JsonSerializer {
+/// What [JsonSerializer::write_f64] does with `NaN`, `Infinity` and `-Infinity`,
+/// which JSON has no way to represent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NonFiniteFloats {
+    /// Write `null`, like JavaScript's `JSON.stringify` does. This is the default.
+    #[default]
+    Null,
+
+    /// Write `NaN`, `Infinity` and `-Infinity`. That's not valid JSON, but it's what
+    /// some other implementations do. [from_str_via_value_lenient] reads it back.
+    Tokens,
+
+    /// Write `null`, and remember the value so that [JsonSerializer::try_into_inner]
+    /// fails with [MerdeJsonError::NonFiniteFloat]. [JsonSerializer::into_inner]
+    /// panics instead, rather than hand out the output with the value missing.
+    Error,
+}
+
+/// How [JsonSerializer::pretty] lays out JSON: each object property and array
+/// element on its own line, indented, with a space after colons.
+///
+/// ```
+/// use merde_json::{JsonSerialize, JsonSerializer, PrettyConfig};
+///
+/// let mut s = JsonSerializer::pretty(PrettyConfig::default().indent("\t").inline_arrays(4));
+/// vec![vec![1, 2], vec![3]].json_serialize(&mut s);
+/// assert_eq!(s.into_inner(), b"[\n\t[1, 2],\n\t[3]\n]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PrettyConfig {
+    /// What each nesting level is indented with, two spaces by default.
+    pub indent: Cow<'static, str>,
+
+    /// Whether to end the output with a newline, `false` by default.
+    pub trailing_newline: bool,
+
+    /// Arrays with at most this many elements are written on a single line,
+    /// as long as none of them are objects or arrays. `0` by default, which
+    /// never inlines.
+    pub inline_arrays: usize,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent: Cow::Borrowed("  "),
+            trailing_newline: false,
+            inline_arrays: 0,
+        }
+    }
+}
+
+impl PrettyConfig {
+    /// Sets [PrettyConfig::indent].
+    pub fn indent(mut self, indent: impl Into<Cow<'static, str>>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Sets [PrettyConfig::trailing_newline].
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Sets [PrettyConfig::inline_arrays].
+    pub fn inline_arrays(mut self, max_len: usize) -> Self {
+        self.inline_arrays = max_len;
+        self
+    }
+}
+
+impl<'w> JsonSerializer<'w> {
     /// Uses the provided buffer as the target for serialization.
     pub fn from_vec(vec: Vec<u8>) -> Self {
-        JsonSerializer { buffer: vec }
+        JsonSerializer {
+            buffer: vec,
+            ..Default::default()
+        }
     }
 
     /// Allocates a new buffer for serialization.
@@ -35,6 +150,56 @@ impl JsonSerializer {
         Self::default()
     }
 
+    /// Allocates a new buffer for indented, human-readable serialization.
+    pub fn pretty(config: PrettyConfig) -> Self {
+        JsonSerializer {
+            pretty: Some(config),
+            ..Default::default()
+        }
+    }
+
+    /// Writes to `writer` in chunks of a few kilobytes as serialization goes, instead
+    /// of building the whole document in memory.
+    ///
+    /// Writing can fail, but the methods used to serialize can't: the first I/O
+    /// error is kept, nothing is written after it, and [JsonSerializer::finish]
+    /// returns it.
+    ///
+    /// ```
+    /// use merde_json::{JsonSerialize, JsonSerializer};
+    ///
+    /// let mut out = Vec::new();
+    /// let mut s = JsonSerializer::from_writer(&mut out);
+    /// vec![1, 2, 3].json_serialize(&mut s);
+    /// s.finish().unwrap();
+    /// assert_eq!(out, b"[1,2,3]");
+    /// ```
+    pub fn from_writer(writer: &'w mut dyn Write) -> Self {
+        JsonSerializer {
+            writer: Some(writer),
+            ..Default::default()
+        }
+    }
+
+    /// Switches to indented, human-readable output, see [JsonSerializer::pretty]. Useful
+    /// along with [JsonSerializer::from_vec] or [JsonSerializer::from_writer].
+    pub fn with_pretty(mut self, config: PrettyConfig) -> Self {
+        self.pretty = Some(config);
+        self
+    }
+
+    /// Sets which characters get escaped in strings, on top of the ones that must be.
+    pub fn escaping(mut self, config: EscapeConfig) -> Self {
+        self.escaping = config;
+        self
+    }
+
+    /// Sets what to do with floats that JSON can't represent, see [NonFiniteFloats].
+    pub fn non_finite_floats(mut self, policy: NonFiniteFloats) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
     /// Writes the JSON `null` value.
     pub fn write_null(&mut self) {
         self.buffer.extend_from_slice(b"null");
@@ -49,38 +214,76 @@ impl JsonSerializer {
     /// Write a number as a JSON number. Numbers bigger than 2**53 might
     /// not be parsed correctly by other implementations.
     pub fn write_i64(&mut self, value: i64) {
-        let _ = write!(self.buffer, "{}", value);
+        self.buffer
+            .extend_from_slice(itoa::Buffer::new().format(value).as_bytes());
     }
 
-    /// Write a floating-point number as a JSON number.
+    /// Write a number as a JSON number, see [JsonSerializer::write_i64].
+    pub fn write_u64(&mut self, value: u64) {
+        self.buffer
+            .extend_from_slice(itoa::Buffer::new().format(value).as_bytes());
+    }
+
+    /// Write a floating-point number as a JSON number, with the fewest digits
+    /// that parse back to the same value. It always has a decimal point or an
+    /// exponent (`1.0`, not `1`), so it's read back as a float, not an integer.
+    ///
+    /// `NaN` and infinities are handled according to [JsonSerializer::non_finite_floats].
     pub fn write_f64(&mut self, value: f64) {
-        let _ = write!(self.buffer, "{}", value);
+        if value.is_finite() {
+            self.buffer
+                .extend_from_slice(ryu::Buffer::new().format_finite(value).as_bytes());
+        } else {
+            self.write_non_finite(value);
+        }
     }
 
-    /// Write a string, with escaping.
-    pub fn write_str(&mut self, value: &str) {
-        self.buffer.push(b'"');
-        for c in value.chars() {
-            match c {
-                '"' => self.buffer.extend_from_slice(b"\\\""),
-                '\\' => self.buffer.extend_from_slice(b"\\\\"),
-                '\n' => self.buffer.extend_from_slice(b"\\n"),
-                '\r' => self.buffer.extend_from_slice(b"\\r"),
-                '\t' => self.buffer.extend_from_slice(b"\\t"),
-                c if c.is_control() => {
-                    let _ = write!(self.buffer, "\\u{:04x}", c as u32);
-                }
-                c => self.buffer.extend_from_slice(c.to_string().as_bytes()),
+    /// Write a floating-point number as a JSON number, see [JsonSerializer::write_f64].
+    ///
+    /// The digits are the shortest that round-trip through an `f32`: `0.1f32` is
+    /// written as `0.1`, not `0.10000000149011612`.
+    pub fn write_f32(&mut self, value: f32) {
+        if value.is_finite() {
+            self.buffer
+                .extend_from_slice(ryu::Buffer::new().format_finite(value).as_bytes());
+        } else {
+            self.write_non_finite(value as f64);
+        }
+    }
+
+    fn write_non_finite(&mut self, value: f64) {
+        match self.non_finite {
+            NonFiniteFloats::Null => self.write_null(),
+            NonFiniteFloats::Tokens => {
+                self.buffer.extend_from_slice(if value.is_nan() {
+                    b"NaN"
+                } else if value > 0.0 {
+                    b"Infinity"
+                } else {
+                    b"-Infinity"
+                });
+            }
+            NonFiniteFloats::Error => {
+                self.non_finite_error.get_or_insert(value);
+                self.write_null();
             }
         }
-        self.buffer.push(b'"');
+    }
+
+    /// Write a string, with escaping.
+    pub fn write_str(&mut self, value: &str) {
+        escape::write_escaped(&mut self.buffer, value, self.escaping);
     }
 
     /// This writes the opening brace of an object, and gives you
     /// a guard object to write the key-value pairs. When the guard
     /// is dropped, the closing brace is written.
-    pub fn write_obj(&mut self) -> ObjectGuard<'_> {
+    pub fn write_obj(&mut self) -> ObjectGuard<'_, 'w> {
+        // arrays with objects in them are never inlined
+        self.hold = None;
         self.buffer.push(b'{');
+        self.depth += 1;
+        self.containers += 1;
         ObjectGuard {
             serializer: self,
             first: true,
@@ -90,19 +293,143 @@ impl JsonSerializer {
     /// This writes the opening bracket of an array, and gives you
     /// a guard object to write the elements. When the guard
     /// is dropped, the closing bracket is written.
-    pub fn write_arr(&mut self) -> ArrayGuard<'_> {
+    pub fn write_arr(&mut self) -> ArrayGuard<'_, 'w> {
         self.buffer.push(b'[');
+        self.depth += 1;
+        self.containers += 1;
+        let start = self.flushed + self.buffer.len();
+        // this array might get inlined, the one it's in (if any) won't be
+        self.hold = self
+            .pretty
+            .as_ref()
+            .is_some_and(|p| p.inline_arrays > 0)
+            .then_some(start);
         ArrayGuard {
+            start,
+            containers: self.containers,
+            len: 0,
             serializer: self,
-            first: true,
         }
     }
 
-    /// Get back the internal buffer
+    /// Get back the internal buffer, unless something couldn't be represented
+    /// as JSON (see [NonFiniteFloats::Error]).
+    pub fn try_into_inner(self) -> Result<Vec<u8>, MerdeJsonError<'static>> {
+        match self.non_finite_error {
+            Some(value) => Err(MerdeJsonError::NonFiniteFloat(value)),
+            None => Ok(self.take_buffer()),
+        }
+    }
+
+    /// Get back the internal buffer. With [JsonSerializer::from_writer], that's only
+    /// what hasn't been written yet: use [JsonSerializer::finish] instead.
+    ///
+    /// # Panics
+    ///
+    /// Under [NonFiniteFloats::Error], if a non-finite float was written: use
+    /// [JsonSerializer::try_into_inner] with that policy.
     pub fn into_inner(self) -> Vec<u8> {
+        if let Some(value) = self.non_finite_error {
+            panic!("{}", MerdeJsonError::NonFiniteFloat(value));
+        }
+        self.take_buffer()
+    }
+
+    fn take_buffer(mut self) -> Vec<u8> {
+        self.end_document();
         self.buffer
     }
 
+    /// Writes whatever is left to the writer given to [JsonSerializer::from_writer]
+    /// and flushes it. Fails with the first I/O error that happened along the way,
+    /// if any, or with [MerdeJsonError::NonFiniteFloat] (as [std::io::ErrorKind::InvalidData])
+    /// under [NonFiniteFloats::Error].
+    ///
+    /// Does nothing for serializers that write to a buffer.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.end_document();
+        if let Some(e) = self.io_error.take() {
+            return Err(e);
+        }
+        if let Some(value) = self.non_finite_error {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                MerdeJsonError::NonFiniteFloat(value),
+            ));
+        }
+        match self.writer {
+            Some(writer) => {
+                writer.write_all(&self.buffer)?;
+                writer.flush()
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn end_document(&mut self) {
+        if self.pretty.as_ref().is_some_and(|p| p.trailing_newline) {
+            self.buffer.push(b'\n');
+        }
+    }
+
+    /// With [JsonSerializer::from_writer], writes out the buffer once it's big enough,
+    /// except for an array that might still get inlined.
+    fn maybe_flush(&mut self) {
+        if self.buffer.len() < FLUSH_THRESHOLD {
+            return;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let end = match self.hold {
+            Some(start) => start - self.flushed,
+            None => self.buffer.len(),
+        };
+        if self.io_error.is_none() {
+            if let Err(e) = writer.write_all(&self.buffer[..end]) {
+                self.io_error = Some(e);
+            }
+        }
+        self.buffer.drain(..end);
+        self.flushed += end;
+    }
+
+    /// In pretty mode, starts a new line at the current indentation level.
+    fn newline(&mut self) {
+        if let Some(pretty) = &self.pretty {
+            self.buffer.push(b'\n');
+            for _ in 0..self.depth {
+                self.buffer.extend_from_slice(pretty.indent.as_bytes());
+            }
+        }
+    }
+
+    /// Writes whatever goes before an object property or array element.
+    fn begin_entry(&mut self, first: bool) {
+        if !first {
+            self.buffer.push(b',');
+        }
+        self.newline();
+    }
+
+    /// Writes whatever goes between an object key and its value.
+    fn write_colon(&mut self) {
+        if self.pretty.is_some() {
+            self.buffer.extend_from_slice(b": ");
+        } else {
+            self.buffer.push(b':');
+        }
+    }
+
+    /// Closes the current object or array, empty ones stay on one line.
+    fn end_container(&mut self, empty: bool, close: u8) {
+        self.depth -= 1;
+        if !empty {
+            self.newline();
+        }
+        self.buffer.push(close);
+    }
+
     /// Mutably borrow the internal buffer (as a `Vec<u8>` so it's growable).
     ///
     /// This is particularly useful when you want to use an interface like format_into that expects a dyn Writer?
@@ -112,56 +439,111 @@ impl JsonSerializer {
 }
 
 /// Allows writing JSON objects
-pub struct ObjectGuard<'a> {
-    serializer: &'a mut JsonSerializer,
+pub struct ObjectGuard<'a, 'w> {
+    serializer: &'a mut JsonSerializer<'w>,
     first: bool,
 }
 
-impl<'a> ObjectGuard<'a> {
+impl ObjectGuard<'_, '_> {
     /// Writes a key-value pair to the object.
     #[inline]
     pub fn pair(&mut self, key: &str, value: &dyn JsonSerialize) -> &mut Self {
-        if !self.first {
-            self.serializer.buffer.push(b',');
-        }
+        self.serializer.begin_entry(self.first);
         self.first = false;
         self.serializer.write_str(key);
-        self.serializer.buffer.push(b':');
+        self.serializer.write_colon();
         value.json_serialize(self.serializer);
+        self.serializer.maybe_flush();
+        self
+    }
+
+    /// Writes a key, then lets `f` write the value with the underlying serializer.
+    ///
+    /// Useful when the value doesn't have a [JsonSerialize] implementation of its own,
+    /// like a nested object built on the fly.
+    #[inline]
+    pub fn pair_with(&mut self, key: &str, f: impl FnOnce(&mut JsonSerializer<'_>)) -> &mut Self {
+        self.serializer.begin_entry(self.first);
+        self.first = false;
+        self.serializer.write_str(key);
+        self.serializer.write_colon();
+        f(self.serializer);
+        self.serializer.maybe_flush();
         self
     }
 }
 
-impl<'a> Drop for ObjectGuard<'a> {
+impl Drop for ObjectGuard<'_, '_> {
     #[inline]
     fn drop(&mut self) {
-        self.serializer.buffer.push(b'}');
+        self.serializer.end_container(self.first, b'}');
+        self.serializer.maybe_flush();
     }
 }
 
 /// A guard object for writing an array.
-pub struct ArrayGuard<'a> {
-    serializer: &'a mut JsonSerializer,
-    first: bool,
+pub struct ArrayGuard<'a, 'w> {
+    serializer: &'a mut JsonSerializer<'w>,
+    /// Where the first element starts, counting bytes already flushed
+    start: usize,
+    /// [JsonSerializer::containers] when the array was started
+    containers: usize,
+    len: usize,
 }
 
-impl<'a> ArrayGuard<'a> {
+impl ArrayGuard<'_, '_> {
     /// Writes an element to the array.
     #[inline]
     pub fn elem(&mut self, value: &dyn JsonSerialize) -> &mut Self {
-        if !self.first {
-            self.serializer.buffer.push(b',');
-        }
-        self.first = false;
+        self.serializer.begin_entry(self.len == 0);
+        self.len += 1;
         value.json_serialize(self.serializer);
+        let s = &mut *self.serializer;
+        if s.hold == Some(self.start) && s.pretty.as_ref().map_or(0, |p| p.inline_arrays) < self.len
+        {
+            s.hold = None;
+        }
+        s.maybe_flush();
         self
     }
+
+    /// Puts the elements (written one per line) back on a single line, if the
+    /// array is short and flat enough, see [PrettyConfig::inline_arrays].
+    fn inline(&mut self) -> bool {
+        let s = &mut *self.serializer;
+        let Some(pretty) = &s.pretty else {
+            return false;
+        };
+        if self.len == 0 || self.len > pretty.inline_arrays || s.containers != self.containers {
+            return false;
+        }
+
+        let prefix = pretty.indent.repeat(s.depth);
+        // held back from flushing so far, see `JsonSerializer::hold`
+        let lines = s.buffer.split_off(self.start - s.flushed);
+        for (i, line) in lines.split(|&b| b == b'\n').skip(1).enumerate() {
+            if i > 0 {
+                s.buffer.push(b' ');
+            }
+            s.buffer
+                .extend_from_slice(line.strip_prefix(prefix.as_bytes()).unwrap_or(line));
+        }
+        s.depth -= 1;
+        s.buffer.push(b']');
+        true
+    }
 }
 
-impl<'a> Drop for ArrayGuard<'a> {
+impl Drop for ArrayGuard<'_, '_> {
     #[inline]
     fn drop(&mut self) {
-        self.serializer.buffer.push(b']');
+        if !self.inline() {
+            self.serializer.end_container(self.len == 0, b']');
+        }
+        if self.serializer.hold == Some(self.start) {
+            self.serializer.hold = None;
+        }
+        self.serializer.maybe_flush();
     }
 }
 
@@ -199,6 +581,16 @@ pub trait JsonSerialize {
     }
 }
 
+/// [JsonSerialize] types that always write an object.
+///
+/// Internally tagged enums (`#[merde(tag = "...")]`) require it of the content
+/// of their newtype variants, since the tag is added to that object. It's derived
+/// along with `JsonSerialize` for structs, and implemented for [Map] and [HashMap].
+pub trait JsonSerializeObject: JsonSerialize {
+    /// Write the key-value pairs of self to an object that's already open.
+    fn json_serialize_pairs(&self, guard: &mut ObjectGuard<'_, '_>);
+}
+
 impl JsonSerialize for Value<'_> {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
         match self {
@@ -215,7 +607,12 @@ impl JsonSerialize for Value<'_> {
 
 impl JsonSerialize for Map<'_> {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
-        let mut guard = serializer.write_obj();
+        self.json_serialize_pairs(&mut serializer.write_obj());
+    }
+}
+
+impl JsonSerializeObject for Map<'_> {
+    fn json_serialize_pairs(&self, guard: &mut ObjectGuard<'_, '_>) {
         for (key, value) in self.iter() {
             guard.pair(key, value);
         }
@@ -241,13 +638,23 @@ where
     }
 }
 
+impl<T> JsonSerializeObject for &T
+where
+    T: ?Sized + JsonSerializeObject,
+{
+    fn json_serialize_pairs(&self, guard: &mut ObjectGuard<'_, '_>) {
+        let this: &T = self;
+        JsonSerializeObject::json_serialize_pairs(this, guard)
+    }
+}
+
 impl JsonSerialize for String {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
         serializer.write_str(self)
     }
 }
 
-impl<'s> JsonSerialize for &'s str {
+impl JsonSerialize for &str {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
         serializer.write_str(self)
     }
@@ -285,7 +692,7 @@ impl JsonSerialize for u32 {
 
 impl JsonSerialize for u64 {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
-        serializer.write_i64(*self as i64);
+        serializer.write_u64(*self);
     }
 }
 
@@ -315,7 +722,7 @@ impl JsonSerialize for i64 {
 
 impl JsonSerialize for usize {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
-        serializer.write_i64(*self as i64);
+        serializer.write_u64(*self as u64);
     }
 }
 
@@ -325,6 +732,18 @@ impl JsonSerialize for isize {
     }
 }
 
+impl JsonSerialize for f32 {
+    fn json_serialize(&self, serializer: &mut JsonSerializer) {
+        serializer.write_f32(*self);
+    }
+}
+
+impl JsonSerialize for f64 {
+    fn json_serialize(&self, serializer: &mut JsonSerializer) {
+        serializer.write_f64(*self);
+    }
+}
+
 impl JsonSerialize for bool {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
         serializer.write_bool(*self);
@@ -333,7 +752,12 @@ impl JsonSerialize for bool {
 
 impl<K: AsRef<str>, V: JsonSerialize> JsonSerialize for HashMap<K, V> {
     fn json_serialize(&self, serializer: &mut JsonSerializer) {
-        let mut guard = serializer.write_obj();
+        self.json_serialize_pairs(&mut serializer.write_obj());
+    }
+}
+
+impl<K: AsRef<str>, V: JsonSerialize> JsonSerializeObject for HashMap<K, V> {
+    fn json_serialize_pairs(&self, guard: &mut ObjectGuard<'_, '_>) {
         for (key, value) in self {
             guard.pair(key.as_ref(), value);
         }
@@ -502,6 +926,28 @@ pub enum MerdeJsonError<'s> {
         /// The JSON source, if available
         source: Option<CowStr<'s>>,
     },
+
+    /// A [MerdeError], along with where the offending value is in the JSON source,
+    /// see [from_str_via_value_spanned]
+    SpannedMerdeError {
+        /// The underlying merde error
+        err: MerdeError,
+        /// The byte range of the offending value in the JSON source
+        span: std::ops::Range<usize>,
+        /// The JSON source, if available
+        source: Option<CowStr<'s>>,
+    },
+
+    /// We were asked to serialize `NaN` or an infinity, with [NonFiniteFloats::Error]
+    NonFiniteFloat(f64),
+
+    /// An error in one of the records of an NDJSON stream, see [ndjson]
+    Line {
+        /// The 1-based line number of the record
+        line: usize,
+        /// The underlying error, with a source (if any) that only covers that line
+        err: Box<MerdeJsonError<'s>>,
+    },
 }
 
 impl<'s> MerdeJsonError<'s> {
@@ -513,6 +959,20 @@ impl<'s> MerdeJsonError<'s> {
             MerdeJsonError::JiterError { err, source: _ } => {
                 MerdeJsonError::JiterError { err, source: None }
             }
+            MerdeJsonError::SpannedMerdeError {
+                err,
+                span,
+                source: _,
+            } => MerdeJsonError::SpannedMerdeError {
+                err,
+                span,
+                source: None,
+            },
+            MerdeJsonError::NonFiniteFloat(v) => MerdeJsonError::NonFiniteFloat(v),
+            MerdeJsonError::Line { line, err } => MerdeJsonError::Line {
+                line,
+                err: Box::new(err.without_source()),
+            },
         }
     }
 
@@ -525,6 +985,68 @@ impl<'s> MerdeJsonError<'s> {
                 err,
                 source: source.map(|s| s.into_static()),
             },
+            MerdeJsonError::SpannedMerdeError { err, span, source } => {
+                MerdeJsonError::SpannedMerdeError {
+                    err,
+                    span,
+                    source: source.map(|s| s.into_static()),
+                }
+            }
+            MerdeJsonError::NonFiniteFloat(v) => MerdeJsonError::NonFiniteFloat(v),
+            MerdeJsonError::Line { line, err } => MerdeJsonError::Line {
+                line,
+                err: Box::new(err.to_static()),
+            },
+        }
+    }
+
+    /// Records that this error happened on (1-based) `line`, see [MerdeJsonError::Line].
+    pub fn at_line(self, line: usize) -> Self {
+        MerdeJsonError::Line {
+            line,
+            err: Box::new(self),
+        }
+    }
+
+    /// The line this error happened on, if it's a [MerdeJsonError::Line].
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            MerdeJsonError::Line { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+}
+
+impl MerdeJsonError<'_> {
+    /// Records that this error happened under `key`, see [MerdeError::at_key].
+    /// Only [MerdeJsonError::MerdeError] has a path, other errors are returned as-is.
+    pub fn at_key(self, key: impl Into<CowStr<'static>>) -> Self {
+        match self {
+            MerdeJsonError::MerdeError(e) => MerdeJsonError::MerdeError(e.at_key(key)),
+            e => e,
+        }
+    }
+
+    /// Records that this error happened at `index`, see [MerdeError::at_index].
+    /// Only [MerdeJsonError::MerdeError] has a path, other errors are returned as-is.
+    pub fn at_index(self, index: usize) -> Self {
+        match self {
+            MerdeJsonError::MerdeError(e) => MerdeJsonError::MerdeError(e.at_index(index)),
+            e => e,
+        }
+    }
+
+    /// Attaches `src` to syntax errors that don't have a source yet.
+    fn with_source<'s>(self, src: &'s str) -> MerdeJsonError<'s>
+    where
+        Self: 's,
+    {
+        match self {
+            MerdeJsonError::JiterError { err, source: None } => MerdeJsonError::JiterError {
+                err,
+                source: Some(src.into()),
+            },
+            e => e,
         }
     }
 }
@@ -542,27 +1064,50 @@ impl<'s> std::fmt::Display for MerdeJsonError<'s> {
             MerdeJsonError::Utf8Error(ue) => write!(f, "UTF-8 Error: {}", ue),
             MerdeJsonError::JiterError { err, source } => {
                 writeln!(f, "JSON parsing error: \x1b[31m{}\x1b[0m", err.error_type)?;
-                if let Some(source) = source {
-                    let context_start = err.index.saturating_sub(20);
-                    let context_end = (err.index + 20).min(source.len());
-                    let context = &source[context_start..context_end];
-
-                    write!(f, "Source: ")?;
-                    for (i, c) in context.char_indices() {
-                        if i + context_start == err.index {
-                            write!(f, "\x1b[48;2;255;200;200m\x1b[97m{}\x1b[0m", c)?;
-                        } else {
-                            write!(f, "\x1b[48;2;200;200;255m\x1b[97m{}\x1b[0m", c)?;
-                        }
-                    }
-                    writeln!(f)?;
-                } else {
-                    writeln!(f, "Error context: (not attached)")?;
-                }
-                Ok(())
+                write_excerpt(f, source.as_deref(), err.index..err.index + 1)
+            }
+            MerdeJsonError::SpannedMerdeError { err, span, source } => {
+                writeln!(f, "Merde Error: {}", err)?;
+                write_excerpt(f, source.as_deref(), span.clone())
+            }
+            MerdeJsonError::NonFiniteFloat(v) => {
+                write!(f, "Cannot serialize {} as JSON", v)
             }
+            MerdeJsonError::Line { line, err } => write!(f, "Line {}: {}", line, err),
+        }
+    }
+}
+
+/// Shows `highlight` in red, in the middle of some context from `source`.
+fn write_excerpt(
+    f: &mut std::fmt::Formatter<'_>,
+    source: Option<&str>,
+    highlight: std::ops::Range<usize>,
+) -> std::fmt::Result {
+    let Some(source) = source else {
+        return writeln!(f, "Error context: (not attached)");
+    };
+
+    let floor = |mut i: usize| {
+        i = i.min(source.len());
+        while !source.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    };
+    let context_start = floor(highlight.start.saturating_sub(20));
+    let context_end = floor(highlight.end.saturating_sub(1).max(highlight.start) + 20);
+    let context = &source[context_start..context_end];
+
+    write!(f, "Source: ")?;
+    for (i, c) in context.char_indices() {
+        if highlight.contains(&(i + context_start)) {
+            write!(f, "\x1b[48;2;255;200;200m\x1b[97m{}\x1b[0m", c)?;
+        } else {
+            write!(f, "\x1b[48;2;200;200;255m\x1b[97m{}\x1b[0m", c)?;
         }
     }
+    writeln!(f)
 }
 
 impl<'s> std::error::Error for MerdeJsonError<'s> {}
@@ -588,6 +1133,9 @@ where
 }
 
 /// Deserialize an instance of type `T` from a string of JSON text.
+///
+/// `NaN`, `Infinity` and `-Infinity` aren't JSON, and are rejected: see
+/// [from_str_via_value_lenient] to accept them.
 pub fn from_str_via_value<'s, T>(s: &'s str) -> Result<T, MerdeJsonError<'s>>
 where
     T: ValueDeserialize<'s>,
@@ -599,6 +1147,83 @@ where
     Ok(merde_core::from_value(value)?)
 }
 
+/// Like [from_str_via_value], but also accepts `NaN`, `Infinity` and `-Infinity`
+/// as floats, e.g. to read back output written with [NonFiniteFloats::Tokens].
+pub fn from_str_via_value_lenient<'s, T>(s: &'s str) -> Result<T, MerdeJsonError<'s>>
+where
+    T: ValueDeserialize<'s>,
+{
+    let value = parser::json_bytes_to_value_lenient(s.as_bytes()).map_err(|e| {
+        MerdeJsonError::JiterError {
+            err: e,
+            source: Some(s.into()),
+        }
+    })?;
+    Ok(merde_core::from_value(value)?)
+}
+
+/// Deserialize an instance of type `T` from the JSON value at the start of `s`,
+/// ignoring anything after it (whereas [from_str_via_value] rejects trailing data).
+///
+/// Returns the number of bytes the value took up along with it, so that
+/// `&s[consumed..]` is whatever comes next.
+pub fn from_str_via_value_prefix<'s, T>(s: &'s str) -> Result<(T, usize), MerdeJsonError<'s>>
+where
+    T: ValueDeserialize<'s>,
+{
+    let (value, consumed) = parser::json_bytes_to_value_prefix(s.as_bytes()).map_err(|e| {
+        MerdeJsonError::JiterError {
+            err: e,
+            source: Some(s.into()),
+        }
+    })?;
+    Ok((merde_core::from_value(value)?, consumed))
+}
+
+/// Like [from_str_via_value], but if deserialization fails, the error
+/// ([MerdeJsonError::SpannedMerdeError]) points at the offending value in `s`.
+///
+/// This has no overhead when deserialization succeeds: `s` only gets parsed
+/// again (keeping track of where values are) if it fails.
+pub fn from_str_via_value_spanned<'s, T>(s: &'s str) -> Result<T, MerdeJsonError<'s>>
+where
+    T: ValueDeserialize<'s>,
+{
+    from_str_via_value(s).map_err(|e| with_span(e, s))
+}
+
+/// Like [owned_from_str_via_value], but if deserialization fails, the error
+/// ([MerdeJsonError::SpannedMerdeError]) points at the offending value in `s`.
+pub fn owned_from_str_via_value_spanned<T>(s: &str) -> Result<T, MerdeJsonError<'_>>
+where
+    T: OwnedValueDeserialize,
+{
+    owned_from_str_via_value(s).map_err(|e| with_span(e, s))
+}
+
+/// Turns a [MerdeJsonError::MerdeError] into a [MerdeJsonError::SpannedMerdeError]
+/// by finding the span of the value its path points to.
+fn with_span<'s>(e: MerdeJsonError<'s>, s: &'s str) -> MerdeJsonError<'s> {
+    let MerdeJsonError::MerdeError(err) = e else {
+        return e;
+    };
+    let span = parser::json_bytes_to_value_with_spans(s.as_bytes())
+        .ok()
+        .and_then(|(_, spans)| match err.path() {
+            Some(path) => spans.closest(path),
+            // the error is about the top-level value
+            None => spans.get(&merde_core::ErrorPath::default()),
+        });
+    match span {
+        Some(span) => MerdeJsonError::SpannedMerdeError {
+            err,
+            span,
+            source: Some(s.into()),
+        },
+        None => MerdeJsonError::MerdeError(err),
+    }
+}
+
 /// Deserialize an instance of type `T` from a string of JSON text, making
 /// sure the result is owned.
 pub fn owned_from_str_via_value<T>(s: &str) -> Result<T, MerdeJsonError<'_>>
@@ -614,6 +1239,60 @@ where
     ))?)
 }
 
+/// Deserialize an instance of type `T` from a string of JSON text, reading
+/// it directly with [JsonDeserialize], without building a [Value] tree first.
+pub fn from_str<'s, T>(s: &'s str) -> Result<T, MerdeJsonError<'s>>
+where
+    T: JsonDeserialize<'s>,
+{
+    let mut de = JsonDeserializer::new(s.as_bytes());
+    T::json_deserialize(&mut de)
+        .and_then(|t| de.finish().map(|_| t))
+        .map_err(|e| e.with_source(s))
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text, see [from_str].
+pub fn from_slice<'s, T>(data: &'s [u8]) -> Result<T, MerdeJsonError<'s>>
+where
+    T: JsonDeserialize<'s>,
+{
+    from_str(std::str::from_utf8(data)?)
+}
+
+/// Deserialize an instance of type `T` from a string of JSON text, see [from_str],
+/// making sure the result is owned.
+pub fn owned_from_str<T>(s: &str) -> Result<T, MerdeJsonError<'_>>
+where
+    T: 'static,
+    for<'s> T: merde_core::WithLifetime<'s>,
+    for<'s> <T as merde_core::WithLifetime<'s>>::Lifetimed:
+        JsonDeserialize<'s> + IntoStatic<Output = T>,
+{
+    from_str::<<T as merde_core::WithLifetime<'_>>::Lifetimed>(s).map(|t| t.into_static())
+}
+
+/// Deserialize an instance of type `T` from an I/O stream of JSON text, reading
+/// it until EOF. See [JsonReader] to reuse the buffer across documents.
+pub fn from_reader<R, T>(reader: R) -> Result<T, MerdeJsonError<'static>>
+where
+    R: std::io::Read,
+    T: OwnedValueDeserialize,
+{
+    JsonReader::new().read(reader)
+}
+
+/// Deserialize an instance of type `T` from an async I/O stream of JSON text,
+/// reading it until EOF. See [JsonReader::read_async] to reuse the buffer across
+/// documents.
+#[cfg(feature = "async")]
+pub async fn from_async_reader<R, T>(reader: R) -> Result<T, MerdeJsonError<'static>>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    T: OwnedValueDeserialize,
+{
+    JsonReader::new().read_async(reader).await
+}
+
 /// Serialize the given data structure as a String of JSON.
 pub fn to_string<T: JsonSerialize>(value: &T) -> String {
     value.to_json_string()
@@ -624,11 +1303,206 @@ pub fn to_vec<T: JsonSerialize>(value: &T) -> Vec<u8> {
     value.to_json_bytes()
 }
 
-/// Serialize the given data structure as JSON into the I/O stream.
-pub fn to_writer<W, T>(mut writer: impl std::io::Write, value: &T) -> std::io::Result<()>
+/// Serialize the given data structure as JSON into the I/O stream, a few kilobytes
+/// at a time, see [JsonSerializer::from_writer].
+pub fn to_writer<T>(mut writer: impl Write, value: &T) -> std::io::Result<()>
+where
+    T: JsonSerialize,
+{
+    let mut s = JsonSerializer::from_writer(&mut writer);
+    value.json_serialize(&mut s);
+    s.finish()
+}
+
+/// Serialize the given data structure as JSON into the async I/O stream, then
+/// flush it.
+///
+/// Unlike [to_writer], the whole document is serialized up front: serializing
+/// never waits on I/O.
+#[cfg(feature = "async")]
+pub async fn to_async_writer<W, T>(mut writer: W, value: &T) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    T: JsonSerialize,
+{
+    use tokio::io::AsyncWriteExt;
+
+    writer.write_all(&value.to_json_bytes()).await?;
+    writer.flush().await
+}
+
+/// Serialize the given data structure as a String of JSON, failing if it contains
+/// floats JSON can't represent, see [NonFiniteFloats::Error].
+pub fn try_to_string<T: JsonSerialize>(value: &T) -> Result<String, MerdeJsonError<'static>> {
+    // SAFETY: the serializer only ever writes valid UTF-8, see `to_json_string`
+    try_to_vec(value).map(|v| unsafe { String::from_utf8_unchecked(v) })
+}
+
+/// Serialize the given data structure as a JSON byte vector, failing if it contains
+/// floats JSON can't represent, see [NonFiniteFloats::Error].
+pub fn try_to_vec<T: JsonSerialize>(value: &T) -> Result<Vec<u8>, MerdeJsonError<'static>> {
+    let mut s = JsonSerializer::new().non_finite_floats(NonFiniteFloats::Error);
+    value.json_serialize(&mut s);
+    s.try_into_inner()
+}
+
+/// Serialize the given data structure as a String of indented JSON, with the
+/// default [PrettyConfig].
+pub fn to_string_pretty<T: JsonSerialize>(value: &T) -> String {
+    // SAFETY: the serializer only ever writes valid UTF-8, see `to_json_string`
+    unsafe { String::from_utf8_unchecked(to_vec_pretty(value)) }
+}
+
+/// Serialize the given data structure as a byte vector of indented JSON, with the
+/// default [PrettyConfig].
+pub fn to_vec_pretty<T: JsonSerialize>(value: &T) -> Vec<u8> {
+    let mut s = JsonSerializer::pretty(PrettyConfig::default());
+    value.json_serialize(&mut s);
+    s.into_inner()
+}
+
+/// Serialize the given data structure as indented JSON into the I/O stream, with
+/// the default [PrettyConfig].
+pub fn to_writer_pretty<T>(mut writer: impl Write, value: &T) -> std::io::Result<()>
 where
     T: JsonSerialize,
 {
-    let bytes = value.to_json_bytes();
-    writer.write_all(&bytes)
+    let mut s = JsonSerializer::from_writer(&mut writer).with_pretty(PrettyConfig::default());
+    value.json_serialize(&mut s);
+    s.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{Array, Value};
+    use proptest::prelude::*;
+
+    use super::{
+        from_str_via_value, to_writer, JsonSerialize, JsonSerializer, NonFiniteFloats,
+        PrettyConfig, FLUSH_THRESHOLD,
+    };
+
+    #[test]
+    fn test_numbers() {
+        for (value, expected) in [
+            (Value::Float(1.0), "1.0"),
+            (Value::Float(-0.0), "-0.0"),
+            (Value::Float(0.1), "0.1"),
+            (Value::Float(1e300), "1e300"),
+            (Value::Float(5e-324), "5e-324"),
+            (Value::Int(i64::MIN), "-9223372036854775808"),
+            (Value::Int(i64::MAX), "9223372036854775807"),
+            (Value::Int(1_000_000_000_000_000_000), "1000000000000000000"),
+        ] {
+            let json = value.to_json_string();
+            assert_eq!(json, expected);
+            assert_eq!(from_str_via_value::<Value>(&json).unwrap(), value);
+        }
+
+        assert_eq!(u64::MAX.to_json_string(), "18446744073709551615");
+        assert_eq!(0.1f32.to_json_string(), "0.1");
+        assert_eq!(f64::MAX.to_json_string(), "1.7976931348623157e308");
+    }
+
+    /// Remembers how big each write was
+    #[derive(Default)]
+    struct Chunks {
+        sizes: Vec<usize>,
+        out: Vec<u8>,
+    }
+
+    impl std::io::Write for Chunks {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sizes.push(buf.len());
+            self.out.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_to_writer_streams() {
+        let strings: Vec<String> = (0..10_000).map(|i| format!("item-{i}")).collect();
+        let mut chunks = Chunks::default();
+        to_writer(&mut chunks, &strings).unwrap();
+        assert_eq!(chunks.out, strings.to_json_bytes());
+        assert!(chunks.sizes.len() > 1);
+        assert!(chunks.sizes.iter().all(|&n| n < FLUSH_THRESHOLD + 32));
+
+        // short flat arrays are inlined in the buffer, so they can't be flushed halfway
+        let config = PrettyConfig::default().inline_arrays(4);
+        let nested = vec![vec![12345u32; 3]; 5000];
+        let flat = vec![1u32; 20_000];
+        for value in [&nested, &vec![flat]] {
+            let mut chunks = Chunks::default();
+            let mut s = JsonSerializer::from_writer(&mut chunks).with_pretty(config.clone());
+            value.json_serialize(&mut s);
+            s.finish().unwrap();
+            assert!(chunks.sizes.len() > 1);
+
+            let mut s = JsonSerializer::pretty(config.clone());
+            value.json_serialize(&mut s);
+            assert_eq!(chunks.out, s.into_inner());
+        }
+    }
+
+    #[test]
+    fn test_to_writer_errors() {
+        struct Full;
+        impl std::io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::WriteZero.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut full = Full;
+        let mut s = JsonSerializer::from_writer(&mut full);
+        vec![u64::MAX; 100_000].json_serialize(&mut s);
+        // nothing piles up once writing has failed
+        assert!(s.buffer.len() < FLUSH_THRESHOLD * 2);
+        let err = s.finish().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+
+        let mut out = Vec::new();
+        let mut s = JsonSerializer::from_writer(&mut out).non_finite_floats(NonFiniteFloats::Error);
+        f64::NAN.json_serialize(&mut s);
+        let err = s.finish().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot serialize NaN as JSON")]
+    fn test_into_inner_does_not_drop_non_finite_floats() {
+        let mut s = JsonSerializer::new().non_finite_floats(NonFiniteFloats::Error);
+        vec![1.0, f64::NAN].json_serialize(&mut s);
+        s.into_inner();
+    }
+
+    proptest! {
+        #[test]
+        fn test_floats_round_trip(f in any::<f64>().prop_filter("finite", |f| f.is_finite())) {
+            let value = Value::Array(Array::new().with(Value::Float(f)));
+            let json = value.to_json_string();
+            let back: Value = from_str_via_value(&json).unwrap();
+            let Value::Array(arr) = back else {
+                panic!("expected an array, got {back:?}");
+            };
+            match arr[0] {
+                Value::Float(g) => prop_assert_eq!(f.to_bits(), g.to_bits(), "{}", json),
+                ref other => prop_assert!(false, "{} came back as {:?}", json, other),
+            }
+        }
+
+        #[test]
+        fn test_ints_round_trip(i in any::<i64>()) {
+            let json = Value::Int(i).to_json_string();
+            prop_assert_eq!(from_str_via_value::<Value>(&json).unwrap(), Value::Int(i));
+        }
+    }
 }
@@ -0,0 +1,468 @@
+//! Reading and writing [NDJSON](https://github.com/ndjson/ndjson-spec) (also known
+//! as [JSON Lines](https://jsonlines.org/)): one JSON value per line.
+//!
+//! Records are read one at a time, so files don't need to fit in memory. Errors
+//! are wrapped in [MerdeJsonError::Line], which carries the 1-based line number
+//! of the offending record. Blank lines are skipped, and `\r\n` line endings are
+//! accepted.
+//!
+//! With the `async` feature, [from_async_reader] and [AsyncWriter] do the same over
+//! tokio's `AsyncBufRead` and `AsyncWrite`.
+//!
+//! ```
+//! use merde_json::ndjson;
+//!
+//! let input = b"[1, 2]\n[3]\n\n[\"four\"]\n";
+//! let mut records = ndjson::from_slice::<Vec<u32>>(input);
+//! assert_eq!(records.next().unwrap().unwrap(), vec![1, 2]);
+//! assert_eq!(records.next().unwrap().unwrap(), vec![3]);
+//! assert_eq!(records.next().unwrap().unwrap_err().line(), Some(4));
+//! assert!(records.next().is_none());
+//! ```
+
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+use merde_core::{MerdeError, OwnedValueDeserialize, ValueDeserialize};
+
+use crate::{
+    from_slice_via_value, owned_from_str_via_value, to_writer, JsonSerialize, MerdeJsonError,
+};
+
+/// Reads records out of NDJSON text held in memory, borrowing from it where
+/// possible.
+pub fn from_slice<'s, T>(data: &'s [u8]) -> SliceIter<'s, T>
+where
+    T: ValueDeserialize<'s>,
+{
+    SliceIter {
+        rest: data,
+        line: 0,
+        _t: PhantomData,
+    }
+}
+
+/// Reads records out of a [BufRead] one line at a time, reusing the same line
+/// buffer throughout. Since that buffer gets overwritten, records are owned.
+///
+/// I/O errors are returned as [MerdeError::Io], after which iteration stops.
+pub fn from_reader<R, T>(reader: R) -> ReaderIter<R, T>
+where
+    R: BufRead,
+    T: OwnedValueDeserialize,
+{
+    ReaderIter {
+        reader,
+        buffer: Vec::new(),
+        line: 0,
+        done: false,
+        _t: PhantomData,
+    }
+}
+
+/// An iterator over the records of NDJSON text held in memory, see [from_slice].
+pub struct SliceIter<'s, T> {
+    rest: &'s [u8],
+    line: usize,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<'s, T> Iterator for SliceIter<'s, T>
+where
+    T: ValueDeserialize<'s>,
+{
+    type Item = Result<T, MerdeJsonError<'s>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() {
+            let (line, rest) = match self.rest.iter().position(|&b| b == b'\n') {
+                Some(i) => (&self.rest[..i], &self.rest[i + 1..]),
+                None => (self.rest, &[][..]),
+            };
+            self.rest = rest;
+            self.line += 1;
+
+            let line = trim_line(line);
+            if !line.is_empty() {
+                return Some(from_slice_via_value(line).map_err(|e| e.at_line(self.line)));
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the records read from a [BufRead], see [from_reader].
+pub struct ReaderIter<R, T> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    done: bool,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<R, T> ReaderIter<R, T> {
+    /// Gets back the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R, T> Iterator for ReaderIter<R, T>
+where
+    R: BufRead,
+    T: OwnedValueDeserialize,
+{
+    type Item = Result<T, MerdeJsonError<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    if let Some(result) = owned_from_line(&self.buffer, self.line) {
+                        return Some(result);
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    let err = MerdeJsonError::from(MerdeError::Io(e));
+                    return Some(Err(err.at_line(self.line + 1)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Deserializes an owned record out of a line read by [ReaderIter] or
+/// [AsyncReaderStream], or returns `None` if the line is blank.
+fn owned_from_line<T>(line: &[u8], number: usize) -> Option<Result<T, MerdeJsonError<'static>>>
+where
+    T: OwnedValueDeserialize,
+{
+    let line = trim_line(line);
+    if line.is_empty() {
+        return None;
+    }
+    let result = std::str::from_utf8(line)
+        .map_err(MerdeJsonError::from)
+        .and_then(owned_from_str_via_value)
+        .map_err(|e| e.to_static().at_line(number));
+    Some(result)
+}
+
+/// Strips the line ending, and treats lines with nothing but whitespace as empty.
+fn trim_line(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    if line.iter().all(u8::is_ascii_whitespace) {
+        &[]
+    } else {
+        line
+    }
+}
+
+/// Writes one compact JSON value per line to an [io::Write](std::io::Write).
+///
+/// Nothing is buffered here: wrap the writer in a [BufWriter](std::io::BufWriter)
+/// when writing lots of small records to a file or socket.
+pub struct Writer<W> {
+    writer: W,
+}
+
+impl<W: Write> Writer<W> {
+    /// Prepares to write records to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes `value` followed by a newline.
+    pub fn write<T: JsonSerialize>(&mut self, value: &T) -> std::io::Result<()> {
+        to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Gets back the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads records out of a tokio `AsyncBufRead` one line at a time, see [from_reader].
+/// The result is a [Stream](futures_core::Stream) of owned records.
+#[cfg(feature = "async")]
+pub fn from_async_reader<R, T>(reader: R) -> AsyncReaderStream<R, T>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    T: OwnedValueDeserialize,
+{
+    AsyncReaderStream {
+        reader,
+        buffer: Vec::new(),
+        line: 0,
+        done: false,
+        _t: PhantomData,
+    }
+}
+
+/// A stream of the records read from a tokio `AsyncBufRead`, see [from_async_reader].
+#[cfg(feature = "async")]
+pub struct AsyncReaderStream<R, T> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    done: bool,
+    _t: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "async")]
+impl<R, T> AsyncReaderStream<R, T> {
+    /// Gets back the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R, T> futures_core::Stream for AsyncReaderStream<R, T>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    T: OwnedValueDeserialize,
+{
+    type Item = Result<T, MerdeJsonError<'static>>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        while !this.done {
+            // same as `AsyncBufReadExt::read_until`, except the partial line
+            // lives in `this.buffer` between polls
+            let mut reader = std::pin::Pin::new(&mut this.reader);
+            let available = match reader.as_mut().poll_fill_buf(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(available)) => available,
+                Poll::Ready(Err(e)) => {
+                    this.done = true;
+                    let err = MerdeJsonError::from(MerdeError::Io(e));
+                    return Poll::Ready(Some(Err(err.at_line(this.line + 1))));
+                }
+            };
+            let (used, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), available.is_empty()),
+            };
+            this.buffer.extend_from_slice(&available[..used]);
+            reader.consume(used);
+            if used == 0 {
+                this.done = true;
+                if this.buffer.is_empty() {
+                    break;
+                }
+            }
+            if complete {
+                this.line += 1;
+                let result = owned_from_line(&this.buffer, this.line);
+                this.buffer.clear();
+                if let Some(result) = result {
+                    return Poll::Ready(Some(result));
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// Writes one compact JSON value per line to a tokio `AsyncWrite`, see [Writer].
+#[cfg(feature = "async")]
+pub struct AsyncWriter<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "async")]
+impl<W> AsyncWriter<W>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    /// Prepares to write records to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes `value` followed by a newline.
+    pub async fn write<T: JsonSerialize>(&mut self, value: &T) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut s = crate::JsonSerializer::from_vec(std::mem::take(&mut self.buffer));
+        value.json_serialize(&mut s);
+        self.buffer = s.into_inner();
+        self.buffer.push(b'\n');
+        let result = self.writer.write_all(&self.buffer).await;
+        self.buffer.clear();
+        result
+    }
+
+    /// Flushes the underlying writer.
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.writer.flush().await
+    }
+
+    /// Gets back the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::BufReader;
+
+    use merde_core::{CowStr, MerdeError};
+
+    use super::{from_reader, from_slice, Writer};
+    use crate::MerdeJsonError;
+
+    const INPUT: &str = "{\"a\":1}\r\n\n  \n{\"a\":2,\"b\":3}\n{\"a\":\"x\"}\n{\"a\":\n{\"a\":4}";
+
+    fn check<E: std::fmt::Debug>(records: Vec<Result<HashMap<String, u32>, E>>) {
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].as_ref().unwrap()["a"], 1);
+        assert_eq!(records[1].as_ref().unwrap()["b"], 3);
+        assert_eq!(records[4].as_ref().unwrap()["a"], 4);
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let records: Vec<_> = from_slice::<HashMap<String, u32>>(INPUT.as_bytes()).collect();
+
+        let err = records[2].as_ref().unwrap_err();
+        assert_eq!(err.line(), Some(5));
+        assert!(err.to_string().starts_with("Line 5: Merde Error"), "{err}");
+        let err = records[3].as_ref().unwrap_err();
+        assert_eq!(err.line(), Some(6));
+        assert!(err.to_string().contains("JSON parsing error"), "{err}");
+        check(records);
+
+        let borrowed: Vec<CowStr> = from_slice(b"\"plain\"\n")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(matches!(borrowed[0], CowStr::Borrowed("plain")));
+        assert_eq!(from_slice::<u8>(b"").count(), 0);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        // a tiny buffer, so lines span several reads
+        let reader = BufReader::with_capacity(3, INPUT.as_bytes());
+        let records: Vec<_> = from_reader::<_, HashMap<String, u32>>(reader).collect();
+        assert_eq!(records[2].as_ref().unwrap_err().line(), Some(5));
+        assert_eq!(records[3].as_ref().unwrap_err().line(), Some(6));
+        check(records);
+
+        let err = from_reader::<_, u8>(BufReader::new(&b"1\n\xff\n"[..]))
+            .nth(1)
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MerdeJsonError::Line { line: 2, ref err } if matches!(**err, MerdeJsonError::Utf8Error(_))
+        ));
+
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("boom"))
+            }
+        }
+        let mut records = from_reader::<_, u8>(BufReader::new(Failing));
+        let err = records.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err,
+            MerdeJsonError::Line { line: 1, ref err }
+                if matches!(**err, MerdeJsonError::MerdeError(MerdeError::Io(_)))
+        ));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut w = Writer::new(Vec::new());
+        w.write(&vec!["multi\nline"]).unwrap();
+        w.write(&HashMap::from([("k", 1)])).unwrap();
+        w.write(&None::<u8>).unwrap();
+        let out = w.into_inner();
+        assert_eq!(out, b"[\"multi\\nline\"]\n{\"k\":1}\nnull\n");
+
+        let back: Vec<crate::Value> = from_slice(&out).collect::<Result<_, _>>().unwrap();
+        assert_eq!(back.len(), 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async() {
+        use std::pin::Pin;
+
+        use futures_core::Stream;
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        use super::{from_async_reader, AsyncWriter};
+
+        let (client, server) = tokio::io::duplex(8);
+        let write = async {
+            let mut w = AsyncWriter::new(client);
+            for i in 0..3u32 {
+                w.write(&HashMap::from([("a", i)])).await.unwrap();
+            }
+            let mut client = w.into_inner();
+            client
+                .write_all(b"\r\n{\"a\": \"x\"}\n\xff\n{\"a\": 7}")
+                .await
+                .unwrap();
+        };
+        let read = async {
+            let mut stream = from_async_reader::<_, HashMap<String, u32>>(BufReader::new(server));
+            let mut records = Vec::new();
+            while let Some(record) =
+                std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+            {
+                records.push(record);
+            }
+            records
+        };
+        let ((), records) = tokio::join!(write, read);
+
+        assert_eq!(records.len(), 6);
+        for (i, record) in records[..3].iter().enumerate() {
+            assert_eq!(record.as_ref().unwrap()["a"], i as u32);
+        }
+        assert_eq!(records[3].as_ref().unwrap_err().line(), Some(5));
+        assert!(matches!(
+            records[4],
+            Err(MerdeJsonError::Line { line: 6, ref err })
+                if matches!(**err, MerdeJsonError::Utf8Error(_))
+        ));
+        assert_eq!(records[5].as_ref().unwrap()["a"], 7);
+    }
+}
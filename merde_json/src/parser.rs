@@ -1,3 +1,8 @@
+//! Turns JSON text into a [Value] tree.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
 use crate::jiter_lite as jiter;
 
 use jiter::errors::JiterError;
@@ -5,11 +10,79 @@ use jiter::jiter::Jiter;
 use jiter::number_decoder::NumberInt;
 use jiter::parse::Peek;
 
-use merde_core::{CowStr, Map, Value};
+use merde_core::{CowStr, ErrorPath, IntoStatic, Map, PathSegment, Value};
 
+/// Parses `src`, which must contain a single JSON value (and optionally, whitespace).
 pub(crate) fn json_bytes_to_value(src: &[u8]) -> Result<Value<'_>, JiterError> {
+    json_bytes_to_value_with(src, Jiter::new(src))
+}
+
+/// Like [json_bytes_to_value], but also accepts `NaN`, `Infinity` and `-Infinity`
+/// as numbers, which isn't valid JSON.
+pub(crate) fn json_bytes_to_value_lenient(src: &[u8]) -> Result<Value<'_>, JiterError> {
+    json_bytes_to_value_with(src, Jiter::new(src).with_allow_inf_nan())
+}
+
+fn json_bytes_to_value_with<'j>(
+    src: &'j [u8],
+    mut iter: Jiter<'j>,
+) -> Result<Value<'j>, JiterError> {
+    let value = jiter_to_value(src, &mut iter)?;
+    iter.finish()?;
+    Ok(value)
+}
+
+/// Parses the JSON value at the start of `src`, ignoring anything after it.
+///
+/// Returns the value along with the number of bytes it took up, including any
+/// leading whitespace: `src[consumed..]` is whatever comes after the value.
+pub fn json_bytes_to_value_prefix(src: &[u8]) -> Result<(Value<'_>, usize), JiterError> {
     let mut iter = Jiter::new(src);
-    jiter_to_value(src, &mut iter)
+    let value = jiter_to_value(src, &mut iter)?;
+    Ok((value, iter.current_index()))
+}
+
+/// Byte ranges of every value in a JSON document, keyed by their path in the
+/// document. See [json_bytes_to_value_with_spans].
+#[derive(Debug, Default, Clone)]
+pub struct Spans {
+    spans: HashMap<ErrorPath, Range<usize>>,
+}
+
+impl Spans {
+    /// The byte range of the value at `path`, if there's one.
+    pub fn get(&self, path: &ErrorPath) -> Option<Range<usize>> {
+        self.spans.get(path).cloned()
+    }
+
+    /// The byte range of the value at `path`, or of its closest ancestor if
+    /// there's no value there (for example, the object a property was missing from).
+    pub fn closest(&self, path: &ErrorPath) -> Option<Range<usize>> {
+        let segments: Vec<&PathSegment> = path.segments().collect();
+        (0..=segments.len()).rev().find_map(|len| {
+            let ancestor: ErrorPath = segments[..len].iter().map(|&s| s.clone()).collect();
+            self.get(&ancestor)
+        })
+    }
+}
+
+/// Records spans as we parse, see [Spans]
+#[derive(Default)]
+struct SpanRecorder {
+    path: Vec<PathSegment>,
+    spans: Spans,
+}
+
+/// Like parsing into a [Value], but also records where each value is in `src`,
+/// so that errors found later on (when deserializing the [Value]) can point back
+/// into the source. This is slower than just parsing, so it's opt-in.
+pub fn json_bytes_to_value_with_spans(src: &[u8]) -> Result<(Value<'_>, Spans), JiterError> {
+    let mut iter = Jiter::new(src);
+    let mut recorder = SpanRecorder::default();
+    let peek = iter.peek()?;
+    let value = jiter_to_value_inner(src, peek, &mut iter, Some(&mut recorder))?;
+    iter.finish()?;
+    Ok((value, recorder.spans))
 }
 
 pub(crate) fn jiter_to_value<'j>(
@@ -25,14 +98,22 @@ pub(crate) fn jiter_to_value_with_peek<'j>(
     peek: Peek,
     iter: &mut Jiter<'j>,
 ) -> Result<Value<'j>, JiterError> {
-    Ok(match peek {
+    jiter_to_value_inner(src, peek, iter, None)
+}
+
+fn jiter_to_value_inner<'j>(
+    src: &'j [u8],
+    peek: Peek,
+    iter: &mut Jiter<'j>,
+    mut spans: Option<&mut SpanRecorder>,
+) -> Result<Value<'j>, JiterError> {
+    let start = iter.current_index();
+    let value = match peek {
         Peek::Null => {
             iter.known_null()?;
             Value::Null
         }
         Peek::True | Peek::False => iter.known_bool(peek)?.into(),
-        Peek::Infinity => Value::Float(f64::INFINITY),
-        Peek::NaN => Value::Float(f64::NAN),
         Peek::String => {
             let s = iter.known_str()?;
             Value::Str(cowify(src, s))
@@ -41,7 +122,13 @@ pub(crate) fn jiter_to_value_with_peek<'j>(
             let mut arr = Vec::new();
             let mut next = iter.known_array()?;
             while let Some(peek) = next {
-                arr.push(jiter_to_value_with_peek(src, peek, iter)?);
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.path.push(PathSegment::Index(arr.len()));
+                }
+                arr.push(jiter_to_value_inner(src, peek, iter, spans.as_deref_mut())?);
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.path.pop();
+                }
                 next = iter.array_step()?;
             }
             Value::Array(arr.into())
@@ -51,7 +138,13 @@ pub(crate) fn jiter_to_value_with_peek<'j>(
             let mut next = iter.known_object()?;
             while let Some(key) = next {
                 let key = cowify(src, key);
-                let value = jiter_to_value_with_peek(src, iter.peek()?, iter)?;
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.path.push(PathSegment::Key(key.clone().into_static()));
+                }
+                let value = jiter_to_value_inner(src, iter.peek()?, iter, spans.as_deref_mut())?;
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.path.pop();
+                }
                 obj.insert(key, value);
                 next = iter.next_key()?;
             }
@@ -74,17 +167,28 @@ pub(crate) fn jiter_to_value_with_peek<'j>(
                         .into());
                     }
                 }
-            } else if let Ok(f) = iter.next_float() {
-                Value::Float(f)
             } else {
-                unreachable!("not an int, not a float!")
+                Value::Float(iter.next_float()?)
+            }
+        }
+        _ => {
+            use crate::jiter_lite::errors::{JsonError, JsonErrorType};
+            return Err(JsonError {
+                error_type: JsonErrorType::ExpectedSomeValue,
+                index: start,
             }
+            .into());
         }
-        _ => unimplemented!("peek {:?}", peek),
-    })
+    };
+
+    if let Some(spans) = spans {
+        let path = spans.path.iter().cloned().collect();
+        spans.spans.spans.insert(path, start..iter.current_index());
+    }
+    Ok(value)
 }
 
-fn cowify<'j>(src: &'j [u8], s: &str) -> CowStr<'j> {
+pub(crate) fn cowify<'j>(src: &'j [u8], s: &str) -> CowStr<'j> {
     if src.as_ptr_range().contains(&s.as_ptr()) {
         CowStr::Borrowed(unsafe {
             std::str::from_utf8_unchecked(std::slice::from_raw_parts(s.as_ptr(), s.len()))
@@ -98,7 +202,13 @@ fn cowify<'j>(src: &'j [u8], s: &str) -> CowStr<'j> {
 mod tests {
     use merde_core::{Array, CowStr, Map, Value};
 
-    use crate::parser::{cowify, json_bytes_to_value};
+    use merde_core::{ErrorPath, PathSegment};
+
+    use crate::jiter_lite::errors::{JiterErrorType, JsonErrorType};
+    use crate::parser::{
+        cowify, json_bytes_to_value, json_bytes_to_value_lenient, json_bytes_to_value_prefix,
+        json_bytes_to_value_with_spans,
+    };
 
     #[test]
     fn test_cowify() {
@@ -111,6 +221,26 @@ mod tests {
         assert_eq!(cowify(src.as_bytes(), s), CowStr::Owned(s.into()));
     }
 
+    #[test]
+    fn test_non_finite_numbers() {
+        let value = json_bytes_to_value_lenient(b"[NaN, Infinity, -Infinity, -1]").unwrap();
+        let Value::Array(arr) = value else {
+            panic!("expected an array, got {value:?}");
+        };
+        assert!(matches!(arr[0], Value::Float(f) if f.is_nan()));
+        assert_eq!(arr[1], Value::Float(f64::INFINITY));
+        assert_eq!(arr[2], Value::Float(f64::NEG_INFINITY));
+        assert_eq!(arr[3], Value::Int(-1));
+
+        assert!(json_bytes_to_value_lenient(b"[Infinit]").is_err());
+        assert!(json_bytes_to_value_lenient(b"-Inf").is_err());
+
+        for src in ["NaN", "Infinity", "-Infinity", "[1, NaN]"] {
+            assert!(json_bytes_to_value(src.as_bytes()).is_err(), "{src}");
+            assert!(json_bytes_to_value_prefix(src.as_bytes()).is_err(), "{src}");
+        }
+    }
+
     #[test]
     fn test_jiter_to_value() {
         let src = r#"
@@ -156,4 +286,86 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_spans() {
+        let src = r#"{ "a": [1, {"b": "hi"}], "c": null }"#;
+        let (value, spans) = json_bytes_to_value_with_spans(src.as_bytes()).unwrap();
+        assert_eq!(value, json_bytes_to_value(src.as_bytes()).unwrap());
+
+        let span_of = |segments: Vec<PathSegment>| {
+            let path: ErrorPath = segments.into_iter().collect();
+            &src[spans.closest(&path).unwrap()]
+        };
+        assert_eq!(span_of(vec![]), src);
+        assert_eq!(
+            span_of(vec![PathSegment::Key("a".into())]),
+            r#"[1, {"b": "hi"}]"#
+        );
+        assert_eq!(
+            span_of(vec![PathSegment::Key("a".into()), PathSegment::Index(1)]),
+            r#"{"b": "hi"}"#
+        );
+        assert_eq!(
+            span_of(vec![
+                PathSegment::Key("a".into()),
+                PathSegment::Index(1),
+                PathSegment::Key("b".into())
+            ]),
+            r#""hi""#
+        );
+        assert_eq!(span_of(vec![PathSegment::Key("c".into())]), "null");
+
+        // missing values point at their closest ancestor
+        assert_eq!(
+            span_of(vec![
+                PathSegment::Key("a".into()),
+                PathSegment::Index(1),
+                PathSegment::Key("nope".into())
+            ]),
+            r#"{"b": "hi"}"#
+        );
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        for (src, index) in [(r#"{"a":1} garbage"#, 8), ("[1][2]", 3), ("1 2", 2)] {
+            let err = json_bytes_to_value(src.as_bytes()).unwrap_err();
+            assert_eq!(
+                err.error_type,
+                JiterErrorType::JsonError(JsonErrorType::TrailingCharacters)
+            );
+            assert_eq!(err.index, index);
+        }
+
+        // trailing whitespace is fine
+        assert_eq!(
+            json_bytes_to_value(b" [1] \n\t").unwrap(),
+            Value::Array(Array::new().with(Value::Int(1)))
+        );
+    }
+
+    #[test]
+    fn test_unexpected_token() {
+        for (src, index) in [("[1, oops]", 4), (r#"{"a": }"#, 6), ("x", 0)] {
+            let err = json_bytes_to_value(src.as_bytes()).unwrap_err();
+            assert_eq!(
+                err.error_type,
+                JiterErrorType::JsonError(JsonErrorType::ExpectedSomeValue)
+            );
+            assert_eq!(err.index, index);
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        let src = b" [1][2] garbage";
+        let (value, consumed) = json_bytes_to_value_prefix(src).unwrap();
+        assert_eq!(value, Value::Array(Array::new().with(Value::Int(1))));
+        assert_eq!(consumed, 4);
+
+        let (value, consumed) = json_bytes_to_value_prefix(&src[consumed..]).unwrap();
+        assert_eq!(value, Value::Array(Array::new().with(Value::Int(2))));
+        assert_eq!(consumed, 3);
+    }
 }
@@ -0,0 +1,165 @@
+//! Deserializing from an [io::Read](std::io::Read), or from a tokio `AsyncRead` with
+//! the `async` feature.
+
+use std::io::Read;
+
+use merde_core::{MerdeError, OwnedValueDeserialize};
+
+use crate::{owned_from_str_via_value, MerdeJsonError};
+
+/// Reads JSON documents out of [Read] implementations, like files or sockets.
+///
+/// Each document is read into an internal buffer before being parsed. That buffer
+/// is kept around between calls, so reading many documents with the same
+/// [JsonReader] doesn't allocate a new one every time. Since the buffer gets
+/// overwritten, the values it returns are owned.
+///
+/// ```
+/// use merde_json::JsonReader;
+///
+/// let mut reader = JsonReader::new();
+/// let a: Vec<u32> = reader.read(&b"[1, 2, 3]"[..]).unwrap();
+/// let b: String = reader.read(&b"\"hello\""[..]).unwrap();
+/// assert_eq!((a, b), (vec![1, 2, 3], "hello".to_owned()));
+/// ```
+#[derive(Debug, Default)]
+pub struct JsonReader {
+    buffer: Vec<u8>,
+}
+
+impl JsonReader {
+    /// Creates a reader with an empty buffer, which grows as needed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a reader whose buffer can hold `capacity` bytes without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Reads `reader` until EOF, and deserializes what it contained as a `T`.
+    ///
+    /// I/O errors are returned as [MerdeError::Io].
+    pub fn read<R, T>(&mut self, mut reader: R) -> Result<T, MerdeJsonError<'static>>
+    where
+        R: Read,
+        T: OwnedValueDeserialize,
+    {
+        self.buffer.clear();
+        reader
+            .read_to_end(&mut self.buffer)
+            .map_err(MerdeError::Io)?;
+        self.parse()
+    }
+
+    /// Reads `reader` until EOF, and deserializes what it contained as a `T`,
+    /// see [JsonReader::read].
+    #[cfg(feature = "async")]
+    pub async fn read_async<R, T>(&mut self, mut reader: R) -> Result<T, MerdeJsonError<'static>>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        T: OwnedValueDeserialize,
+    {
+        use tokio::io::AsyncReadExt;
+
+        self.buffer.clear();
+        reader
+            .read_to_end(&mut self.buffer)
+            .await
+            .map_err(MerdeError::Io)?;
+        self.parse()
+    }
+
+    fn parse<T: OwnedValueDeserialize>(&self) -> Result<T, MerdeJsonError<'static>> {
+        let s = std::str::from_utf8(&self.buffer)?;
+        owned_from_str_via_value(s).map_err(|e| e.to_static())
+    }
+
+    /// How many bytes the internal buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use merde_core::MerdeError;
+
+    use super::JsonReader;
+    use crate::{from_reader, MerdeJsonError};
+
+    #[test]
+    fn test_reuses_buffer() {
+        let mut reader = JsonReader::new();
+        let big = format!("[{}1]", "1,".repeat(1000));
+        let v: Vec<u8> = reader.read(big.as_bytes()).unwrap();
+        assert_eq!(v.len(), 1001);
+
+        let capacity = reader.capacity();
+        assert!(capacity >= big.len());
+        let m: HashMap<String, String> = reader.read(&br#"{"k":"v"}"#[..]).unwrap();
+        assert_eq!(m["k"], "v");
+        assert_eq!(reader.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_errors() {
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("boom"))
+            }
+        }
+        let err = from_reader::<_, u8>(Failing).unwrap_err();
+        assert!(matches!(err, MerdeJsonError::MerdeError(MerdeError::Io(_))));
+
+        let err = from_reader::<_, u8>(&b"\xff"[..]).unwrap_err();
+        assert!(matches!(err, MerdeJsonError::Utf8Error(_)));
+
+        let err = from_reader::<_, u8>(&b"[1"[..]).unwrap_err();
+        assert!(
+            matches!(err, MerdeJsonError::JiterError { source: Some(ref s), .. } if *s == "[1")
+        );
+
+        let err = from_reader::<_, u8>(&b"300"[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            MerdeJsonError::MerdeError(MerdeError::OutOfRange)
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_round_trip() {
+        use tokio::io::AsyncWriteExt;
+
+        use crate::{from_async_reader, to_async_writer};
+
+        let value: HashMap<String, Vec<u32>> =
+            HashMap::from([("a".to_owned(), vec![1, 2]), ("b".to_owned(), vec![])]);
+        let (mut client, server) = tokio::io::duplex(16);
+
+        let write = async {
+            to_async_writer(&mut client, &value).await.unwrap();
+            client.shutdown().await.unwrap();
+            drop(client);
+        };
+        let (read, ()) = tokio::join!(from_async_reader(server), write);
+        let read: HashMap<String, Vec<u32>> = read.unwrap();
+        assert_eq!(read, value);
+
+        let (mut client, server) = tokio::io::duplex(16);
+        client.write_all(b"[1, oops]").await.unwrap();
+        drop(client);
+        let err = JsonReader::new()
+            .read_async::<_, Vec<u32>>(server)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MerdeJsonError::JiterError { .. }), "{err}");
+    }
+}
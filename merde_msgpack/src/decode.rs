@@ -0,0 +1,479 @@
+//! Turns MessagePack bytes into a [Value] tree, see [msgpack_bytes_to_value_prefix].
+
+use merde_core::{Array, CowStr, Map, Value};
+
+use crate::MerdeMsgpackError;
+
+/// How deeply arrays and maps can be nested, so that a few bytes of input
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+/// The ext type of timestamps, see [timestamp_to_rfc3339].
+pub(crate) const TIMESTAMP_EXT: i8 = -1;
+
+/// Decodes the value at the start of `data`, borrowing strings from it.
+/// Returns the value along with how many bytes it took up.
+pub(crate) fn msgpack_bytes_to_value_prefix(
+    data: &[u8],
+) -> Result<(Value<'_>, usize), MerdeMsgpackError> {
+    let mut decoder = Decoder { data, pos: 0 };
+    let value = decoder.value(0)?;
+    Ok((value, decoder.pos))
+}
+
+/// Like [msgpack_bytes_to_value_prefix], but `data` must hold exactly one value.
+pub(crate) fn msgpack_bytes_to_value(data: &[u8]) -> Result<Value<'_>, MerdeMsgpackError> {
+    let (value, len) = msgpack_bytes_to_value_prefix(data)?;
+    if len != data.len() {
+        return Err(decode_error(len, "trailing bytes after the value"));
+    }
+    Ok(value)
+}
+
+fn decode_error(offset: usize, reason: impl Into<String>) -> MerdeMsgpackError {
+    MerdeMsgpackError::DecodeError {
+        reason: reason.into(),
+        offset,
+    }
+}
+
+struct Decoder<'s> {
+    data: &'s [u8],
+    pos: usize,
+}
+
+impl<'s> Decoder<'s> {
+    fn take(&mut self, len: usize) -> Result<&'s [u8], MerdeMsgpackError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| decode_error(self.data.len(), "unexpected end of input"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], MerdeMsgpackError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, MerdeMsgpackError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, MerdeMsgpackError> {
+        self.take_array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, MerdeMsgpackError> {
+        self.take_array().map(u32::from_be_bytes)
+    }
+
+    /// The length of a str, bin, ext, array or map, stored on `size` bytes.
+    fn len(&mut self, size: usize) -> Result<usize, MerdeMsgpackError> {
+        Ok(match size {
+            1 => self.u8()? as usize,
+            2 => self.u16()? as usize,
+            _ => self.u32()? as usize,
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value<'s>, MerdeMsgpackError> {
+        let start = self.pos;
+        let marker = self.u8()?;
+        Ok(match marker {
+            0x00..=0x7f => Value::Int(marker as i64),
+            0x80..=0x8f => self.map((marker & 0x0f) as usize, depth)?,
+            0x90..=0x9f => self.array((marker & 0x0f) as usize, depth)?,
+            0xa0..=0xbf => Value::Str(self.str((marker & 0x1f) as usize, start)?),
+            0xc0 => Value::Null,
+            0xc1 => return Err(decode_error(start, "0xc1 is never used")),
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                bytes_to_value(self.take(len)?)
+            }
+            0xc7..=0xc9 => {
+                let len = self.len(1 << (marker - 0xc7))?;
+                self.ext(len, start)?
+            }
+            0xca => Value::Float(f32::from_be_bytes(self.take_array()?) as f64),
+            0xcb => Value::Float(f64::from_be_bytes(self.take_array()?)),
+            0xcc => Value::Int(self.u8()? as i64),
+            0xcd => Value::Int(self.u16()? as i64),
+            0xce => Value::Int(self.u32()? as i64),
+            0xcf => {
+                let u = u64::from_be_bytes(self.take_array()?);
+                // like merde_json, integers that don't fit in an i64 become floats
+                i64::try_from(u).map_or(Value::Float(u as f64), Value::Int)
+            }
+            0xd0 => Value::Int(i8::from_be_bytes(self.take_array()?) as i64),
+            0xd1 => Value::Int(i16::from_be_bytes(self.take_array()?) as i64),
+            0xd2 => Value::Int(i32::from_be_bytes(self.take_array()?) as i64),
+            0xd3 => Value::Int(i64::from_be_bytes(self.take_array()?)),
+            0xd4..=0xd8 => self.ext(1 << (marker - 0xd4), start)?,
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                Value::Str(self.str(len, start)?)
+            }
+            0xdc | 0xdd => {
+                let len = self.len(2 << (marker - 0xdc))?;
+                self.array(len, depth)?
+            }
+            0xde | 0xdf => {
+                let len = self.len(2 << (marker - 0xde))?;
+                self.map(len, depth)?
+            }
+            0xe0..=0xff => Value::Int(marker as i8 as i64),
+        })
+    }
+
+    fn str(&mut self, len: usize, start: usize) -> Result<CowStr<'s>, MerdeMsgpackError> {
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes)
+            .map(CowStr::Borrowed)
+            .map_err(|e| decode_error(start, format!("invalid UTF-8 in a string: {e}")))
+    }
+
+    /// Every element takes up at least a byte, which keeps a bogus length
+    /// from allocating more than the input could ever fill.
+    fn capacity(&self, len: usize) -> usize {
+        len.min(self.data.len() - self.pos)
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), MerdeMsgpackError> {
+        if depth >= MAX_DEPTH {
+            return Err(decode_error(
+                self.pos - 1,
+                "arrays and maps are nested too deeply",
+            ));
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, len: usize, depth: usize) -> Result<Value<'s>, MerdeMsgpackError> {
+        self.check_depth(depth)?;
+        let mut items = Vec::with_capacity(self.capacity(len));
+        for _ in 0..len {
+            items.push(self.value(depth + 1)?);
+        }
+        Ok(Value::Array(Array::from(items)))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Result<Value<'s>, MerdeMsgpackError> {
+        self.check_depth(depth)?;
+        let mut map = Map::new();
+        map.reserve(self.capacity(len) / 2);
+        for _ in 0..len {
+            let key_start = self.pos;
+            let key = match self.value(depth + 1)? {
+                Value::Str(key) => key,
+                Value::Int(i) => CowStr::from(i.to_string()),
+                other => {
+                    return Err(decode_error(
+                        key_start,
+                        format!(
+                            "map keys must be strings or integers, found {:?}",
+                            other.value_type()
+                        ),
+                    ))
+                }
+            };
+            let value = self.value(depth + 1)?;
+            map.insert(key, value);
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn ext(&mut self, len: usize, start: usize) -> Result<Value<'s>, MerdeMsgpackError> {
+        let ext_type = i8::from_be_bytes(self.take_array()?);
+        let data = self.take(len)?;
+        if ext_type == TIMESTAMP_EXT {
+            return timestamp_to_rfc3339(data)
+                .map(|s| Value::Str(CowStr::from(s)))
+                .ok_or_else(|| decode_error(start, "invalid timestamp"));
+        }
+        Ok(Value::Map(
+            Map::new()
+                .with("type", Value::Int(ext_type as i64))
+                .with("data", bytes_to_value(data)),
+        ))
+    }
+}
+
+/// `bin` payloads (and the data of unknown ext types) become arrays of byte values.
+fn bytes_to_value(bytes: &[u8]) -> Value<'static> {
+    Value::Array(
+        bytes
+            .iter()
+            .map(|&b| Value::Int(b as i64))
+            .collect::<Vec<_>>()
+            .into(),
+    )
+}
+
+/// Formats the payload of a timestamp ext (in any of its three sizes) as an
+/// RFC 3339 date-time in UTC, e.g. `2024-09-17T10:30:00.5Z`. Returns `None` if
+/// the payload is malformed, or if the year isn't between 0 and 9999.
+pub(crate) fn timestamp_to_rfc3339(data: &[u8]) -> Option<String> {
+    let (seconds, nanos) = match data.len() {
+        4 => (u32::from_be_bytes(data.try_into().ok()?) as i64, 0),
+        8 => {
+            let n = u64::from_be_bytes(data.try_into().ok()?);
+            ((n & 0x3_ffff_ffff) as i64, (n >> 34) as u32)
+        }
+        12 => (
+            i64::from_be_bytes(data[4..].try_into().ok()?),
+            u32::from_be_bytes(data[..4].try_into().ok()?),
+        ),
+        _ => return None,
+    };
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+
+    let days = seconds.div_euclid(86_400);
+    let secs_of_day = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+
+    let mut s = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    );
+    if nanos > 0 {
+        let fraction = format!("{nanos:09}");
+        s.push('.');
+        s.push_str(fraction.trim_end_matches('0'));
+    }
+    s.push('Z');
+    Some(s)
+}
+
+/// The (proleptic Gregorian) date `days` days after 1970-01-01, see
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Value};
+
+    use super::{msgpack_bytes_to_value, timestamp_to_rfc3339};
+
+    fn decode(data: &[u8]) -> Value<'_> {
+        msgpack_bytes_to_value(data).unwrap()
+    }
+
+    fn decode_err(data: &[u8]) -> String {
+        msgpack_bytes_to_value(data).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_scalars() {
+        for (data, expected) in [
+            (&[0xc0][..], value!(null)),
+            (&[0xc2], value!(false)),
+            (&[0xc3], value!(true)),
+            (&[0x00], value!(0)),
+            (&[0x7f], value!(127)),
+            (&[0xff], value!(-1)),
+            (&[0xe0], value!(-32)),
+            (&[0xcc, 0xff], value!(255)),
+            (&[0xcd, 0x01, 0x00], value!(256)),
+            (&[0xce, 0xff, 0xff, 0xff, 0xff], value!(4_294_967_295i64)),
+            (
+                &[0xcf, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                value!(i64::MAX),
+            ),
+            (
+                &[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                value!(u64::MAX as f64),
+            ),
+            (&[0xd0, 0x80], value!(-128)),
+            (&[0xd1, 0x80, 0x00], value!(-32768)),
+            (&[0xd2, 0x80, 0x00, 0x00, 0x00], value!(i32::MIN as i64)),
+            (&[0xd3, 0x80, 0, 0, 0, 0, 0, 0, 0], value!(i64::MIN)),
+            (&[0xca, 0x3f, 0xc0, 0x00, 0x00], value!(1.5)),
+            (
+                &[0xcb, 0x40, 0x09, 0x21, 0xfb, 0x54, 0x44, 0x2d, 0x18],
+                value!(std::f64::consts::PI),
+            ),
+        ] {
+            assert_eq!(decode(data), expected, "{data:02x?}");
+        }
+    }
+
+    #[test]
+    fn test_strings_are_borrowed() {
+        let long = "x".repeat(300);
+        let mut str8 = vec![0xd9, 40];
+        str8.extend_from_slice(&long.as_bytes()[..40]);
+        let mut str16 = vec![0xda, 0x01, 0x2c];
+        str16.extend_from_slice(long.as_bytes());
+        let mut str32 = vec![0xdb, 0, 0, 0x01, 0x2c];
+        str32.extend_from_slice(long.as_bytes());
+
+        for (data, expected) in [
+            (&[0xa0][..], ""),
+            (&[0xa3, b'a', b'b', b'c'][..], "abc"),
+            (&str8[..], &long[..40]),
+            (&str16[..], &long[..]),
+            (&str32[..], &long[..]),
+        ] {
+            match decode(data) {
+                Value::Str(CowStr::Borrowed(s)) => assert_eq!(s, expected),
+                other => panic!("expected a borrowed string, got {other:?}"),
+            }
+        }
+
+        assert_eq!(
+            decode_err(&[0x91, 0xa2, 0xc3, 0x28]),
+            "MessagePack decoding error: invalid UTF-8 in a string: \
+             invalid utf-8 sequence of 1 bytes from index 0 at offset 1"
+        );
+    }
+
+    #[test]
+    fn test_arrays_and_maps() {
+        assert_eq!(decode(&[0x90]), value!([]));
+        assert_eq!(decode(&[0x80]), value!({}));
+        assert_eq!(
+            decode(&[0x82, 0xa1, b'a', 0x92, 0x01, 0xc0, 0xa1, b'b', 0x81, 0xa1, b'c', 0xc3]),
+            value!({ "a": [1, null], "b": { "c": true } })
+        );
+        assert_eq!(decode(&[0xdc, 0x00, 0x02, 0x01, 0x02]), value!([1, 2]));
+        assert_eq!(decode(&[0xdd, 0, 0, 0, 0x01, 0x01]), value!([1]));
+        assert_eq!(
+            decode(&[0xde, 0x00, 0x01, 0xa1, b'k', 0x01]),
+            value!({ "k": 1 })
+        );
+        assert_eq!(
+            decode(&[0xdf, 0, 0, 0, 0x01, 0xa1, b'k', 0x02]),
+            value!({ "k": 2 })
+        );
+
+        // integer keys are stringified, like JSON object keys would be
+        assert_eq!(
+            decode(&[0x82, 0x01, 0xa1, b'a', 0xff, 0xa1, b'b']),
+            value!({ "1": "a", "-1": "b" })
+        );
+        assert_eq!(
+            decode_err(&[0x81, 0x90, 0x01]),
+            "MessagePack decoding error: map keys must be strings or integers, found Array at offset 1"
+        );
+    }
+
+    #[test]
+    fn test_bin_and_ext() {
+        assert_eq!(decode(&[0xc4, 0x00]), value!([]));
+        assert_eq!(decode(&[0xc4, 0x02, 0x00, 0xff]), value!([0, 255]));
+        assert_eq!(decode(&[0xc5, 0x00, 0x01, 0x2a]), value!([42]));
+        assert_eq!(decode(&[0xc6, 0, 0, 0, 0x01, 0x2a]), value!([42]));
+
+        assert_eq!(
+            decode(&[0xd4, 0x05, 0x2a]),
+            value!({ "type": 5, "data": [42] })
+        );
+        assert_eq!(
+            decode(&[0xd5, 0x80, 0x01, 0x02]),
+            value!({ "type": -128, "data": [1, 2] })
+        );
+        assert_eq!(
+            decode(&[0xd8, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            value!({ "type": 1, "data": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] })
+        );
+        assert_eq!(
+            decode(&[0xc7, 0x00, 0x10]),
+            value!({ "type": 16, "data": [] })
+        );
+        assert_eq!(
+            decode(&[0xc8, 0x00, 0x01, 0x10, 0x07]),
+            value!({ "type": 16, "data": [7] })
+        );
+        assert_eq!(
+            decode(&[0xc9, 0, 0, 0, 0x01, 0x10, 0x07]),
+            value!({ "type": 16, "data": [7] })
+        );
+    }
+
+    #[test]
+    fn test_timestamps() {
+        // timestamp 32
+        assert_eq!(
+            decode(&[0xd6, 0xff, 0, 0, 0, 0]),
+            value!("1970-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            decode(&[0xd6, 0xff, 0x66, 0xe9, 0x5a, 0x28]),
+            value!("2024-09-17T10:30:00Z")
+        );
+        // timestamp 64: 500ms, then 34 bits of seconds
+        let n: u64 = (500_000_000 << 34) | 1_726_569_000;
+        let mut data = vec![0xd7, 0xff];
+        data.extend_from_slice(&n.to_be_bytes());
+        assert_eq!(decode(&data), value!("2024-09-17T10:30:00.5Z"));
+        // timestamp 96, before the epoch
+        let mut data = vec![0xc7, 12, 0xff];
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&(-1i64).to_be_bytes());
+        assert_eq!(decode(&data), value!("1969-12-31T23:59:59.000000001Z"));
+
+        assert_eq!(
+            timestamp_to_rfc3339(&951_782_400u32.to_be_bytes()).unwrap(),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(timestamp_to_rfc3339(&[0, 0]), None);
+        assert_eq!(timestamp_to_rfc3339(&[0xff; 8]), None);
+        let mut year_10000 = vec![0, 0, 0, 0];
+        year_10000.extend_from_slice(&253_402_300_800i64.to_be_bytes());
+        assert_eq!(timestamp_to_rfc3339(&year_10000), None);
+        assert_eq!(
+            decode_err(&[0x91, 0xd4, 0xff, 0x00]),
+            "MessagePack decoding error: invalid timestamp at offset 1"
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        for (data, expected) in [
+            (&[][..], "unexpected end of input at offset 0"),
+            (&[0xc1], "0xc1 is never used at offset 0"),
+            (&[0xcd, 0x01], "unexpected end of input at offset 2"),
+            (&[0xa2, b'a'], "unexpected end of input at offset 2"),
+            (
+                &[0xdd, 0xff, 0xff, 0xff, 0xff],
+                "unexpected end of input at offset 5",
+            ),
+            (&[0xc0, 0xc0], "trailing bytes after the value at offset 1"),
+        ] {
+            assert_eq!(
+                decode_err(data),
+                format!("MessagePack decoding error: {expected}"),
+                "{data:02x?}"
+            );
+        }
+
+        let nested = vec![0x91; 1000];
+        assert_eq!(
+            decode_err(&nested),
+            "MessagePack decoding error: arrays and maps are nested too deeply at offset 256"
+        );
+    }
+}
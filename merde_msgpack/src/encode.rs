@@ -0,0 +1,497 @@
+//! Writes MessagePack, see [MsgpackSerializer] and [MsgpackSerialize].
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use merde_core::{Array, CowStr, Map, Value};
+
+/// Writes MessagePack to a `Vec<u8>`. None of its methods can fail, since it
+/// doesn't target an `io::Write`. You can provide your own buffer via
+/// [MsgpackSerializer::from_vec].
+///
+/// Numbers are written in the smallest encoding that holds them, except for
+/// floats, which are always written as 64-bit floats by [MsgpackSerializer::write_f64].
+///
+/// Arrays and maps are prefixed with their length, so unlike with JSON, it has
+/// to be known up front: call [MsgpackSerializer::write_array_len], then write
+/// that many elements, or [MsgpackSerializer::write_map_len], then that many
+/// key/value pairs.
+///
+/// Strings, binary payloads, arrays and maps are limited to `u32::MAX` bytes or
+/// elements, which is as far as MessagePack goes: the write methods panic past that.
+#[derive(Debug, Default)]
+pub struct MsgpackSerializer {
+    buffer: Vec<u8>,
+}
+
+impl MsgpackSerializer {
+    /// Uses the provided buffer as the target for serialization.
+    pub fn from_vec(vec: Vec<u8>) -> Self {
+        MsgpackSerializer { buffer: vec }
+    }
+
+    /// Allocates a new buffer for serialization.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes `nil`.
+    pub fn write_nil(&mut self) {
+        self.buffer.push(0xc0);
+    }
+
+    /// Writes a boolean.
+    pub fn write_bool(&mut self, value: bool) {
+        self.buffer.push(if value { 0xc3 } else { 0xc2 });
+    }
+
+    /// Writes a signed integer.
+    pub fn write_i64(&mut self, value: i64) {
+        if value >= 0 {
+            return self.write_u64(value as u64);
+        }
+        if value >= -32 {
+            self.buffer.push(value as u8);
+        } else if let Ok(v) = i8::try_from(value) {
+            self.buffer.push(0xd0);
+            self.buffer.extend_from_slice(&v.to_be_bytes());
+        } else if let Ok(v) = i16::try_from(value) {
+            self.buffer.push(0xd1);
+            self.buffer.extend_from_slice(&v.to_be_bytes());
+        } else if let Ok(v) = i32::try_from(value) {
+            self.buffer.push(0xd2);
+            self.buffer.extend_from_slice(&v.to_be_bytes());
+        } else {
+            self.buffer.push(0xd3);
+            self.buffer.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    /// Writes an unsigned integer.
+    pub fn write_u64(&mut self, value: u64) {
+        if value < 0x80 {
+            self.buffer.push(value as u8);
+        } else if let Ok(v) = u8::try_from(value) {
+            self.buffer.push(0xcc);
+            self.buffer.push(v);
+        } else if let Ok(v) = u16::try_from(value) {
+            self.buffer.push(0xcd);
+            self.buffer.extend_from_slice(&v.to_be_bytes());
+        } else if let Ok(v) = u32::try_from(value) {
+            self.buffer.push(0xce);
+            self.buffer.extend_from_slice(&v.to_be_bytes());
+        } else {
+            self.buffer.push(0xcf);
+            self.buffer.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    /// Writes a 32-bit float.
+    pub fn write_f32(&mut self, value: f32) {
+        self.buffer.push(0xca);
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a 64-bit float.
+    pub fn write_f64(&mut self, value: f64) {
+        self.buffer.push(0xcb);
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a string.
+    pub fn write_str(&mut self, value: &str) {
+        let len = value.len();
+        if len < 32 {
+            self.buffer.push(0xa0 | len as u8);
+        } else {
+            self.write_len(len, [0xd9, 0xda, 0xdb]);
+        }
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    /// Writes a binary payload. Note that merde_msgpack decodes those as arrays
+    /// of integers, since [Value] has no binary type.
+    pub fn write_bin(&mut self, value: &[u8]) {
+        self.write_len(value.len(), [0xc4, 0xc5, 0xc6]);
+        self.buffer.extend_from_slice(value);
+    }
+
+    /// Writes an extension value of type `ext_type`, see [crate::from_slice_via_value]
+    /// for how they're decoded.
+    pub fn write_ext(&mut self, ext_type: i8, data: &[u8]) {
+        match data.len() {
+            1 => self.buffer.push(0xd4),
+            2 => self.buffer.push(0xd5),
+            4 => self.buffer.push(0xd6),
+            8 => self.buffer.push(0xd7),
+            16 => self.buffer.push(0xd8),
+            len => self.write_len(len, [0xc7, 0xc8, 0xc9]),
+        }
+        self.buffer.extend_from_slice(&ext_type.to_be_bytes());
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Starts an array of `len` elements, which have to be written next.
+    pub fn write_array_len(&mut self, len: usize) {
+        if len < 16 {
+            self.buffer.push(0x90 | len as u8);
+        } else {
+            self.write_len16(len, [0xdc, 0xdd]);
+        }
+    }
+
+    /// Starts a map of `len` entries, which have to be written next, each key
+    /// right before its value.
+    pub fn write_map_len(&mut self, len: usize) {
+        if len < 16 {
+            self.buffer.push(0x80 | len as u8);
+        } else {
+            self.write_len16(len, [0xde, 0xdf]);
+        }
+    }
+
+    /// Writes one of `markers` (for 8, 16 and 32-bit lengths), then `len`.
+    fn write_len(&mut self, len: usize, markers: [u8; 3]) {
+        if let Ok(len) = u8::try_from(len) {
+            self.buffer.push(markers[0]);
+            self.buffer.push(len);
+        } else {
+            self.write_len16(len, [markers[1], markers[2]]);
+        }
+    }
+
+    /// Writes one of `markers` (for 16 and 32-bit lengths), then `len`.
+    fn write_len16(&mut self, len: usize, markers: [u8; 2]) {
+        if let Ok(len) = u16::try_from(len) {
+            self.buffer.push(markers[0]);
+            self.buffer.extend_from_slice(&len.to_be_bytes());
+        } else {
+            let len = u32::try_from(len).expect("MessagePack lengths are at most u32::MAX");
+            self.buffer.push(markers[1]);
+            self.buffer.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+
+    /// Returns the underlying buffer.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Mutably borrow the underlying buffer
+    pub fn as_mut_vec(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+}
+
+/// Implemented by anything that can be serialized to MessagePack, like
+/// `JsonSerialize` is for JSON.
+///
+/// Implementations are provided for primitive types, strings, [Value] and its
+/// parts, vectors, slices, HashMap and Option (`None` is written as `nil`).
+/// Types that only implement `ValueSerialize` (derived impls, for example) can
+/// be serialized via `merde_core::to_value`.
+pub trait MsgpackSerialize {
+    /// Write self to a `MsgpackSerializer`.
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer);
+
+    /// Allocate a new `Vec<u8>` and serialize self to it.
+    fn to_msgpack_bytes(&self) -> Vec<u8> {
+        let mut s = MsgpackSerializer::new();
+        self.msgpack_serialize(&mut s);
+        s.into_inner()
+    }
+}
+
+impl MsgpackSerialize for Value<'_> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        match self {
+            Value::Null => serializer.write_nil(),
+            Value::Bool(b) => serializer.write_bool(*b),
+            Value::Int(i) => serializer.write_i64(*i),
+            Value::Float(f) => serializer.write_f64(*f),
+            Value::Str(s) => serializer.write_str(s),
+            Value::Array(arr) => arr.msgpack_serialize(serializer),
+            Value::Map(map) => map.msgpack_serialize(serializer),
+        }
+    }
+}
+
+impl MsgpackSerialize for Map<'_> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_map_len(self.len());
+        for (key, value) in self.iter() {
+            serializer.write_str(key);
+            value.msgpack_serialize(serializer);
+        }
+    }
+}
+
+impl MsgpackSerialize for Array<'_> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        self[..].msgpack_serialize(serializer)
+    }
+}
+
+impl<T> MsgpackSerialize for &T
+where
+    T: ?Sized + MsgpackSerialize,
+{
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        let this: &T = self;
+        MsgpackSerialize::msgpack_serialize(this, serializer)
+    }
+}
+
+impl MsgpackSerialize for str {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_str(self)
+    }
+}
+
+impl MsgpackSerialize for String {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_str(self)
+    }
+}
+
+impl MsgpackSerialize for Cow<'_, str> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_str(self)
+    }
+}
+
+impl MsgpackSerialize for CowStr<'_> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_str(self)
+    }
+}
+
+macro_rules! impl_msgpack_serialize_int {
+    ($method:ident as $target:ty: $($ty:ty),*) => {
+        $(
+            impl MsgpackSerialize for $ty {
+                fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+                    serializer.$method(*self as $target);
+                }
+            }
+        )*
+    };
+}
+
+impl_msgpack_serialize_int!(write_u64 as u64: u8, u16, u32, u64, usize);
+impl_msgpack_serialize_int!(write_i64 as i64: i8, i16, i32, i64, isize);
+
+impl MsgpackSerialize for f32 {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_f32(*self)
+    }
+}
+
+impl MsgpackSerialize for f64 {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_f64(*self)
+    }
+}
+
+impl MsgpackSerialize for bool {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_bool(*self)
+    }
+}
+
+impl<K: AsRef<str>, V: MsgpackSerialize> MsgpackSerialize for HashMap<K, V> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_map_len(self.len());
+        for (key, value) in self {
+            serializer.write_str(key.as_ref());
+            value.msgpack_serialize(serializer);
+        }
+    }
+}
+
+impl<T: MsgpackSerialize> MsgpackSerialize for [T] {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        serializer.write_array_len(self.len());
+        for value in self {
+            value.msgpack_serialize(serializer);
+        }
+    }
+}
+
+impl<T: MsgpackSerialize> MsgpackSerialize for Vec<T> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        self[..].msgpack_serialize(serializer)
+    }
+}
+
+impl<T: MsgpackSerialize> MsgpackSerialize for Option<T> {
+    fn msgpack_serialize(&self, serializer: &mut MsgpackSerializer) {
+        match self {
+            Some(value) => value.msgpack_serialize(serializer),
+            None => serializer.write_nil(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use merde_core::{value, CowStr, Value};
+
+    use super::{MsgpackSerialize, MsgpackSerializer};
+    use crate::decode::msgpack_bytes_to_value;
+
+    #[test]
+    fn test_ints() {
+        for (value, expected) in [
+            (0i64, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0xcc, 0x80]),
+            (256, &[0xcd, 0x01, 0x00]),
+            (65536, &[0xce, 0x00, 0x01, 0x00, 0x00]),
+            (1 << 32, &[0xcf, 0, 0, 0, 0x01, 0, 0, 0, 0]),
+            (-1, &[0xff]),
+            (-32, &[0xe0]),
+            (-33, &[0xd0, 0xdf]),
+            (-129, &[0xd1, 0xff, 0x7f]),
+            (-32769, &[0xd2, 0xff, 0xff, 0x7f, 0xff]),
+            (i64::MIN, &[0xd3, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+        ] {
+            let data = value.to_msgpack_bytes();
+            assert_eq!(data, expected, "{value}");
+            assert_eq!(msgpack_bytes_to_value(&data).unwrap(), Value::Int(value));
+        }
+        assert_eq!(
+            u64::MAX.to_msgpack_bytes(),
+            [0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(200u8.to_msgpack_bytes(), [0xcc, 0xc8]);
+        assert_eq!((-2i8).to_msgpack_bytes(), [0xfe]);
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(Value::Null.to_msgpack_bytes(), [0xc0]);
+        assert_eq!(true.to_msgpack_bytes(), [0xc3]);
+        assert_eq!(false.to_msgpack_bytes(), [0xc2]);
+        assert_eq!(1.5f32.to_msgpack_bytes(), [0xca, 0x3f, 0xc0, 0x00, 0x00]);
+        assert_eq!(
+            1.5f64.to_msgpack_bytes(),
+            [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(None::<bool>.to_msgpack_bytes(), [0xc0]);
+        assert_eq!(Some(true).to_msgpack_bytes(), [0xc3]);
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!("".to_msgpack_bytes(), [0xa0]);
+        assert_eq!("abc".to_msgpack_bytes(), [0xa3, b'a', b'b', b'c']);
+        assert_eq!(
+            String::from("abc").to_msgpack_bytes(),
+            [0xa3, b'a', b'b', b'c']
+        );
+        assert_eq!(Cow::Borrowed("é").to_msgpack_bytes(), [0xa2, 0xc3, 0xa9]);
+        assert_eq!(
+            CowStr::from("abc").to_msgpack_bytes(),
+            [0xa3, b'a', b'b', b'c']
+        );
+
+        for (len, header) in [
+            (31, &[0xbf][..]),
+            (32, &[0xd9, 32]),
+            (255, &[0xd9, 0xff]),
+            (256, &[0xda, 0x01, 0x00]),
+            (65536, &[0xdb, 0x00, 0x01, 0x00, 0x00]),
+        ] {
+            let s = "x".repeat(len);
+            let data = s.to_msgpack_bytes();
+            assert_eq!(&data[..header.len()], header, "{len}");
+            assert_eq!(data.len(), header.len() + len);
+            assert_eq!(msgpack_bytes_to_value(&data).unwrap(), Value::Str(s.into()));
+        }
+    }
+
+    #[test]
+    fn test_arrays_and_maps() {
+        assert_eq!(Vec::<u8>::new().to_msgpack_bytes(), [0x90]);
+        assert_eq!(vec![1u8, 2].to_msgpack_bytes(), [0x92, 0x01, 0x02]);
+        assert_eq!([true][..].to_msgpack_bytes(), [0x91, 0xc3]);
+        assert_eq!(
+            HashMap::from([("k", 1u8)]).to_msgpack_bytes(),
+            [0x81, 0xa1, b'k', 0x01]
+        );
+
+        let long = vec![0u8; 16];
+        let data = long.to_msgpack_bytes();
+        assert_eq!(&data[..3], [0xdc, 0x00, 0x10]);
+        let data = vec![0u8; 65536].to_msgpack_bytes();
+        assert_eq!(&data[..5], [0xdd, 0x00, 0x01, 0x00, 0x00]);
+        assert_eq!(data.len(), 5 + 65536);
+
+        let mut s = MsgpackSerializer::new();
+        s.write_map_len(16);
+        s.write_map_len(65536);
+        assert_eq!(
+            s.into_inner(),
+            [0xde, 0x00, 0x10, 0xdf, 0x00, 0x01, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_bin_and_ext() {
+        let mut s = MsgpackSerializer::from_vec(vec![0x93]);
+        s.write_bin(&[0x00, 0xff]);
+        s.write_ext(5, &[0x2a]);
+        s.write_ext(-1, &1_726_569_000u32.to_be_bytes());
+        let data = s.into_inner();
+        assert_eq!(
+            data,
+            [0x93, 0xc4, 0x02, 0x00, 0xff, 0xd4, 0x05, 0x2a, 0xd6, 0xff, 0x66, 0xe9, 0x5a, 0x28]
+        );
+        assert_eq!(
+            msgpack_bytes_to_value(&data).unwrap(),
+            value!([[0, 255], { "type": 5, "data": [42] }, "2024-09-17T10:30:00Z"])
+        );
+
+        for (len, header) in [
+            (0, &[0xc7, 0x00, 0x07][..]),
+            (3, &[0xc7, 0x03, 0x07]),
+            (16, &[0xd8, 0x07]),
+            (17, &[0xc7, 0x11, 0x07]),
+            (256, &[0xc8, 0x01, 0x00, 0x07]),
+        ] {
+            let mut s = MsgpackSerializer::new();
+            s.write_ext(7, &vec![0; len]);
+            let data = s.into_inner();
+            assert_eq!(&data[..header.len()], header, "{len}");
+            assert_eq!(data.len(), header.len() + len);
+        }
+
+        let mut s = MsgpackSerializer::new();
+        s.write_bin(&[0; 256]);
+        assert_eq!(&s.into_inner()[..3], [0xc5, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_round_trip_values() {
+        for value in [
+            value!(null),
+            value!(true),
+            value!(i64::MIN),
+            value!(i64::MAX),
+            value!(-0.0),
+            value!(1e300),
+            value!("ünïcödé"),
+            value!([]),
+            value!({}),
+            value!([1, [2, [3, []]], { "a": { "b": null } }]),
+            value!({ "name": "merde", "tags": ["a", "b"], "ratio": 0.25, "ok": false }),
+        ] {
+            let data = value.to_msgpack_bytes();
+            assert_eq!(msgpack_bytes_to_value(&data).unwrap(), value, "{data:02x?}");
+        }
+    }
+}
@@ -0,0 +1,214 @@
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+mod decode;
+mod encode;
+
+pub use encode::{MsgpackSerialize, MsgpackSerializer};
+
+use decode::{msgpack_bytes_to_value, msgpack_bytes_to_value_prefix};
+use merde_core::{MerdeError, OwnedValueDeserialize, ValueDeserialize};
+
+use std::io::Write;
+
+/// Unifies [MerdeError] and MessagePack decoding errors into a single type
+pub enum MerdeMsgpackError {
+    /// A [MerdeError]
+    MerdeError(MerdeError),
+
+    /// The input isn't valid MessagePack, or holds something that doesn't fit
+    /// in a [merde_core::Value], like a map with array keys
+    DecodeError {
+        /// What's wrong
+        reason: String,
+        /// Where it's wrong, in bytes from the start of the input
+        offset: usize,
+    },
+}
+
+impl From<MerdeError> for MerdeMsgpackError {
+    fn from(e: MerdeError) -> Self {
+        MerdeMsgpackError::MerdeError(e)
+    }
+}
+
+impl std::fmt::Display for MerdeMsgpackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MerdeMsgpackError::MerdeError(me) => write!(f, "Merde Error: {}", me),
+            MerdeMsgpackError::DecodeError { reason, offset } => {
+                write!(
+                    f,
+                    "MessagePack decoding error: {} at offset {}",
+                    reason, offset
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerdeMsgpackError {}
+
+impl std::fmt::Debug for MerdeMsgpackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+/// Deserialize an instance of type `T` from a MessagePack value, which must
+/// take up all of `data`.
+///
+/// Strings are borrowed from `data`. Timestamps (ext type -1) come out as
+/// RFC 3339 strings, which `merde_time::Rfc3339` deserializes from.
+pub fn from_slice_via_value<'s, T>(data: &'s [u8]) -> Result<T, MerdeMsgpackError>
+where
+    T: ValueDeserialize<'s>,
+{
+    Ok(merde_core::from_value(msgpack_bytes_to_value(data)?)?)
+}
+
+/// Deserialize an instance of type `T` from a MessagePack value, making sure
+/// the result is owned.
+pub fn owned_from_slice_via_value<T>(data: &[u8]) -> Result<T, MerdeMsgpackError>
+where
+    T: OwnedValueDeserialize,
+{
+    Ok(T::owned_from_value(Some(msgpack_bytes_to_value(data)?))?)
+}
+
+/// Deserialize an instance of type `T` from the MessagePack value at the start
+/// of `data`, which may be followed by more data, e.g. another value in a stream.
+///
+/// Returns the deserialized value along with the number of bytes it took up.
+pub fn from_slice_via_value_prefix<'s, T>(data: &'s [u8]) -> Result<(T, usize), MerdeMsgpackError>
+where
+    T: ValueDeserialize<'s>,
+{
+    let (value, consumed) = msgpack_bytes_to_value_prefix(data)?;
+    Ok((merde_core::from_value(value)?, consumed))
+}
+
+/// Serialize the given data structure as a MessagePack byte vector.
+///
+/// For types that implement `ValueSerialize` but not [MsgpackSerialize], go
+/// through [merde_core::to_value] first.
+pub fn to_vec<T: MsgpackSerialize + ?Sized>(value: &T) -> Vec<u8> {
+    value.to_msgpack_bytes()
+}
+
+/// Serialize the given data structure as MessagePack into the I/O stream.
+pub fn to_writer<T>(mut writer: impl Write, value: &T) -> std::io::Result<()>
+where
+    T: MsgpackSerialize + ?Sized,
+{
+    writer.write_all(&to_vec(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Map, MerdeError, Value, ValueDeserialize};
+    use merde_time::Rfc3339;
+    use std::collections::HashMap;
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Event<'s> {
+        name: CowStr<'s>,
+        at: Rfc3339<time::OffsetDateTime>,
+        attendees: Option<Vec<u32>>,
+    }
+
+    impl<'s> ValueDeserialize<'s> for Event<'s> {
+        fn from_value_ref(value: Option<&Value<'s>>) -> Result<Self, MerdeError> {
+            Self::from_value(value.cloned())
+        }
+
+        fn from_value(value: Option<Value<'s>>) -> Result<Self, MerdeError> {
+            let mut map = Map::from_value(value)?;
+            Ok(Event {
+                name: map.must_remove("name")?,
+                at: map.must_remove("at")?,
+                attendees: map.must_remove("attendees")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_from_slice_via_value() {
+        // {"name": "launch", "at": <timestamp 32, 2024-09-17T10:30:00Z>, "attendees": nil}
+        let mut data = vec![0x83, 0xa4, b'n', b'a', b'm', b'e', 0xa6];
+        data.extend_from_slice(b"launch");
+        data.extend_from_slice(&[0xa2, b'a', b't', 0xd6, 0xff]);
+        data.extend_from_slice(&1_726_569_000u32.to_be_bytes());
+        data.push(0xa9);
+        data.extend_from_slice(b"attendees");
+        data.push(0xc0);
+
+        let event: Event = from_slice_via_value(&data).unwrap();
+        assert_eq!(
+            event,
+            Event {
+                name: "launch".into(),
+                at: Rfc3339(datetime!(2024-09-17 10:30:00 UTC)),
+                attendees: None,
+            }
+        );
+        assert!(matches!(event.name, CowStr::Borrowed(_)));
+
+        let owned: HashMap<String, Vec<String>> =
+            owned_from_slice_via_value(&[0x81, 0xa1, b'v', 0x92, 0xa1, b'a', 0xa1, b'b']).unwrap();
+        assert_eq!(owned["v"], ["a", "b"]);
+
+        let err =
+            from_slice_via_value::<Event>(&[0x81, 0xa4, b'n', b'a', b'm', b'e', 0x01]).unwrap_err();
+        assert!(matches!(err, MerdeMsgpackError::MerdeError(_)), "{err}");
+
+        let err = from_slice_via_value::<Value>(&[0x92, 0x01]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MessagePack decoding error: unexpected end of input at offset 2"
+        );
+    }
+
+    #[test]
+    fn test_from_slice_via_value_prefix() {
+        let data = [0x01, 0xa2, b'h', b'i', 0xc3];
+        let (first, consumed): (i64, usize) = from_slice_via_value_prefix(&data).unwrap();
+        assert_eq!((first, consumed), (1, 1));
+        let (second, consumed): (CowStr, usize) = from_slice_via_value_prefix(&data[1..]).unwrap();
+        assert_eq!((second, consumed), (CowStr::from("hi"), 3));
+
+        let err = from_slice_via_value::<i64>(&data).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MessagePack decoding error: trailing bytes after the value at offset 1"
+        );
+    }
+
+    #[test]
+    fn test_to_vec() {
+        let value = value!({ "tags": ["a", null, 1.5] });
+        let data = to_vec(&value);
+        assert_eq!(
+            data,
+            [
+                0x81, 0xa4, b't', b'a', b'g', b's', 0x93, 0xa1, b'a', 0xc0, 0xcb, 0x3f, 0xf8, 0, 0,
+                0, 0, 0, 0
+            ]
+        );
+        assert_eq!(from_slice_via_value::<Value>(&data).unwrap(), value);
+
+        let mut out = Vec::new();
+        to_writer(&mut out, &value).unwrap();
+        assert_eq!(out, data);
+
+        let attendees: Option<Vec<u32>> = Some(vec![1, 300]);
+        assert_eq!(to_vec(&attendees), [0x92, 0x01, 0xcd, 0x01, 0x2c]);
+        assert_eq!(
+            to_vec(&merde_core::to_value(&attendees)),
+            [0x92, 0x01, 0xcd, 0x01, 0x2c]
+        );
+    }
+}
@@ -88,6 +88,17 @@ mod merde_impls {
             }
         }
     }
+
+    #[cfg(feature = "serialize")]
+    impl merde_core::ValueSerialize for Rfc3339<time::OffsetDateTime> {
+        fn to_value(&self) -> merde_core::Value<'_> {
+            let s = self
+                .0
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap();
+            merde_core::Value::Str(s.into())
+        }
+    }
 }
 
 #[cfg(feature = "json")]
@@ -106,6 +117,16 @@ mod merde_json_impls {
             buf.push(b'"');
         }
     }
+
+    #[cfg(feature = "deserialize")]
+    impl<'s> merde_json::JsonDeserialize<'s> for Rfc3339<time::OffsetDateTime> {
+        fn json_deserialize(
+            de: &mut merde_json::JsonDeserializer<'s>,
+        ) -> Result<Self, merde_json::MerdeJsonError<'s>> {
+            let value = de.deserialize_value()?;
+            Ok(merde_core::ValueDeserialize::from_value(Some(value))?)
+        }
+    }
 }
 
 #[cfg(all(test, feature = "full",))]
@@ -134,5 +155,16 @@ mod tests {
         let json = r#""2023-05-15T14:30:00Z""#;
         let deserialized: Rfc3339<time::OffsetDateTime> = from_str_via_value(json).unwrap();
         assert_eq!(deserialized, Rfc3339(datetime!(2023-05-15 14:30:00 UTC)));
+        let deserialized: Rfc3339<time::OffsetDateTime> = merde_json::from_str(json).unwrap();
+        assert_eq!(deserialized, Rfc3339(datetime!(2023-05-15 14:30:00 UTC)));
+    }
+
+    #[test]
+    fn test_rfc3339_offset_date_time_to_value() {
+        let dt = Rfc3339(datetime!(2023-05-15 14:30:00 UTC));
+        let value = merde_core::to_value(&dt);
+        assert_eq!(value, merde_core::Value::Str("2023-05-15T14:30:00Z".into()));
+        let back: Rfc3339<time::OffsetDateTime> = merde_core::from_value(value).unwrap();
+        assert_eq!(back, dt);
     }
 }
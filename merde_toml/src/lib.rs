@@ -0,0 +1,195 @@
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+mod parser;
+mod serialize;
+
+use merde_core::{MerdeError, OwnedValueDeserialize, ValueDeserialize, ValueSerialize};
+use parser::toml_str_to_value;
+
+use std::io::Write;
+
+/// Represents a line and column in the TOML source, see [MerdeTomlError::ParseError].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePosition {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number, starting at 1, counted in characters.
+    pub column: usize,
+}
+
+impl std::fmt::Display for LinePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} column {}", self.line, self.column)
+    }
+}
+
+/// Unifies [MerdeError] and TOML parsing errors into a single type
+pub enum MerdeTomlError {
+    /// A [MerdeError]
+    MerdeError(MerdeError),
+
+    /// The source isn't valid TOML
+    ParseError {
+        /// What's wrong
+        reason: String,
+        /// Where it's wrong
+        position: LinePosition,
+    },
+
+    /// The value can't be written as TOML, e.g. because it contains a `null` in
+    /// an array, or because it isn't a map
+    Unrepresentable {
+        /// The dotted key of the offending value, empty for the top-level value
+        path: String,
+        /// What's wrong
+        reason: &'static str,
+    },
+}
+
+impl From<MerdeError> for MerdeTomlError {
+    fn from(e: MerdeError) -> Self {
+        MerdeTomlError::MerdeError(e)
+    }
+}
+
+impl std::fmt::Display for MerdeTomlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MerdeTomlError::MerdeError(me) => write!(f, "Merde Error: {}", me),
+            MerdeTomlError::ParseError { reason, position } => {
+                write!(f, "TOML parsing error: {} at {}", reason, position)
+            }
+            MerdeTomlError::Unrepresentable { path, reason } if path.is_empty() => {
+                write!(f, "Cannot serialize as TOML: {}", reason)
+            }
+            MerdeTomlError::Unrepresentable { path, reason } => {
+                write!(f, "Cannot serialize `{}` as TOML: {}", path, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerdeTomlError {}
+
+impl std::fmt::Debug for MerdeTomlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+/// Deserialize an instance of type `T` from a string of TOML text.
+///
+/// Keys and strings without escapes are borrowed from `s`. Offset date-times
+/// come out as RFC 3339 strings, which `merde_time::Rfc3339` deserializes from.
+pub fn from_str_via_value<'s, T>(s: &'s str) -> Result<T, MerdeTomlError>
+where
+    T: ValueDeserialize<'s>,
+{
+    Ok(merde_core::from_value(toml_str_to_value(s)?)?)
+}
+
+/// Deserialize an instance of type `T` from a string of TOML text, making
+/// sure the result is owned.
+pub fn owned_from_str_via_value<T>(s: &str) -> Result<T, MerdeTomlError>
+where
+    T: OwnedValueDeserialize,
+{
+    Ok(T::owned_from_value(Some(toml_str_to_value(s)?))?)
+}
+
+/// Serialize the given data structure as a TOML document. It has to serialize
+/// to a map, and `null`s are only allowed as map values, which are left out.
+///
+/// Strings are always written as strings, including RFC 3339 date-times.
+pub fn to_string<T: ValueSerialize + ?Sized>(value: &T) -> Result<String, MerdeTomlError> {
+    let mut out = String::new();
+    serialize::write_document(&mut out, &value.to_value())?;
+    Ok(out)
+}
+
+/// Serialize the given data structure as a TOML document into the I/O stream,
+/// see [to_string]. [MerdeTomlError::Unrepresentable] is reported as
+/// [std::io::ErrorKind::InvalidData].
+pub fn to_writer<T>(mut writer: impl Write, value: &T) -> std::io::Result<()>
+where
+    T: ValueSerialize + ?Sized,
+{
+    let toml =
+        to_string(value).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    writer.write_all(toml.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Map, MerdeError, Value, ValueDeserialize};
+    use merde_time::Rfc3339;
+    use std::collections::HashMap;
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Release<'s> {
+        name: CowStr<'s>,
+        date: Rfc3339<time::OffsetDateTime>,
+        yanked: Option<bool>,
+    }
+
+    impl<'s> ValueDeserialize<'s> for Release<'s> {
+        fn from_value_ref(value: Option<&Value<'s>>) -> Result<Self, MerdeError> {
+            Self::from_value(value.cloned())
+        }
+
+        fn from_value(value: Option<Value<'s>>) -> Result<Self, MerdeError> {
+            let mut map = Map::from_value(value)?;
+            Ok(Release {
+                name: map.must_remove("name")?,
+                date: map.must_remove("date")?,
+                yanked: map.must_remove("yanked")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_from_str_via_value() {
+        let src = "name = 'merde'\ndate = 2024-09-17 12:30:00+02:00\n";
+        let release: Release = from_str_via_value(src).unwrap();
+        assert_eq!(
+            release,
+            Release {
+                name: "merde".into(),
+                date: Rfc3339(datetime!(2024-09-17 12:30:00 +02:00)),
+                yanked: None,
+            }
+        );
+        assert!(matches!(release.name, CowStr::Borrowed(_)));
+
+        let owned: HashMap<String, Vec<String>> =
+            owned_from_str_via_value("v = ['a', \"b\"]").unwrap();
+        assert_eq!(owned["v"], ["a", "b"]);
+
+        let err = from_str_via_value::<Release>("name = 'merde'\ndate = 2024-09-17\n").unwrap_err();
+        assert!(matches!(err, MerdeTomlError::MerdeError(_)), "{err}");
+
+        let err = from_str_via_value::<Value>("name = 'merde\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "TOML parsing error: unterminated string at line 1 column 8"
+        );
+    }
+
+    #[test]
+    fn test_to_string() {
+        let value = value!({ "package": { "name": "merde", "keywords": ["toml"] } });
+        let toml = to_string(&value).unwrap();
+        assert_eq!(from_str_via_value::<Value>(&toml).unwrap(), value);
+
+        let mut out = Vec::new();
+        to_writer(&mut out, &value).unwrap();
+        assert_eq!(out, toml.into_bytes());
+
+        let err = to_writer(Vec::new(), &value!(null)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
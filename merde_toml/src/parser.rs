@@ -0,0 +1,1120 @@
+//! Turns TOML text into a [Value] tree, see [toml_str_to_value].
+
+use std::collections::HashMap;
+
+use merde_core::{Array, CowStr, Map, Value};
+
+use crate::{LinePosition, MerdeTomlError};
+
+/// Parses a TOML document into a [Value::Map]. Keys and strings without escapes
+/// are borrowed from `src`.
+///
+/// Date-times are turned into strings: offset date-times as RFC 3339 (with a `T`
+/// between the date and the time, which TOML lets you write as a space), local
+/// date-times, dates and times as they were written.
+pub(crate) fn toml_str_to_value(src: &str) -> Result<Value<'_>, MerdeTomlError> {
+    let mut p = Parser {
+        src,
+        pos: src.strip_prefix('\u{feff}').map_or(0, |_| 3),
+    };
+    let mut root = Table::new(Kind::Header);
+    // the keys of the last `[header]` or `[[header]]`
+    let mut current: Vec<CowStr<'_>> = Vec::new();
+
+    loop {
+        p.skip_trivia()?;
+        if p.peek().is_none() {
+            break;
+        }
+
+        if p.eat(b'[') {
+            let array = p.eat(b'[');
+            p.skip_ws();
+            let keys = p.key()?;
+            p.skip_ws();
+            p.expect(b']', "`]` after a table header")?;
+            if array {
+                p.expect(b']', "`]]` after an array of tables header")?;
+            }
+            open_table(src, &mut root, &keys, array)?;
+            current = keys.into_iter().map(|(key, _)| key).collect();
+        } else {
+            let keys = p.key()?;
+            p.skip_ws();
+            p.expect(b'=', "`=` after a key")?;
+            p.skip_ws();
+            let value = p.value()?;
+            insert_dotted(src, current_table(&mut root, &current), keys, value)?;
+        }
+        p.end_of_line()?;
+    }
+
+    Ok(root.into_value())
+}
+
+/// Where a TOML parsing error happened.
+fn parse_error(src: &str, mut index: usize, reason: impl Into<String>) -> MerdeTomlError {
+    while !src.is_char_boundary(index) {
+        index -= 1;
+    }
+    let before = &src[..index];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    MerdeTomlError::ParseError {
+        reason: reason.into(),
+        position: LinePosition {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        },
+    }
+}
+
+/// How a table came to be, which decides how it can be added to later on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Created as the parent of a header, e.g. `a` in `[a.b]`: it can still be
+    /// defined by its own header later on.
+    Implicit,
+    /// Defined by a header, can't be defined again.
+    Header,
+    /// Created by a dotted key, e.g. `a` in `a.b = 1`: only other dotted keys
+    /// and sub-table headers can add to it.
+    Dotted,
+}
+
+enum Item<'s> {
+    /// A scalar, or a static array or inline table: those can't be added to
+    Value(Value<'s>),
+    Table(Table<'s>),
+    /// An array of tables, which `[[header]]`s add elements to. Never empty.
+    Array(Vec<Table<'s>>),
+}
+
+impl<'s> Item<'s> {
+    fn into_value(self) -> Value<'s> {
+        match self {
+            Item::Value(value) => value,
+            Item::Table(table) => table.into_value(),
+            Item::Array(tables) => Value::Array(
+                tables
+                    .into_iter()
+                    .map(Table::into_value)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// A table that's still being built, with its entries in document order.
+struct Table<'s> {
+    kind: Kind,
+    entries: Vec<(CowStr<'s>, Item<'s>)>,
+    index: HashMap<CowStr<'s>, usize>,
+}
+
+impl<'s> Table<'s> {
+    fn new(kind: Kind) -> Self {
+        Table {
+            kind,
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn find(&self, key: &str) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    fn push(&mut self, key: CowStr<'s>, item: Item<'s>) -> usize {
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, item));
+        self.entries.len() - 1
+    }
+
+    fn into_value(self) -> Value<'s> {
+        let mut map = Map::new();
+        for (key, item) in self.entries {
+            map.insert(key, item.into_value());
+        }
+        Value::Map(map)
+    }
+}
+
+type Keys<'s> = Vec<(CowStr<'s>, usize)>;
+
+/// `a.b.c`, for `keys[..=upto]`
+fn dotted(keys: &[(CowStr<'_>, usize)], upto: usize) -> String {
+    let keys: Vec<&str> = keys[..=upto].iter().map(|(key, _)| &**key).collect();
+    keys.join(".")
+}
+
+/// Handles a `[keys]` or `[[keys]]` header.
+fn open_table<'s>(
+    src: &str,
+    mut table: &mut Table<'s>,
+    keys: &[(CowStr<'s>, usize)],
+    array: bool,
+) -> Result<(), MerdeTomlError> {
+    let ((last, last_pos), parents) = keys.split_last().expect("keys are never empty");
+    for (i, (key, pos)) in parents.iter().enumerate() {
+        let index = match table.find(key) {
+            Some(index) => index,
+            None => table.push(key.clone(), Item::Table(Table::new(Kind::Implicit))),
+        };
+        table = match &mut table.entries[index].1 {
+            Item::Table(table) => table,
+            Item::Array(tables) => tables.last_mut().expect("arrays of tables are never empty"),
+            Item::Value(_) => {
+                return Err(parse_error(
+                    src,
+                    *pos,
+                    format!("`{}` is not a table", dotted(keys, i)),
+                ))
+            }
+        };
+    }
+
+    let defined = || {
+        parse_error(
+            src,
+            *last_pos,
+            format!("`{}` is already defined", dotted(keys, parents.len())),
+        )
+    };
+    match table.find(last) {
+        None if array => {
+            table.push(last.clone(), Item::Array(vec![Table::new(Kind::Header)]));
+        }
+        None => {
+            table.push(last.clone(), Item::Table(Table::new(Kind::Header)));
+        }
+        Some(index) => match (&mut table.entries[index].1, array) {
+            (Item::Array(tables), true) => tables.push(Table::new(Kind::Header)),
+            (Item::Table(table), false) if table.kind == Kind::Implicit => {
+                table.kind = Kind::Header
+            }
+            _ => return Err(defined()),
+        },
+    }
+    Ok(())
+}
+
+/// The table that the last header opened, which [open_table] made sure exists.
+fn current_table<'t, 's>(mut table: &'t mut Table<'s>, path: &[CowStr<'s>]) -> &'t mut Table<'s> {
+    for key in path {
+        let index = table.find(key).expect("headers create their tables");
+        table = match &mut table.entries[index].1 {
+            Item::Table(table) => table,
+            Item::Array(tables) => tables.last_mut().expect("arrays of tables are never empty"),
+            Item::Value(_) => unreachable!("headers only go through tables"),
+        };
+    }
+    table
+}
+
+/// Handles `keys = value`.
+fn insert_dotted<'s>(
+    src: &str,
+    mut table: &mut Table<'s>,
+    keys: Keys<'s>,
+    value: Value<'s>,
+) -> Result<(), MerdeTomlError> {
+    let defined = |i: usize| {
+        parse_error(
+            src,
+            keys[i].1,
+            format!("`{}` is already defined", dotted(&keys, i)),
+        )
+    };
+
+    let last = keys.len() - 1;
+    for (i, (key, _)) in keys[..last].iter().enumerate() {
+        let index = match table.find(key) {
+            Some(index) => index,
+            None => table.push(key.clone(), Item::Table(Table::new(Kind::Dotted))),
+        };
+        table = match &mut table.entries[index].1 {
+            Item::Table(table) if table.kind != Kind::Header => {
+                table.kind = Kind::Dotted;
+                table
+            }
+            _ => return Err(defined(i)),
+        };
+    }
+
+    if table.find(&keys[last].0).is_some() {
+        return Err(defined(last));
+    }
+    table.push(keys[last].0.clone(), Item::Value(value));
+    Ok(())
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn peek(&self) -> Option<u8> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.pos + offset).copied()
+    }
+
+    fn looking_at(&self, s: &str) -> bool {
+        self.src.as_bytes()[self.pos..].starts_with(s.as_bytes())
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, b: u8, what: &str) -> Result<(), MerdeTomlError> {
+        if self.eat(b) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {what}")))
+        }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> MerdeTomlError {
+        parse_error(self.src, self.pos, reason)
+    }
+
+    fn error_at(&self, pos: usize, reason: impl Into<String>) -> MerdeTomlError {
+        parse_error(self.src, pos, reason)
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat_newline(&mut self) -> bool {
+        if self.looking_at("\r\n") {
+            self.pos += 2;
+            true
+        } else {
+            self.eat(b'\n')
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), MerdeTomlError> {
+        if !self.eat(b'#') {
+            return Ok(());
+        }
+        while let Some(b) = self.peek() {
+            match b {
+                b'\n' => break,
+                b'\r' if self.peek_at(1) == Some(b'\n') => break,
+                b if is_control(b) => {
+                    return Err(self.error("control characters aren't allowed in comments"))
+                }
+                _ => self.pos += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips whitespace, comments and newlines.
+    fn skip_trivia(&mut self) -> Result<(), MerdeTomlError> {
+        loop {
+            self.skip_ws();
+            self.skip_comment()?;
+            if !self.eat_newline() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), MerdeTomlError> {
+        self.skip_ws();
+        self.skip_comment()?;
+        if self.peek().is_none() || self.eat_newline() {
+            Ok(())
+        } else {
+            Err(self.error("expected a newline"))
+        }
+    }
+
+    /// A dotted key, with where each part of it starts.
+    fn key(&mut self) -> Result<Keys<'s>, MerdeTomlError> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_ws();
+            let pos = self.pos;
+            keys.push((self.simple_key()?, pos));
+            self.skip_ws();
+            if !self.eat(b'.') {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<CowStr<'s>, MerdeTomlError> {
+        match self.peek() {
+            Some(b'"') => self.basic_string(),
+            Some(b'\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(b) if b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return Err(self.error("expected a key"));
+                }
+                Ok(CowStr::Borrowed(&self.src[start..self.pos]))
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value<'s>, MerdeTomlError> {
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some(b'"') if self.looking_at("\"\"\"") => self.ml_basic_string().map(Value::Str),
+            Some(b'"') => self.basic_string().map(Value::Str),
+            Some(b'\'') if self.looking_at("'''") => self.ml_literal_string().map(Value::Str),
+            Some(b'\'') => self.literal_string().map(Value::Str),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.inline_table(),
+            Some(b't') if self.looking_at("true") => {
+                self.pos += 4;
+                Ok(Value::Bool(true))
+            }
+            Some(b'f') if self.looking_at("false") => {
+                self.pos += 5;
+                Ok(Value::Bool(false))
+            }
+            Some(_) => match self.datetime()? {
+                Some(value) => Ok(value),
+                None => self.number(),
+            },
+        }
+    }
+
+    fn array(&mut self) -> Result<Value<'s>, MerdeTomlError> {
+        self.pos += 1;
+        let mut arr = Array::new();
+        loop {
+            self.skip_trivia()?;
+            if self.eat(b']') {
+                return Ok(Value::Array(arr));
+            }
+            arr.push(self.value()?);
+            self.skip_trivia()?;
+            if !self.eat(b',') {
+                self.expect(b']', "`,` or `]`")?;
+                return Ok(Value::Array(arr));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value<'s>, MerdeTomlError> {
+        self.pos += 1;
+        let mut table = Table::new(Kind::Dotted);
+        self.skip_ws();
+        if self.eat(b'}') {
+            return Ok(table.into_value());
+        }
+        loop {
+            let keys = self.key()?;
+            self.expect(b'=', "`=` after a key")?;
+            self.skip_ws();
+            let value = self.value()?;
+            insert_dotted(self.src, &mut table, keys, value)?;
+            self.skip_ws();
+            if !self.eat(b',') {
+                self.expect(b'}', "`,` or `}`")?;
+                return Ok(table.into_value());
+            }
+        }
+    }
+
+    /// A `"basic string"`, borrowed if it has no escapes.
+    fn basic_string(&mut self) -> Result<CowStr<'s>, MerdeTomlError> {
+        let open = self.pos;
+        self.pos += 1;
+        let mut owned: Option<String> = None;
+        let mut chunk = self.pos;
+        loop {
+            match self.peek() {
+                None | Some(b'\n' | b'\r') => {
+                    return Err(self.error_at(open, "unterminated string"));
+                }
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(finish_string(owned, &self.src[chunk..self.pos - 1]));
+                }
+                Some(b'\\') => {
+                    let out = owned.get_or_insert_with(String::new);
+                    out.push_str(&self.src[chunk..self.pos]);
+                    self.escape(out)?;
+                    chunk = self.pos;
+                }
+                Some(b) if is_control(b) => {
+                    return Err(self.error("control characters must be escaped"));
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// A `"""multi-line basic string"""`, borrowed if it has no escapes.
+    fn ml_basic_string(&mut self) -> Result<CowStr<'s>, MerdeTomlError> {
+        let open = self.pos;
+        self.pos += 3;
+        self.eat_newline();
+        let mut owned: Option<String> = None;
+        let mut chunk = self.pos;
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(open, "unterminated string")),
+                Some(b'"') => {
+                    if let Some(end) = self.closing_quotes(b'"')? {
+                        let s = finish_string(owned, &self.src[chunk..end]);
+                        return Ok(s);
+                    }
+                }
+                Some(b'\\') => {
+                    let out = owned.get_or_insert_with(String::new);
+                    out.push_str(&self.src[chunk..self.pos]);
+                    if !self.line_ending_backslash() {
+                        self.escape(out)?;
+                    }
+                    chunk = self.pos;
+                }
+                Some(b'\n') => self.pos += 1,
+                Some(_) if self.eat_newline() => {}
+                Some(b) if is_control(b) => {
+                    return Err(self.error("control characters must be escaped"));
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// A `'literal string'`, always borrowed.
+    fn literal_string(&mut self) -> Result<CowStr<'s>, MerdeTomlError> {
+        let open = self.pos;
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some(b'\n' | b'\r') => {
+                    return Err(self.error_at(open, "unterminated string"));
+                }
+                Some(b'\'') => {
+                    self.pos += 1;
+                    return Ok(CowStr::Borrowed(&self.src[start..self.pos - 1]));
+                }
+                Some(b) if is_control(b) => {
+                    return Err(self.error("control characters aren't allowed in literal strings"));
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// A `'''multi-line literal string'''`, always borrowed.
+    fn ml_literal_string(&mut self) -> Result<CowStr<'s>, MerdeTomlError> {
+        let open = self.pos;
+        self.pos += 3;
+        self.eat_newline();
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(open, "unterminated string")),
+                Some(b'\'') => {
+                    if let Some(end) = self.closing_quotes(b'\'')? {
+                        return Ok(CowStr::Borrowed(&self.src[start..end]));
+                    }
+                }
+                Some(b'\n') => self.pos += 1,
+                Some(_) if self.eat_newline() => {}
+                Some(b) if is_control(b) => {
+                    return Err(self.error("control characters aren't allowed in literal strings"));
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// At a quote in a multi-line string: if it's the closing delimiter, skips
+    /// it and returns where the string's contents end. Up to two quotes right
+    /// before the delimiter are part of the string.
+    fn closing_quotes(&mut self, quote: u8) -> Result<Option<usize>, MerdeTomlError> {
+        let count = self.src.as_bytes()[self.pos..]
+            .iter()
+            .take_while(|&&b| b == quote)
+            .count();
+        if count < 3 {
+            self.pos += count;
+            return Ok(None);
+        }
+        if count > 5 {
+            return Err(self.error_at(self.pos + 5, "too many quotes in a row"));
+        }
+        let end = self.pos + count - 3;
+        self.pos += count;
+        Ok(Some(end))
+    }
+
+    /// At a backslash in a multi-line basic string: if only whitespace follows
+    /// it on its line, skips it along with all the whitespace and newlines after it.
+    fn line_ending_backslash(&mut self) -> bool {
+        let start = self.pos;
+        self.pos += 1;
+        self.skip_ws();
+        if !self.eat_newline() {
+            self.pos = start;
+            return false;
+        }
+        loop {
+            self.skip_ws();
+            if !self.eat_newline() {
+                return true;
+            }
+        }
+    }
+
+    /// At a backslash: decodes the escape sequence into `out`.
+    fn escape(&mut self, out: &mut String) -> Result<(), MerdeTomlError> {
+        let start = self.pos;
+        self.pos += 1;
+        let c = match self.peek() {
+            Some(b'b') => '\u{8}',
+            Some(b't') => '\t',
+            Some(b'n') => '\n',
+            Some(b'f') => '\u{c}',
+            Some(b'r') => '\r',
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b @ (b'u' | b'U')) => {
+                let len = if b == b'u' { 4 } else { 8 };
+                let hex = self.src.get(self.pos + 1..self.pos + 1 + len);
+                let c = hex
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error_at(start, "invalid unicode escape"))?;
+                self.pos += len;
+                c
+            }
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        self.pos += 1;
+        out.push(c);
+        Ok(())
+    }
+
+    /// An offset date-time, local date-time, local date or local time, if
+    /// that's what comes next.
+    fn datetime(&mut self) -> Result<Option<Value<'s>>, MerdeTomlError> {
+        let start = self.pos;
+        let bytes = &self.src.as_bytes()[start..];
+        let is_date =
+            bytes.len() >= 5 && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-';
+        let is_time =
+            bytes.len() >= 3 && bytes[..2].iter().all(u8::is_ascii_digit) && bytes[2] == b':';
+
+        if is_time {
+            self.pos = self.time(start)?;
+            return Ok(Some(Value::Str(CowStr::Borrowed(
+                &self.src[start..self.pos],
+            ))));
+        }
+        if !is_date {
+            return Ok(None);
+        }
+
+        let year = digits(bytes, 0, 4);
+        let month = digits(bytes, 5, 2).filter(|_| bytes.get(7) == Some(&b'-'));
+        let day = digits(bytes, 8, 2);
+        let (Some(year), Some(month), Some(day)) = (year, month, day) else {
+            return Err(self.error_at(start, "invalid date"));
+        };
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(self.error_at(start, "invalid date"));
+        }
+        self.pos = start + 10;
+
+        // a space only separates the date from a time if a time follows
+        let separator = self.peek();
+        let has_time = match separator {
+            Some(b'T' | b't') => true,
+            Some(b' ') => matches!(self.peek_at(1), Some(b) if b.is_ascii_digit()),
+            _ => false,
+        };
+        if !has_time {
+            return Ok(Some(Value::Str(CowStr::Borrowed(
+                &self.src[start..self.pos],
+            ))));
+        }
+        self.pos = self.time(self.pos + 1)?;
+
+        let offset = match self.peek() {
+            Some(b'Z' | b'z') => {
+                self.pos += 1;
+                true
+            }
+            Some(b'+' | b'-') => {
+                let bytes = self.src.as_bytes();
+                let hours = digits(bytes, self.pos + 1, 2);
+                let minutes = digits(bytes, self.pos + 4, 2)
+                    .filter(|_| bytes.get(self.pos + 3) == Some(&b':'));
+                match (hours, minutes) {
+                    (Some(hours), Some(minutes)) if hours < 24 && minutes < 60 => {
+                        self.pos += 6;
+                        true
+                    }
+                    _ => return Err(self.error("invalid time offset")),
+                }
+            }
+            _ => false,
+        };
+
+        let text = &self.src[start..self.pos];
+        let needs_normalizing = offset && (separator != Some(b'T') || text.ends_with('z'));
+        Ok(Some(Value::Str(if needs_normalizing {
+            // `time`'s RFC 3339 parser wants a `T` and a `Z`
+            let mut text = text.to_owned();
+            text.replace_range(10..11, "T");
+            if text.ends_with('z') {
+                text.replace_range(text.len() - 1.., "Z");
+            }
+            CowStr::from(text)
+        } else {
+            CowStr::Borrowed(text)
+        })))
+    }
+
+    /// `HH:MM:SS`, with optional fractional seconds, starting at `start`.
+    /// Returns where it ends.
+    fn time(&self, start: usize) -> Result<usize, MerdeTomlError> {
+        let bytes = self.src.as_bytes();
+        let hours = digits(bytes, start, 2);
+        let minutes = digits(bytes, start + 3, 2).filter(|_| bytes.get(start + 2) == Some(&b':'));
+        let seconds = digits(bytes, start + 6, 2).filter(|_| bytes.get(start + 5) == Some(&b':'));
+        match (hours, minutes, seconds) {
+            (Some(hours), Some(minutes), Some(seconds))
+                if hours < 24 && minutes < 60 && seconds <= 60 => {}
+            _ => return Err(self.error_at(start, "invalid time")),
+        }
+
+        let mut end = start + 8;
+        if bytes.get(end) == Some(&b'.') {
+            let fraction = bytes[end + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            if fraction == 0 {
+                return Err(self.error_at(end, "invalid time"));
+            }
+            end += 1 + fraction;
+        }
+        Ok(end)
+    }
+
+    fn number(&mut self) -> Result<Value<'s>, MerdeTomlError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b) if b.is_ascii_alphanumeric() || matches!(b, b'_' | b'+' | b'-' | b'.'))
+        {
+            self.pos += 1;
+        }
+        let token = &self.src[start..self.pos];
+        if token.is_empty() {
+            return Err(self.error("expected a value"));
+        }
+        parse_number(token).map_err(|reason| self.error_at(start, format!("`{token}` {reason}")))
+    }
+}
+
+fn is_control(b: u8) -> bool {
+    (b < 0x20 && b != b'\t') || b == 0x7f
+}
+
+fn finish_string<'s>(owned: Option<String>, rest: &'s str) -> CowStr<'s> {
+    match owned {
+        None => CowStr::Borrowed(rest),
+        Some(mut owned) => {
+            owned.push_str(rest);
+            CowStr::from(owned)
+        }
+    }
+}
+
+/// The `len` ASCII digits at `at`, as a number.
+fn digits(bytes: &[u8], at: usize, len: usize) -> Option<u32> {
+    let digits = bytes.get(at..at + len)?;
+    digits.iter().try_fold(0, |n, b| {
+        b.is_ascii_digit().then(|| n * 10 + u32::from(b - b'0'))
+    })
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Integers and floats, in any of the forms TOML allows. The error completes
+/// "`token` ...".
+fn parse_number(token: &str) -> Result<Value<'static>, &'static str> {
+    const INVALID: &str = "is not a valid value";
+
+    match token {
+        "inf" | "+inf" => return Ok(Value::Float(f64::INFINITY)),
+        "-inf" => return Ok(Value::Float(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Ok(Value::Float(f64::NAN)),
+        _ => {}
+    }
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(rest) = token.strip_prefix(prefix) {
+            let digits = without_underscores(rest, radix).ok_or(INVALID)?;
+            return i64::from_str_radix(&digits, radix)
+                .map(Value::Int)
+                .map_err(|_| "doesn't fit in a 64-bit integer");
+        }
+    }
+
+    let (sign, body) = match token.as_bytes()[0] {
+        b'+' | b'-' => token.split_at(1),
+        _ => ("", token),
+    };
+    let split = body.find(['.', 'e', 'E']).unwrap_or(body.len());
+    let (int, rest) = body.split_at(split);
+    let int = without_underscores(int, 10).ok_or(INVALID)?;
+    if int.len() > 1 && int.starts_with('0') {
+        return Err("has leading zeros");
+    }
+
+    if rest.is_empty() {
+        return format!("{sign}{int}")
+            .parse()
+            .map(Value::Int)
+            .map_err(|_| "doesn't fit in a 64-bit integer");
+    }
+
+    let mut float = format!("{sign}{int}");
+    let exponent = match rest.strip_prefix('.') {
+        Some(rest) => {
+            let split = rest.find(['e', 'E']).unwrap_or(rest.len());
+            let (fraction, exponent) = rest.split_at(split);
+            float.push('.');
+            float.push_str(&without_underscores(fraction, 10).ok_or(INVALID)?);
+            exponent
+        }
+        None => rest,
+    };
+    if !exponent.is_empty() {
+        let exponent = &exponent[1..];
+        let (sign, digits) = match exponent.as_bytes().first() {
+            Some(b'+' | b'-') => exponent.split_at(1),
+            _ => ("", exponent),
+        };
+        float.push('e');
+        float.push_str(sign);
+        float.push_str(&without_underscores(digits, 10).ok_or(INVALID)?);
+    }
+    float.parse().map(Value::Float).map_err(|_| INVALID)
+}
+
+/// `s` without its underscores, if it's made of digits in `radix` with
+/// underscores only ever between two of them.
+fn without_underscores(s: &str, radix: u32) -> Option<String> {
+    let is_digit = |c: char| c.is_digit(radix);
+    let valid = s.starts_with(is_digit)
+        && s.ends_with(is_digit)
+        && !s.contains("__")
+        && s.chars().all(|c| c == '_' || is_digit(c));
+    valid.then(|| s.replace('_', ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Value};
+
+    use super::toml_str_to_value;
+    use crate::MerdeTomlError;
+
+    fn parse(src: &str) -> Value<'_> {
+        match toml_str_to_value(src) {
+            Ok(value) => value,
+            Err(e) => panic!("{e}\n{src}"),
+        }
+    }
+
+    #[test]
+    fn test_tables() {
+        let src = r#"
+# a comment
+name = "merde" # trailing comment
+version.major = 4
+version.minor = 0
+
+[package]
+edition = "2021"
+
+[dependencies.merde_core]
+path = "../merde_core"
+
+[dependencies]
+saphyr-parser = "0.0.6"
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+
+[bin.extra]
+debug = true
+
+[[bin.targets]]
+os = "linux"
+
+[fruit]
+apple.color = "red"
+
+[fruit.apple.texture]
+smooth = true
+"#;
+        assert_eq!(
+            parse(src),
+            value!({
+                "name": "merde",
+                "version": { "major": 4, "minor": 0 },
+                "package": { "edition": "2021" },
+                "dependencies": {
+                    "merde_core": { "path": "../merde_core" },
+                    "saphyr-parser": "0.0.6"
+                },
+                "bin": [
+                    { "name": "a" },
+                    { "name": "b", "extra": { "debug": true }, "targets": [{ "os": "linux" }] }
+                ],
+                "fruit": { "apple": { "color": "red", "texture": { "smooth": true } } }
+            })
+        );
+
+        assert_eq!(parse(""), value!({}));
+        assert_eq!(
+            parse("\u{feff}a = 1\r\n[b]\r\n"),
+            value!({ "a": 1, "b": {} })
+        );
+        assert_eq!(
+            parse("\"quoted key\" = 1\n'literal.key' = 2\n\"\" = 3\n  a . \"b\" . c = 4\n"),
+            value!({ "quoted key": 1, "literal.key": 2, "": 3, "a": { "b": { "c": 4 } } })
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            parse(
+                r#"
+point = { x = 1, y = 2, z.w = 3 }
+empty = {}
+nested = [[1, 2], ["a", 'b'], [{ k = "v" }], []]
+multiline = [
+    1, # one
+    # nothing here
+    2,
+]
+"#
+            ),
+            value!({
+                "point": { "x": 1, "y": 2, "z": { "w": 3 } },
+                "empty": {},
+                "nested": [[1, 2], ["a", "b"], [{ "k": "v" }], []],
+                "multiline": [1, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let src = r#"
+basic = "tab\there \"quoted\" \\ \u00e9 \U0001F4A9"
+borrowed = "no escapes"
+literal = 'C:\Users\nodejs'
+ml_basic = """
+Roses are red
+Violets are blue"""
+ml_trimmed = """\
+    The quick brown \
+    fox jumps over \
+    the lazy dog.\
+    """
+ml_quotes = """Here are two quotation marks: "". Simple enough.""""
+ml_literal = '''
+The first newline is
+trimmed in raw strings.
+   All other whitespace
+   is preserved.
+'''
+ml_literal_quotes = ''''That,' she said, 'is still pointless.''''
+"#;
+        let value = parse(src);
+        let map = value.as_map().unwrap();
+        let get = |key: &str| match map.get(key) {
+            Some(Value::Str(s)) => s,
+            other => panic!("{key}: {other:?}"),
+        };
+        assert_eq!(*get("basic"), "tab\there \"quoted\" \\ é 💩");
+        assert_eq!(*get("literal"), r"C:\Users\nodejs");
+        assert_eq!(*get("ml_basic"), "Roses are red\nViolets are blue");
+        assert_eq!(
+            *get("ml_trimmed"),
+            "The quick brown fox jumps over the lazy dog."
+        );
+        assert_eq!(
+            *get("ml_quotes"),
+            "Here are two quotation marks: \"\". Simple enough.\""
+        );
+        assert_eq!(
+            *get("ml_literal"),
+            "The first newline is\ntrimmed in raw strings.\n   All other whitespace\n   is preserved.\n"
+        );
+        assert_eq!(
+            *get("ml_literal_quotes"),
+            "'That,' she said, 'is still pointless.'"
+        );
+
+        for key in ["borrowed", "literal", "ml_basic", "ml_literal"] {
+            assert!(matches!(get(key), CowStr::Borrowed(_)), "{key}");
+        }
+        assert!(matches!(get("basic"), CowStr::Owned(_)));
+    }
+
+    #[test]
+    fn test_numbers() {
+        let src = "
+ints = [+99, 42, 0, -17, 1_000, 5_349_221, -9223372036854775808]
+radix = [0xDEADBEEF, 0xdead_beef, 0o755, 0b1101_0110]
+floats = [+1.0, 3.25, -0.01, 5e+22, 1e06, -2E-2, 6.626e-34, 224_617.445_991]
+special = [inf, +inf, -inf]
+";
+        assert_eq!(
+            parse(src),
+            value!({
+                "ints": [99, 42, 0, -17, 1000, 5349221, i64::MIN],
+                "radix": [0xDEADBEEF_i64, 0xDEADBEEF_i64, 0o755, 0b11010110],
+                "floats": [1.0, 3.25, -0.01, 5e22, 1e6, -2e-2, 6.626e-34, 224617.445991],
+                "special": [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY]
+            })
+        );
+        for nan in ["nan", "+nan", "-nan"] {
+            let value = parse_one(nan);
+            assert!(matches!(value, Value::Float(f) if f.is_nan()), "{nan}");
+        }
+    }
+
+    fn parse_one(value: &str) -> Value<'_> {
+        let src = format!("v = {value}");
+        let parsed = toml_str_to_value(&src)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .as_map()
+            .unwrap()
+            .get("v")
+            .cloned()
+            .unwrap();
+        merde_core::IntoStatic::into_static(parsed)
+    }
+
+    #[test]
+    fn test_datetimes() {
+        for (toml, expected) in [
+            ("1979-05-27T07:32:00Z", "1979-05-27T07:32:00Z"),
+            ("1979-05-27 07:32:00z", "1979-05-27T07:32:00Z"),
+            (
+                "1979-05-27T00:32:00.999999-07:00",
+                "1979-05-27T00:32:00.999999-07:00",
+            ),
+            ("1979-05-27t07:32:00+01:30", "1979-05-27T07:32:00+01:30"),
+            ("1979-05-27T07:32:00", "1979-05-27T07:32:00"),
+            ("1979-05-27 07:32:00.5", "1979-05-27 07:32:00.5"),
+            ("2024-02-29", "2024-02-29"),
+            ("07:32:00", "07:32:00"),
+            ("00:32:00.999999", "00:32:00.999999"),
+        ] {
+            assert_eq!(parse_one(toml), Value::from(expected), "{toml}");
+        }
+        assert_eq!(
+            parse("d = 1979-05-27 # a date\n"),
+            value!({ "d": "1979-05-27" })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for (src, line, column, reason) in [
+            ("a = 1\na = 2\n", 2, 1, "`a` is already defined"),
+            ("[a]\n[a]\n", 2, 2, "`a` is already defined"),
+            ("[a]\nb = 1\n[a.b]\n", 3, 4, "`a.b` is already defined"),
+            ("a.b = 1\n[a]\n", 2, 2, "`a` is already defined"),
+            ("[a.b]\n[a]\nb.c = 1\n", 3, 1, "`b` is already defined"),
+            ("a = [1]\n[[a]]\n", 2, 3, "`a` is already defined"),
+            ("a = {}\n[a.b]\n", 2, 2, "`a` is not a table"),
+            ("a = { b = 1 }\na.c = 2\n", 2, 1, "`a` is already defined"),
+            ("a = { b = 1, b = 2 }\n", 1, 14, "`b` is already defined"),
+            ("a = \"unterminated\n", 1, 5, "unterminated string"),
+            ("a = \"\\x\"\n", 1, 6, "invalid escape sequence"),
+            ("a = \"\\uD800\"\n", 1, 6, "invalid unicode escape"),
+            ("a = 01\n", 1, 5, "`01` has leading zeros"),
+            ("a = 1__0\n", 1, 5, "`1__0` is not a valid value"),
+            ("a = 1.\n", 1, 5, "`1.` is not a valid value"),
+            ("a = .5\n", 1, 5, "`.5` is not a valid value"),
+            (
+                "a = 9223372036854775808\n",
+                1,
+                5,
+                "`9223372036854775808` doesn't fit in a 64-bit integer",
+            ),
+            ("a = 1979-02-29\n", 1, 5, "invalid date"),
+            ("a = 24:00:00\n", 1, 5, "invalid time"),
+            ("a = 1 b = 2\n", 1, 7, "expected a newline"),
+            ("a = { b = 1, }\n", 1, 14, "expected a key"),
+            ("a = { b = 1\n}\n", 1, 12, "expected `,` or `}`"),
+            ("a = [1 2]\n", 1, 8, "expected `,` or `]`"),
+            ("a =\n", 1, 4, "expected a value"),
+            ("[a\n", 1, 3, "expected `]` after a table header"),
+            (
+                "[[a]\n",
+                1,
+                5,
+                "expected `]]` after an array of tables header",
+            ),
+            ("é = 1\n", 1, 1, "expected a key"),
+            ("a = \"é\" é\n", 1, 9, "expected a newline"),
+        ] {
+            let err = toml_str_to_value(src).unwrap_err();
+            let MerdeTomlError::ParseError {
+                reason: actual,
+                position,
+            } = &err
+            else {
+                panic!("{err}");
+            };
+            assert_eq!(
+                (actual.as_str(), position.line, position.column),
+                (reason, line, column),
+                "{src}"
+            );
+        }
+    }
+}
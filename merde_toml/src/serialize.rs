@@ -0,0 +1,265 @@
+//! Writes [Value] trees as TOML documents.
+
+use merde_core::{Map, Value};
+
+use crate::MerdeTomlError;
+
+/// Writes `value`, which has to be a map, as a TOML document.
+///
+/// Maps become `[tables]` and arrays of maps `[[arrays of tables]]`, except inside
+/// other arrays, where they're written inline. `null` map entries are left out,
+/// since TOML has no null.
+pub(crate) fn write_document(out: &mut String, value: &Value<'_>) -> Result<(), MerdeTomlError> {
+    match value {
+        Value::Map(map) => write_table(out, &mut Vec::new(), map),
+        _ => Err(unrepresentable(
+            &[],
+            "the top level of a TOML document is a table",
+        )),
+    }
+}
+
+fn unrepresentable(path: &[&str], reason: &'static str) -> MerdeTomlError {
+    MerdeTomlError::Unrepresentable {
+        path: path.join("."),
+        reason,
+    }
+}
+
+/// Whether `value` gets its own `[table]` or `[[array of tables]]` section.
+fn is_section(value: &Value<'_>) -> bool {
+    match value {
+        Value::Map(_) => true,
+        Value::Array(arr) => !arr.is_empty() && arr.iter().all(|v| matches!(v, Value::Map(_))),
+        _ => false,
+    }
+}
+
+/// Writes the key/value pairs of `map`, then its sections: TOML has no way to
+/// get back to a table once a sub-table's header has been written.
+fn write_table<'v>(
+    out: &mut String,
+    path: &mut Vec<&'v str>,
+    map: &'v Map<'_>,
+) -> Result<(), MerdeTomlError> {
+    for (key, value) in map.iter() {
+        if is_section(value) || matches!(value, Value::Null) {
+            continue;
+        }
+        path.push(key);
+        write_key(out, key);
+        out.push_str(" = ");
+        write_inline(out, path, value)?;
+        out.push('\n');
+        path.pop();
+    }
+
+    for (key, value) in map.iter() {
+        path.push(key);
+        match value {
+            Value::Map(map) => {
+                // a table with nothing but sub-tables is created by their headers
+                if map.is_empty() || map.values().any(|v| !is_section(v)) {
+                    write_header(out, path, "[", "]");
+                }
+                write_table(out, path, map)?;
+            }
+            Value::Array(arr) if is_section(value) => {
+                for table in arr.iter() {
+                    write_header(out, path, "[[", "]]");
+                    if let Value::Map(map) = table {
+                        write_table(out, path, map)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn write_header(out: &mut String, path: &[&str], open: &str, close: &str) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(open);
+    for (i, key) in path.iter().enumerate() {
+        if i > 0 {
+            out.push('.');
+        }
+        write_key(out, key);
+    }
+    out.push_str(close);
+    out.push('\n');
+}
+
+fn write_inline<'v>(
+    out: &mut String,
+    path: &mut Vec<&'v str>,
+    value: &'v Value<'_>,
+) -> Result<(), MerdeTomlError> {
+    match value {
+        Value::Null => return Err(unrepresentable(path, "TOML has no null")),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Int(i) => out.push_str(&i.to_string()),
+        Value::Float(f) if f.is_nan() => out.push_str("nan"),
+        Value::Float(f) if f.is_infinite() => out.push_str(if *f > 0.0 { "inf" } else { "-inf" }),
+        // `Debug` always has a `.` or an exponent, so it reads back as a float
+        Value::Float(f) => out.push_str(&format!("{f:?}")),
+        Value::Str(s) => write_str(out, s),
+        Value::Array(arr) => {
+            out.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_inline(out, path, item)?;
+            }
+            out.push(']');
+        }
+        Value::Map(map) => {
+            out.push('{');
+            let mut first = true;
+            for (key, value) in map.iter() {
+                if matches!(value, Value::Null) {
+                    continue;
+                }
+                out.push_str(if first { " " } else { ", " });
+                first = false;
+                path.push(key);
+                write_key(out, key);
+                out.push_str(" = ");
+                write_inline(out, path, value)?;
+                path.pop();
+            }
+            out.push_str(if first { "}" } else { " }" });
+        }
+    }
+    Ok(())
+}
+
+fn write_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if bare {
+        out.push_str(key);
+    } else {
+        write_str(out, key);
+    }
+}
+
+/// Writes `s` as a basic string.
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' ' || c == '\u{7f}' => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, Value};
+
+    use super::write_document;
+    use crate::parser::toml_str_to_value;
+    use crate::MerdeTomlError;
+
+    fn to_toml(value: &Value<'_>) -> String {
+        let mut out = String::new();
+        write_document(&mut out, value).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_sections() {
+        // single-key maps, so that the output doesn't depend on key order
+        let value = value!({
+            "package": {
+                "metadata": { "docs": { "features": ["a", "b"] } }
+            }
+        });
+        assert_eq!(
+            to_toml(&value),
+            "[package.metadata.docs]\nfeatures = [\"a\", \"b\"]\n"
+        );
+
+        let value = value!({ "bin": [{ "name": "a" }, { "target": { "os": "linux" } }] });
+        assert_eq!(
+            to_toml(&value),
+            "[[bin]]\nname = \"a\"\n\n[[bin]]\n\n[bin.target]\nos = \"linux\"\n"
+        );
+        assert_eq!(toml_str_to_value(&to_toml(&value)).unwrap(), value);
+
+        let value = value!({
+            "name": "merde",
+            "empty": {},
+            "tables": [{}, { "a": 1 }],
+            "mixed": [1, { "x": [{ "y": null }] }, []],
+            "deps": { "merde_core": { "path": "../merde_core", "optional": null } },
+            "skipped": null,
+        });
+        let toml = to_toml(&value);
+        let mut expected = value.clone();
+        expected.pointer_remove("/skipped").unwrap();
+        expected
+            .pointer_remove("/deps/merde_core/optional")
+            .unwrap();
+        *expected.pointer_mut("/mixed/1/x/0").unwrap() = value!({});
+        assert_eq!(toml_str_to_value(&toml).unwrap(), expected, "{toml}");
+    }
+
+    #[test]
+    fn test_round_trip_scalars() {
+        for value in [
+            value!("plain"),
+            value!(""),
+            value!("quotes \" and \\ and \u{8}\t\n\u{c}\r\0\u{7f} and ünïcödé"),
+            value!(i64::MIN),
+            value!(i64::MAX),
+            value!(true),
+            value!(1.0),
+            value!(-0.0),
+            value!(1e300),
+            value!(2.5e-8),
+            value!(f64::INFINITY),
+            value!(f64::NEG_INFINITY),
+        ] {
+            let doc = value!({ "v": (value.clone()), "we\"ird key.": [(value.clone())] });
+            let toml = to_toml(&doc);
+            assert_eq!(toml_str_to_value(&toml).unwrap(), doc, "{toml}");
+        }
+    }
+
+    #[test]
+    fn test_unrepresentable() {
+        let mut out = String::new();
+        for (value, expected) in [
+            (
+                value!([1]),
+                "Cannot serialize as TOML: the top level of a TOML document is a table",
+            ),
+            (
+                value!({ "a": { "b": [1, null] } }),
+                "Cannot serialize `a.b` as TOML: TOML has no null",
+            ),
+        ] {
+            let err = write_document(&mut out, &value).unwrap_err();
+            assert!(matches!(err, MerdeTomlError::Unrepresentable { .. }));
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+mod parser;
+mod serialize;
+
+use merde_core::{MerdeError, OwnedValueDeserialize, Value, ValueDeserialize, ValueSerialize};
+use parser::yaml_str_to_values;
+use saphyr_parser::ScanError;
+
+use std::io::Write;
+
+/// Unifies [MerdeError] and YAML parsing errors into a single type
+pub enum MerdeYamlError {
+    /// A [MerdeError]
+    MerdeError(MerdeError),
+
+    /// The source isn't valid YAML
+    ParseError(ScanError),
+
+    /// The source is valid YAML, but can't be turned into a [Value], e.g. because
+    /// it uses a sequence as a map key
+    Unsupported {
+        /// What's wrong
+        reason: String,
+        /// The 1-based line of the offending node
+        line: usize,
+        /// The 1-based column of the offending node
+        column: usize,
+    },
+
+    /// [from_str_via_value] was given a stream with several documents, see
+    /// [from_str_via_value_multi]
+    MultipleDocuments(usize),
+
+    /// An error in one of the documents of a stream, see [from_str_via_value_multi]
+    Document {
+        /// The 0-based index of the document in the stream
+        index: usize,
+        /// The underlying error
+        err: Box<MerdeYamlError>,
+    },
+}
+
+impl From<ScanError> for MerdeYamlError {
+    fn from(e: ScanError) -> Self {
+        MerdeYamlError::ParseError(e)
+    }
+}
+
+impl From<MerdeError> for MerdeYamlError {
+    fn from(e: MerdeError) -> Self {
+        MerdeYamlError::MerdeError(e)
+    }
+}
+
+impl std::fmt::Display for MerdeYamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MerdeYamlError::MerdeError(me) => write!(f, "Merde Error: {}", me),
+            MerdeYamlError::ParseError(e) => write!(f, "YAML parsing error: {}", e),
+            MerdeYamlError::Unsupported {
+                reason,
+                line,
+                column,
+            } => write!(
+                f,
+                "Unsupported YAML: {} (line {}, column {})",
+                reason, line, column
+            ),
+            MerdeYamlError::MultipleDocuments(count) => write!(
+                f,
+                "Expected a single YAML document, found {} of them",
+                count
+            ),
+            MerdeYamlError::Document { index, err } => write!(f, "Document {}: {}", index, err),
+        }
+    }
+}
+
+impl std::error::Error for MerdeYamlError {}
+
+impl std::fmt::Debug for MerdeYamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+/// Parses the single document in `s`: an empty stream is `null`, several
+/// documents are an error.
+fn yaml_str_to_value(s: &str) -> Result<Value<'_>, MerdeYamlError> {
+    let mut documents = yaml_str_to_values(s)?;
+    match documents.len() {
+        0 => Ok(Value::Null),
+        1 => Ok(documents.pop().unwrap()),
+        n => Err(MerdeYamlError::MultipleDocuments(n)),
+    }
+}
+
+/// Deserialize an instance of type `T` from a string of YAML text.
+///
+/// Plain scalars (and quoted ones without escapes) are borrowed from `s`. The
+/// stream must hold at most one document, see [from_str_via_value_multi].
+pub fn from_str_via_value<'s, T>(s: &'s str) -> Result<T, MerdeYamlError>
+where
+    T: ValueDeserialize<'s>,
+{
+    Ok(merde_core::from_value(yaml_str_to_value(s)?)?)
+}
+
+/// Deserialize an instance of type `T` from a string of YAML text, making
+/// sure the result is owned.
+pub fn owned_from_str_via_value<T>(s: &str) -> Result<T, MerdeYamlError>
+where
+    T: OwnedValueDeserialize,
+{
+    Ok(T::owned_from_value(Some(yaml_str_to_value(s)?))?)
+}
+
+/// Deserialize one instance of type `T` per document in a YAML stream (documents
+/// are separated by `---`). Errors are wrapped in [MerdeYamlError::Document].
+pub fn from_str_via_value_multi<'s, T>(s: &'s str) -> Result<Vec<T>, MerdeYamlError>
+where
+    T: ValueDeserialize<'s>,
+{
+    yaml_str_to_values(s)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            merde_core::from_value(value).map_err(|e| MerdeYamlError::Document {
+                index,
+                err: Box::new(e.into()),
+            })
+        })
+        .collect()
+}
+
+/// Serialize the given data structure as a YAML document, in block style.
+///
+/// Anchors and aliases are never written: a value that shows up twice is written twice.
+pub fn to_string<T: ValueSerialize + ?Sized>(value: &T) -> String {
+    let mut out = String::new();
+    serialize::write_document(&mut out, &value.to_value());
+    out
+}
+
+/// Serialize the given data structures as a YAML stream, one document each.
+pub fn to_string_multi<T: ValueSerialize>(values: &[T]) -> String {
+    let mut out = String::new();
+    for value in values {
+        out.push_str("---\n");
+        serialize::write_document(&mut out, &value.to_value());
+    }
+    out
+}
+
+/// Serialize the given data structure as a YAML document into the I/O stream.
+pub fn to_writer<T>(mut writer: impl Write, value: &T) -> std::io::Result<()>
+where
+    T: ValueSerialize + ?Sized,
+{
+    writer.write_all(to_string(value).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Map, MerdeError, Value, ValueDeserialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Service<'s> {
+        name: CowStr<'s>,
+        port: Option<u16>,
+    }
+
+    impl<'s> ValueDeserialize<'s> for Service<'s> {
+        fn from_value_ref(value: Option<&Value<'s>>) -> Result<Self, MerdeError> {
+            Self::from_value(value.cloned())
+        }
+
+        fn from_value(value: Option<Value<'s>>) -> Result<Self, MerdeError> {
+            let mut map = Map::from_value(value)?;
+            Ok(Service {
+                name: map.must_remove("name")?,
+                port: map.must_remove("port")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_from_str_via_value() {
+        let src = "name: web\nport: 8080\n";
+        let service: Service = from_str_via_value(src).unwrap();
+        assert_eq!(
+            service,
+            Service {
+                name: "web".into(),
+                port: Some(8080)
+            }
+        );
+        assert!(matches!(service.name, CowStr::Borrowed(_)));
+
+        let owned: Vec<String> = owned_from_str_via_value("[web, '8080']").unwrap();
+        assert_eq!(owned, ["web", "8080"]);
+
+        assert_eq!(from_str_via_value::<Value>("").unwrap(), Value::Null);
+        let err = from_str_via_value::<Value>("1\n---\n2\n").unwrap_err();
+        assert!(matches!(err, MerdeYamlError::MultipleDocuments(2)), "{err}");
+
+        let err = from_str_via_value::<Service>("name: web\nport: -1\n").unwrap_err();
+        assert!(matches!(err, MerdeYamlError::MerdeError(_)), "{err}");
+    }
+
+    #[test]
+    fn test_multi() {
+        let src = "name: a\n---\nname: b\nport: 1\n";
+        let services: Vec<Service> = from_str_via_value_multi(src).unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[1].port, Some(1));
+
+        let err = from_str_via_value_multi::<Service>("name: a\n---\nport: 1\n").unwrap_err();
+        assert!(
+            matches!(&err, MerdeYamlError::Document { index: 1, .. }),
+            "{err}"
+        );
+
+        let docs = [value!({ "a": [1, 2.5] }), value!(null), value!("x")];
+        let yaml = to_string_multi(&docs);
+        assert_eq!(yaml, "---\na:\n  - 1\n  - 2.5\n---\nnull\n---\nx\n");
+        assert_eq!(
+            from_str_via_value_multi::<Value>(&yaml).unwrap(),
+            docs.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let value = value!({ "list": ["a b", "c: d"] });
+        let mut out = Vec::new();
+        to_writer(&mut out, &value).unwrap();
+        assert_eq!(out, to_string(&value).into_bytes());
+        assert_eq!(
+            from_str_via_value::<Value>(std::str::from_utf8(&out).unwrap()).unwrap(),
+            value
+        );
+    }
+}
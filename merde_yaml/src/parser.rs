@@ -0,0 +1,567 @@
+//! Turns a stream of YAML events into [Value] trees, one per document.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use merde_core::{CowStr, Map, Value};
+use saphyr_parser::{Event, Parser, ScalarStyle, Span, Tag};
+
+use crate::MerdeYamlError;
+
+/// How many nodes aliases may expand to in a single document, so that a small
+/// document full of aliases to aliases (a "billion laughs") can't eat up all memory.
+const MAX_ALIAS_EXPANSION: usize = 1_000_000;
+
+/// Parses every document in `src`. An empty stream has no documents.
+pub(crate) fn yaml_str_to_values(src: &str) -> Result<Vec<Value<'_>>, MerdeYamlError> {
+    let mut builder = Builder {
+        src,
+        documents: Vec::new(),
+        root: None,
+        stack: Vec::new(),
+        anchors: HashMap::new(),
+        expanded: 0,
+        cursor: (0, 0),
+    };
+    for event in Parser::new_from_str(src) {
+        let (event, span) = event?;
+        builder.event(event, span)?;
+    }
+    Ok(builder.documents)
+}
+
+/// A value, and how many nodes it's made of (itself included)
+type Node<'s> = (Value<'s>, usize);
+
+struct Builder<'s> {
+    src: &'s str,
+    documents: Vec<Value<'s>>,
+    root: Option<Value<'s>>,
+    stack: Vec<Frame<'s>>,
+    anchors: HashMap<usize, Anchored<'s>>,
+    expanded: usize,
+    /// A char index into `src` along with its byte offset, see [Builder::byte_offset]
+    cursor: (usize, usize),
+}
+
+/// What an anchor stands for
+struct Anchored<'s> {
+    node: Node<'s>,
+    /// for scalars, how they were written, so that aliases used as keys are
+    /// kept as written too
+    text: Option<CowStr<'s>>,
+}
+
+/// A collection we're in the middle of
+enum Frame<'s> {
+    Seq {
+        anchor: usize,
+        items: Vec<Value<'s>>,
+        nodes: usize,
+    },
+    Map {
+        anchor: usize,
+        map: Map<'s>,
+        key: Option<Key<'s>>,
+        /// values of `<<` keys, merged in once the map is complete
+        merges: Vec<(Value<'s>, Span)>,
+        nodes: usize,
+    },
+}
+
+enum Key<'s> {
+    Str(CowStr<'s>),
+    /// a plain `<<`, see <https://yaml.org/type/merge.html>
+    Merge(Span),
+}
+
+impl<'s> Builder<'s> {
+    fn event(&mut self, event: Event<'s>, span: Span) -> Result<(), MerdeYamlError> {
+        match event {
+            Event::Nothing | Event::StreamStart | Event::StreamEnd | Event::DocumentStart(_) => {}
+            Event::DocumentEnd => {
+                self.documents.push(self.root.take().unwrap_or(Value::Null));
+                self.anchors.clear();
+                self.expanded = 0;
+            }
+            Event::Scalar(text, style, anchor, tag) => {
+                let text = self.borrow(text, style, span);
+                if self.expects_key() {
+                    let key = if style == ScalarStyle::Plain && tag.is_none() && text == "<<" {
+                        Key::Merge(span)
+                    } else {
+                        // keys are kept as written: `1.0: x` has the key "1.0"
+                        Key::Str(text.clone())
+                    };
+                    if anchor > 0 {
+                        let value = resolve(text.clone(), style, tag.as_deref(), span)?;
+                        self.anchor_scalar(anchor, value, text);
+                    }
+                    self.set_key(key);
+                } else {
+                    let value = resolve(text.clone(), style, tag.as_deref(), span)?;
+                    if anchor > 0 {
+                        self.anchor_scalar(anchor, value.clone(), text);
+                    }
+                    self.push((value, 1), 0);
+                }
+            }
+            Event::Alias(anchor) => {
+                let Some(anchored) = self.anchors.get(&anchor) else {
+                    return Err(unsupported("alias to an unknown anchor", span));
+                };
+                if self.expects_key() {
+                    let key = anchored
+                        .text
+                        .clone()
+                        .ok_or_else(|| unsupported("only scalars can be used as keys", span))?;
+                    self.set_key(Key::Str(key));
+                } else {
+                    let (value, nodes) = anchored.node.clone();
+                    self.expanded += nodes;
+                    if self.expanded > MAX_ALIAS_EXPANSION {
+                        return Err(unsupported("aliases expand to too many nodes", span));
+                    }
+                    self.push((value, nodes), 0);
+                }
+            }
+            Event::SequenceStart(anchor, _) => {
+                self.check_not_key(span)?;
+                self.stack.push(Frame::Seq {
+                    anchor,
+                    items: Vec::new(),
+                    nodes: 1,
+                });
+            }
+            Event::MappingStart(anchor, _) => {
+                self.check_not_key(span)?;
+                self.stack.push(Frame::Map {
+                    anchor,
+                    map: Map::new(),
+                    key: None,
+                    merges: Vec::new(),
+                    nodes: 1,
+                });
+            }
+            Event::SequenceEnd => {
+                let Some(Frame::Seq {
+                    anchor,
+                    items,
+                    nodes,
+                }) = self.stack.pop()
+                else {
+                    unreachable!("the parser only ends sequences it started")
+                };
+                self.push((Value::Array(items.into()), nodes), anchor);
+            }
+            Event::MappingEnd => {
+                let Some(Frame::Map {
+                    anchor,
+                    mut map,
+                    merges,
+                    nodes,
+                    ..
+                }) = self.stack.pop()
+                else {
+                    unreachable!("the parser only ends mappings it started")
+                };
+                for (merge, span) in merges {
+                    merge_into(&mut map, merge, span)?;
+                }
+                self.push((Value::Map(map), nodes), anchor);
+            }
+        }
+        Ok(())
+    }
+
+    /// Borrows `text` from the source if it's in there verbatim, as plain
+    /// scalars and quoted ones without escapes are. The parser hands out owned
+    /// strings, even when it didn't have to unescape or fold anything.
+    fn borrow(&mut self, text: Cow<'s, str>, style: ScalarStyle, span: Span) -> CowStr<'s> {
+        match text {
+            Cow::Borrowed(text) => CowStr::Borrowed(text),
+            Cow::Owned(text) => {
+                let mut start = self.byte_offset(span.start.index());
+                if matches!(style, ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted) {
+                    start += 1;
+                }
+                match self.src.get(start..start + text.len()) {
+                    Some(verbatim) if verbatim == text => CowStr::Borrowed(verbatim),
+                    _ => CowStr::from(text),
+                }
+            }
+        }
+    }
+
+    /// Turns a char index, which is what the parser's markers hold, into a byte
+    /// offset into `src`. Scalars come in order, so this picks up from the last one.
+    fn byte_offset(&mut self, char_index: usize) -> usize {
+        if char_index < self.cursor.0 {
+            self.cursor = (0, 0);
+        }
+        let (chars, bytes) = self.cursor;
+        let offset = self.src[bytes..]
+            .char_indices()
+            .nth(char_index - chars)
+            .map_or(self.src.len(), |(i, _)| bytes + i);
+        self.cursor = (char_index, offset);
+        offset
+    }
+
+    fn anchor_scalar(&mut self, anchor: usize, value: Value<'s>, text: CowStr<'s>) {
+        let anchored = Anchored {
+            node: (value, 1),
+            text: Some(text),
+        };
+        self.anchors.insert(anchor, anchored);
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Map { key: None, .. }))
+    }
+
+    fn check_not_key(&self, span: Span) -> Result<(), MerdeYamlError> {
+        if self.expects_key() {
+            return Err(unsupported("only scalars can be used as keys", span));
+        }
+        Ok(())
+    }
+
+    fn set_key(&mut self, new_key: Key<'s>) {
+        if let Some(Frame::Map { key, .. }) = self.stack.last_mut() {
+            *key = Some(new_key);
+        }
+    }
+
+    /// Adds a complete value to whatever it's part of.
+    fn push(&mut self, (value, value_nodes): Node<'s>, anchor: usize) {
+        if anchor > 0 {
+            let anchored = Anchored {
+                node: (value.clone(), value_nodes),
+                text: None,
+            };
+            self.anchors.insert(anchor, anchored);
+        }
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(Frame::Seq { items, nodes, .. }) => {
+                items.push(value);
+                *nodes += value_nodes;
+            }
+            Some(Frame::Map {
+                map,
+                key,
+                merges,
+                nodes,
+                ..
+            }) => {
+                match key.take() {
+                    Some(Key::Str(key)) => {
+                        map.insert(key, value);
+                    }
+                    Some(Key::Merge(span)) => merges.push((value, span)),
+                    None => unreachable!("values only get pushed into maps after their key"),
+                }
+                *nodes += value_nodes;
+            }
+        }
+    }
+}
+
+/// Merges the value of a `<<` key into `map`: keys that are already there win,
+/// as do maps that come first in a list of them.
+fn merge_into<'s>(map: &mut Map<'s>, merge: Value<'s>, span: Span) -> Result<(), MerdeYamlError> {
+    match merge {
+        Value::Map(merged) => {
+            for (key, value) in merged.0 {
+                map.entry(key).or_insert(value);
+            }
+            Ok(())
+        }
+        Value::Array(arr) => arr.into_iter().try_for_each(|merge| match merge {
+            Value::Map(_) => merge_into(map, merge, span),
+            _ => Err(unsupported("`<<` needs a map, or a list of maps", span)),
+        }),
+        _ => Err(unsupported("`<<` needs a map, or a list of maps", span)),
+    }
+}
+
+/// Turns a scalar into a [Value], following its tag if it has one of the core
+/// schema's, or its style otherwise: only plain scalars can be something else
+/// than strings. Other tags are ignored.
+fn resolve<'s>(
+    text: CowStr<'s>,
+    style: ScalarStyle,
+    tag: Option<&Tag>,
+    span: Span,
+) -> Result<Value<'s>, MerdeYamlError> {
+    let expected = match tag {
+        Some(tag) if tag.is_yaml_core_schema() => tag.suffix.as_str(),
+        _ if style == ScalarStyle::Plain => {
+            return Ok(resolve_plain(&text).unwrap_or(Value::Str(text)))
+        }
+        _ => return Ok(Value::Str(text)),
+    };
+    let value = match (expected, resolve_plain(&text)) {
+        ("str", _) => Value::Str(text),
+        ("null", Some(Value::Null))
+        | ("bool", Some(Value::Bool(_)))
+        | ("int", Some(Value::Int(_)))
+        | ("float", Some(Value::Float(_))) => resolve_plain(&text).unwrap(),
+        ("float", Some(Value::Int(i))) => Value::Float(i as f64),
+        ("null" | "bool" | "int" | "float", _) => {
+            return Err(unsupported(
+                &format!("{text:?} is not a valid !!{expected}"),
+                span,
+            ))
+        }
+        (_, resolved) => resolved.unwrap_or(Value::Str(text)),
+    };
+    Ok(value)
+}
+
+/// What a plain scalar stands for, per the YAML 1.2 core schema, if it's not a string.
+pub(crate) fn resolve_plain(text: &str) -> Option<Value<'static>> {
+    Some(match text {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => Value::Float(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => Value::Float(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => Value::Float(f64::NAN),
+        _ => return resolve_number(text),
+    })
+}
+
+fn resolve_number(text: &str) -> Option<Value<'static>> {
+    let all_digits = |s: &str, radix: u32| !s.is_empty() && s.chars().all(|c| c.is_digit(radix));
+
+    if let Some(hex) = text.strip_prefix("0x") {
+        return all_digits(hex, 16)
+            .then(|| i64::from_str_radix(hex, 16).ok().map(Value::Int))
+            .flatten();
+    }
+    if let Some(octal) = text.strip_prefix("0o") {
+        return all_digits(octal, 8)
+            .then(|| i64::from_str_radix(octal, 8).ok().map(Value::Int))
+            .flatten();
+    }
+
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if all_digits(unsigned, 10) {
+        // too big for an i64: keep as much of it as a float can
+        return Some(match text.parse() {
+            Ok(i) => Value::Int(i),
+            Err(_) => Value::Float(text.parse().ok()?),
+        });
+    }
+
+    // [-+]?(\.[0-9]+|[0-9]+(\.[0-9]*)?)([eE][-+]?[0-9]+)?
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((int, frac)) => {
+            (all_digits(int, 10) || int.is_empty())
+                && (all_digits(frac, 10) || frac.is_empty())
+                && !(int.is_empty() && frac.is_empty())
+        }
+        None => all_digits(mantissa, 10),
+    };
+    let exponent_ok =
+        exponent.is_none_or(|e| all_digits(e.strip_prefix(['-', '+']).unwrap_or(e), 10));
+    if mantissa_ok && exponent_ok {
+        text.parse().ok().map(Value::Float)
+    } else {
+        None
+    }
+}
+
+fn unsupported(reason: &str, span: Span) -> MerdeYamlError {
+    MerdeYamlError::Unsupported {
+        reason: reason.to_owned(),
+        line: span.start.line(),
+        column: span.start.col() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, CowStr, Value};
+
+    use super::{resolve_plain, yaml_str_to_values};
+    use crate::MerdeYamlError;
+
+    fn parse(src: &str) -> Value<'_> {
+        let mut documents = yaml_str_to_values(src).unwrap();
+        assert_eq!(documents.len(), 1, "{src}");
+        documents.remove(0)
+    }
+
+    #[test]
+    fn test_core_schema() {
+        for (text, expected) in [
+            ("~", Value::Null),
+            ("", Value::Null),
+            ("True", Value::Bool(true)),
+            ("FALSE", Value::Bool(false)),
+            ("-12", Value::Int(-12)),
+            ("+7", Value::Int(7)),
+            ("0x1F", Value::Int(31)),
+            ("0o17", Value::Int(15)),
+            ("1.5", Value::Float(1.5)),
+            ("-.5e1", Value::Float(-5.0)),
+            ("1.", Value::Float(1.0)),
+            ("2E3", Value::Float(2000.0)),
+            ("-.inf", Value::Float(f64::NEG_INFINITY)),
+            ("99999999999999999999", Value::Float(1e20)),
+        ] {
+            assert_eq!(resolve_plain(text), Some(expected), "{text:?}");
+        }
+        assert!(matches!(resolve_plain(".NaN"), Some(Value::Float(f)) if f.is_nan()));
+        for text in [
+            "yes", "off", "0x", "0xG", "1_000", "1e", ".", "e5", "inf", "NaN", "1.2.3",
+        ] {
+            assert_eq!(resolve_plain(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_documents() {
+        let src = "name: web\nreplicas: 3\nports:\n  - 80\n  - 443\nlabels: {tier: front, 'quoted': \"yes\"}\nempty:\n";
+        assert_eq!(
+            parse(src),
+            value!({
+                "name": "web",
+                "replicas": 3,
+                "ports": [80, 443],
+                "labels": { "tier": "front", "quoted": "yes" },
+                "empty": null
+            })
+        );
+
+        // plain scalars are borrowed, ones that had escapes can't be
+        let value = parse("a: plain text\nb: \"esc\\taped\"\nc: |\n  literal\n");
+        let map = value.as_map().unwrap();
+        assert!(matches!(
+            map.get("a"),
+            Some(Value::Str(CowStr::Borrowed("plain text")))
+        ));
+        assert_eq!(map.get("b"), Some(&Value::from("esc\taped")));
+        assert_eq!(map.get("c"), Some(&Value::from("literal\n")));
+        assert!(map.keys().all(|k| matches!(k, CowStr::Borrowed(_))));
+
+        // quoted scalars without escapes are borrowed too, including after
+        // non-ASCII text (the parser counts offsets in chars)
+        let value = parse("é: ünï\nb: plain\n'q': 'single'\nd: \"double\"\ne: 'it''s'\n");
+        let map = value.as_map().unwrap();
+        for (key, expected) in [
+            ("é", "ünï"),
+            ("b", "plain"),
+            ("q", "single"),
+            ("d", "double"),
+        ] {
+            assert!(
+                matches!(map.get(key), Some(Value::Str(CowStr::Borrowed(s))) if *s == expected),
+                "{key}: {:?}",
+                map.get(key)
+            );
+        }
+        assert!(matches!(map.get("e"), Some(Value::Str(CowStr::Owned(s))) if *s == "it's"));
+        assert!(map.keys().all(|k| matches!(k, CowStr::Borrowed(_))));
+
+        // keys are kept as written, quoted scalars are strings
+        assert_eq!(
+            parse("1.0: a\nnull: b\nv: '12'\nw: !!str 12\nx: !!float 3\n"),
+            value!({ "1.0": "a", "null": "b", "v": "12", "w": "12", "x": 3.0 })
+        );
+
+        assert_eq!(
+            yaml_str_to_values("---\na: 1\n...\n--- 2\n---\n").unwrap(),
+            vec![value!({ "a": 1 }), value!(2), value!(null)]
+        );
+        assert_eq!(yaml_str_to_values("").unwrap(), vec![]);
+        assert_eq!(yaml_str_to_values("# just a comment\n").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_anchors() {
+        let src = "
+defaults: &defaults
+  image: nginx
+  replicas: 1
+tags: &tags [a, b]
+web:
+  <<: *defaults
+  replicas: 3
+  tags: *tags
+both:
+  <<: [{x: 1}, {x: 2, y: 2}]
+\"<<\": literal
+";
+        assert_eq!(
+            parse(src),
+            value!({
+                "defaults": { "image": "nginx", "replicas": 1 },
+                "tags": ["a", "b"],
+                "web": { "image": "nginx", "replicas": 3, "tags": ["a", "b"] },
+                "both": { "x": 1, "y": 2 },
+                "<<": "literal"
+            })
+        );
+
+        // an anchored key can be reused as a key
+        assert_eq!(
+            parse("? &k key\n: 1\nother: {*k : 2}\n"),
+            value!({ "key": 1, "other": { "key": 2 } })
+        );
+        // alias keys are kept as written, like any other key
+        assert_eq!(
+            parse("a: &f 1.0\nb: &n ~\nc: {*f : x, *n : y}\n"),
+            value!({ "a": 1.0, "b": null, "c": { "1.0": "x", "~": "y" } })
+        );
+        assert_eq!(
+            parse("&f 1.0 : a\n? &n ~\n: b\nc: {*f : x, *n : y}\n"),
+            value!({ "1.0": "a", "~": "b", "c": { "1.0": "x", "~": "y" } })
+        );
+
+        let laughs = "a: &a [x, x, x, x, x, x, x, x, x, x]
+b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]
+c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]
+d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]
+e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]
+f: &f [*e, *e, *e, *e, *e, *e, *e, *e, *e, *e]
+g: [*f, *f, *f, *f, *f, *f, *f, *f, *f, *f]
+";
+        let err = yaml_str_to_values(laughs).unwrap_err();
+        assert!(
+            matches!(&err, MerdeYamlError::Unsupported { line: 6, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for (src, expected) in [
+            (
+                "[a]: 1\n",
+                "only scalars can be used as keys (line 1, column 1)",
+            ),
+            (
+                "a: <<\nb:\n  <<: 1\n",
+                "`<<` needs a map, or a list of maps (line 3, column 3)",
+            ),
+            (
+                "a: !!int twelve\n",
+                "\"twelve\" is not a valid !!int (line 1, column 10)",
+            ),
+        ] {
+            let err = yaml_str_to_values(src).unwrap_err();
+            assert_eq!(err.to_string(), format!("Unsupported YAML: {expected}"));
+        }
+
+        let err = yaml_str_to_values("a: [1, 2\nb: 3\n").unwrap_err();
+        assert!(matches!(err, MerdeYamlError::ParseError(_)), "{err}");
+    }
+}
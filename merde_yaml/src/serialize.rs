@@ -0,0 +1,219 @@
+//! Writes [Value] trees as block-style YAML.
+
+use merde_core::{Array, Map, Value};
+
+use crate::parser::resolve_plain;
+
+/// Writes `value` as a YAML document, ending with a newline.
+pub(crate) fn write_document(out: &mut String, value: &Value<'_>) {
+    match value {
+        Value::Map(map) if !map.is_empty() => write_map(out, map, 0),
+        Value::Array(arr) if !arr.is_empty() => write_seq(out, arr, 0),
+        value => {
+            write_inline(out, value);
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes the entries of `map`, the first one where `out` currently is, the
+/// others on their own line, indented by `indent`.
+fn write_map(out: &mut String, map: &Map<'_>, indent: usize) {
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 {
+            pad(out, indent);
+        }
+        write_str(out, key);
+        out.push(':');
+        match value {
+            Value::Map(map) if !map.is_empty() => {
+                out.push('\n');
+                pad(out, indent + 2);
+                write_map(out, map, indent + 2);
+            }
+            Value::Array(arr) if !arr.is_empty() => {
+                out.push('\n');
+                pad(out, indent + 2);
+                write_seq(out, arr, indent + 2);
+            }
+            value => {
+                out.push(' ');
+                write_inline(out, value);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Like [write_map], for the items of `arr`.
+fn write_seq(out: &mut String, arr: &Array<'_>, indent: usize) {
+    for (i, item) in arr.iter().enumerate() {
+        if i > 0 {
+            pad(out, indent);
+        }
+        out.push_str("- ");
+        match item {
+            Value::Map(map) if !map.is_empty() => write_map(out, map, indent + 2),
+            Value::Array(arr) if !arr.is_empty() => write_seq(out, arr, indent + 2),
+            value => {
+                write_inline(out, value);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn pad(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+/// Writes a scalar, or an empty collection.
+fn write_inline(out: &mut String, value: &Value<'_>) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Int(i) => out.push_str(&i.to_string()),
+        Value::Float(f) if f.is_nan() => out.push_str(".nan"),
+        Value::Float(f) if f.is_infinite() => out.push_str(if *f > 0.0 { ".inf" } else { "-.inf" }),
+        // `Debug` always has a `.` or an exponent, so it reads back as a float
+        Value::Float(f) => out.push_str(&format!("{f:?}")),
+        Value::Str(s) => write_str(out, s),
+        Value::Array(_) => out.push_str("[]"),
+        Value::Map(_) => out.push_str("{}"),
+    }
+}
+
+/// Writes `s` as a plain scalar if it would read back as the same string,
+/// double-quoted otherwise.
+fn write_str(out: &mut String, s: &str) {
+    if is_plain_safe(s) {
+        out.push_str(s);
+        return;
+    }
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Conservative: some strings that would be fine plain get quoted anyway.
+fn is_plain_safe(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '/'))
+        && !s.ends_with(' ')
+        && s.chars().all(|c| {
+            c.is_alphanumeric()
+                || matches!(
+                    c,
+                    ' ' | '_' | '-' | '.' | '/' | '(' | ')' | '+' | '=' | '@' | '$'
+                )
+        })
+        && resolve_plain(s).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use merde_core::{value, Value};
+
+    use super::write_document;
+    use crate::parser::yaml_str_to_values;
+
+    fn to_yaml(value: &Value<'_>) -> String {
+        let mut out = String::new();
+        write_document(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn test_block_style() {
+        let value = value!({
+            "web": { "ports": [80, { "host": 8080 }, [1, [2]], []] }
+        });
+        let yaml = to_yaml(&value);
+        assert_eq!(
+            yaml,
+            "web:
+  ports:
+    - 80
+    - host: 8080
+    - - 1
+      - - 2
+    - []
+"
+        );
+        assert_eq!(yaml_str_to_values(&yaml).unwrap(), vec![value]);
+
+        let value = value!({ "a": {}, "b": [{ "c": 1, "d": { "e": null } }] });
+        let yaml = to_yaml(&value);
+        assert_eq!(yaml_str_to_values(&yaml).unwrap(), vec![value]);
+        assert_eq!(to_yaml(&value!([])), "[]\n");
+    }
+
+    #[test]
+    fn test_round_trip_scalars() {
+        let strings = [
+            "plain words",
+            "",
+            " padded ",
+            "true",
+            "12",
+            "1e3",
+            "~",
+            "null",
+            "<<",
+            "- item",
+            "key: value",
+            "a # comment",
+            "'quoted'",
+            "\"double\"",
+            "multi\nline\ttabbed\r\0\u{7}",
+            "back\\slash",
+            "ünïcödé ✓",
+            "*alias",
+            "&anchor",
+            "!tag",
+            "[flow]",
+            "{flow}",
+            "@reserved",
+            "%directive",
+            "---",
+        ];
+        let others = [
+            Value::Null,
+            Value::Bool(false),
+            Value::Int(i64::MIN),
+            Value::Float(1.0),
+            Value::Float(-0.0),
+            Value::Float(1e300),
+            Value::Float(f64::INFINITY),
+            Value::Float(f64::NEG_INFINITY),
+        ];
+        for value in strings.into_iter().map(Value::from).chain(others) {
+            let yaml = to_yaml(&value);
+            assert_eq!(
+                yaml_str_to_values(&yaml).unwrap(),
+                vec![value.clone()],
+                "{yaml}"
+            );
+
+            // as a key and in a sequence too
+            if let Value::Str(s) = &value {
+                let nested = value!({ (s.clone()): [(s.clone())] });
+                let yaml = to_yaml(&nested);
+                assert_eq!(yaml_str_to_values(&yaml).unwrap(), vec![nested], "{yaml}");
+            }
+        }
+
+        let yaml = to_yaml(&Value::Float(f64::NAN));
+        assert!(matches!(yaml_str_to_values(&yaml).unwrap()[..], [Value::Float(f)] if f.is_nan()));
+    }
+}